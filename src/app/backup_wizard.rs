@@ -0,0 +1,387 @@
+use crate::{
+    app::{
+        compression_kind::CompressionKind, compression_level::CompressionLevel,
+        hash_algorithm::HashAlgorithm, settings::Settings,
+    },
+    backup::{disk::BackupDisk, kind::BackupKind, metadata::BackupMetadata},
+    ui::input::{
+        boolfield::BoolField, enumfield::EnumField, enumvariants::Choice, field::Field,
+        inputfield::InputField, inputform::InputForm, stringfield::StringField, value::Values,
+    },
+};
+use chrono::Local;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Disk choice for backups written to a plain path.
+const NO_DISK: &str = "None";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Sources,
+    Target,
+    Options,
+    Summary,
+}
+
+/// What submitting a step of the wizard leads to.
+pub enum WizardOutcome {
+    /// The next step, or the same one again with the reason it was not
+    /// accepted.
+    Step(InputForm),
+    /// The summary was confirmed. Backups without a disk go to the disk
+    /// their target path is on.
+    Start {
+        metadata: Box<BackupMetadata>,
+        disk: Option<BackupDisk>,
+    },
+    /// The summary was not confirmed.
+    Discarded,
+}
+
+/// Collects a one-off backup over several forms: its sources, kind and
+/// target, compression, hashing and note, and a summary to confirm. Values
+/// are kept as entered so going back a step shows them again.
+pub struct BackupWizard {
+    step: Step,
+    name: String,
+    sources: String,
+    kind: BackupKind,
+    disks: Vec<BackupDisk>,
+    disk: Option<Uuid>,
+    target: String,
+    compression: CompressionKind,
+    compression_level: CompressionLevel,
+    checksum: bool,
+    hash_algorithm: HashAlgorithm,
+    note: String,
+}
+
+impl BackupWizard {
+    pub fn new(settings: &Settings, disks: &[BackupDisk], sources: &[PathBuf]) -> Self {
+        let compression = if settings.compression_enable() {
+            settings.compression_kind().clone()
+        } else {
+            CompressionKind::None
+        };
+
+        Self {
+            step: Step::Sources,
+            name: String::new(),
+            sources: sources
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", "),
+            kind: BackupKind::Full,
+            disks: disks.to_vec(),
+            disk: None,
+            target: settings.default_backup_dir().to_string_lossy().to_string(),
+            compression,
+            compression_level: settings.compression_level().clone(),
+            checksum: false,
+            hash_algorithm: settings.hash_algorithm().clone(),
+            note: String::new(),
+        }
+    }
+
+    /// The form of the current step.
+    pub fn form(&self) -> InputForm {
+        match self.step {
+            Step::Sources => InputForm::new(
+                "NEW BACKUP (1/4): SOURCES",
+                vec![
+                    Field::String(InputField::new_with_value(
+                        "name",
+                        StringField::from(self.name.clone()),
+                    )),
+                    Field::String(InputField::new_with_value(
+                        "sources",
+                        StringField::from(self.sources.clone()),
+                    )),
+                ]
+                .into(),
+            ),
+            Step::Target => InputForm::new(
+                "NEW BACKUP (2/4): TARGET",
+                vec![
+                    Field::Enum(InputField::new_with_value(
+                        "kind",
+                        EnumField::from(self.kind),
+                    )),
+                    Field::Enum(InputField::new_with_value(
+                        "disk",
+                        EnumField::from(Choice::new(
+                            self.disk_choices(),
+                            &self
+                                .selected_disk()
+                                .map(disk_choice)
+                                .unwrap_or_else(|| NO_DISK.to_string()),
+                        )),
+                    )),
+                    Field::String(InputField::new_with_value(
+                        "target",
+                        StringField::from(self.target.clone()),
+                    )),
+                ]
+                .into(),
+            ),
+            Step::Options => InputForm::new(
+                "NEW BACKUP (3/4): OPTIONS",
+                vec![
+                    Field::Enum(InputField::new_with_value(
+                        "compression",
+                        EnumField::from(self.compression.clone()),
+                    )),
+                    Field::Enum(InputField::new_with_value(
+                        "compression_level",
+                        EnumField::from(self.compression_level.clone()),
+                    )),
+                    Field::Bool(InputField::new_with_value(
+                        "checksum",
+                        BoolField::from(self.checksum),
+                    )),
+                    Field::Enum(InputField::new_with_value(
+                        "hash_algorithm",
+                        EnumField::from(self.hash_algorithm.clone()),
+                    )),
+                    Field::String(InputField::new_with_value(
+                        "note",
+                        StringField::from(self.note.clone()),
+                    )),
+                ]
+                .into(),
+            ),
+            Step::Summary => {
+                let summary = [
+                    ("name", self.name.clone()),
+                    ("sources", self.source_paths().len().to_string()),
+                    ("kind", self.kind.to_string()),
+                    ("target", self.target_dir().display().to_string()),
+                    (
+                        "compression",
+                        if self.compression == CompressionKind::None {
+                            self.compression.to_string()
+                        } else {
+                            format!(
+                                "{} ({})",
+                                self.compression.to_string(),
+                                self.compression_level.to_string()
+                            )
+                        },
+                    ),
+                    (
+                        "checksum",
+                        if self.checksum {
+                            self.hash_algorithm.to_string()
+                        } else {
+                            "None".to_string()
+                        },
+                    ),
+                    ("note", self.note.clone()),
+                ];
+
+                let lines = summary
+                    .into_iter()
+                    .map(|(label, value)| format!("{}: {}", label, value))
+                    .collect();
+
+                InputForm::new(
+                    "NEW BACKUP (4/4): SUMMARY",
+                    vec![Field::Bool(InputField::new_with_value(
+                        "confirm",
+                        BoolField::from(true),
+                    ))]
+                    .into(),
+                )
+                .with_lines(lines)
+            }
+        }
+    }
+
+    /// Takes the values of the current step and moves on if they are valid.
+    pub fn submit(&mut self, values: &Values) -> WizardOutcome {
+        self.store(values);
+
+        if let Err(reason) = self.validate() {
+            return WizardOutcome::Step(self.form().with_error(reason));
+        }
+
+        self.step = match self.step {
+            Step::Sources => Step::Target,
+            Step::Target => Step::Options,
+            Step::Options => Step::Summary,
+            Step::Summary if values.get_bool("confirm").unwrap_or(false) => {
+                return WizardOutcome::Start {
+                    metadata: Box::new(self.metadata()),
+                    disk: self.selected_disk().cloned(),
+                };
+            }
+            Step::Summary => return WizardOutcome::Discarded,
+        };
+
+        WizardOutcome::Step(self.form())
+    }
+
+    /// Goes back a step, keeping what was entered in the current one.
+    /// Returns `None` on the first step, which leaves the wizard.
+    pub fn back(&mut self, values: &Values) -> Option<InputForm> {
+        self.store(values);
+
+        self.step = match self.step {
+            Step::Sources => return None,
+            Step::Target => Step::Sources,
+            Step::Options => Step::Target,
+            Step::Summary => Step::Options,
+        };
+
+        Some(self.form())
+    }
+
+    fn store(&mut self, values: &Values) {
+        match self.step {
+            Step::Sources => {
+                self.name = values.get_string("name").unwrap_or_default();
+                self.sources = values.get_string("sources").unwrap_or_default();
+            }
+            Step::Target => {
+                self.kind = BackupKind::from(values.get_enum("kind").unwrap_or_default().as_str());
+                let disk = values.get_enum("disk").unwrap_or_default();
+                self.disk = self
+                    .disks
+                    .iter()
+                    .find(|candidate| disk_choice(candidate) == disk)
+                    .map(|disk| disk.id());
+                self.target = values.get_string("target").unwrap_or_default();
+            }
+            Step::Options => {
+                self.compression = CompressionKind::from(
+                    values.get_enum("compression").unwrap_or_default().as_str(),
+                );
+                self.compression_level = CompressionLevel::from(
+                    values
+                        .get_enum("compression_level")
+                        .unwrap_or_default()
+                        .as_str(),
+                );
+                self.checksum = values.get_bool("checksum").unwrap_or(false);
+                self.hash_algorithm = HashAlgorithm::from(
+                    values
+                        .get_enum("hash_algorithm")
+                        .unwrap_or_default()
+                        .as_str(),
+                );
+                self.note = values.get_string("note").unwrap_or_default();
+            }
+            Step::Summary => {}
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self.step {
+            Step::Sources => {
+                let name = self.name.trim();
+                if name.is_empty() {
+                    return Err("A name is required".to_string());
+                }
+                if name.contains('/') {
+                    return Err("The name cannot contain /".to_string());
+                }
+
+                let sources = self.source_paths();
+                if sources.is_empty() {
+                    return Err("At least one source is required".to_string());
+                }
+                if let Some(source) = sources.iter().find(|source| !source.exists()) {
+                    return Err(format!("{} does not exist", source.display()));
+                }
+            }
+            Step::Target => {
+                if matches!(
+                    self.kind,
+                    BackupKind::Incremental | BackupKind::Differential
+                ) {
+                    return Err(format!(
+                        "{} backups build on a profile, create one with p in the browser",
+                        self.kind.to_string()
+                    ));
+                }
+                if self.target.trim().is_empty() {
+                    return Err("A target is required".to_string());
+                }
+                if self.selected_disk().is_none() && !Path::new(self.target.trim()).is_absolute() {
+                    return Err("Without a disk the target has to be an absolute path".to_string());
+                }
+            }
+            Step::Options => match (self.kind, &self.compression) {
+                (BackupKind::Compressed, CompressionKind::None) => {
+                    return Err("Compressed backups need a compression".to_string());
+                }
+                (BackupKind::Compressed, _) | (_, CompressionKind::None) => {}
+                (kind, _) => {
+                    return Err(format!(
+                        "{} backups are not compressed, pick the Compressed kind",
+                        kind.to_string()
+                    ));
+                }
+            },
+            Step::Summary => {}
+        }
+
+        Ok(())
+    }
+
+    fn disk_choices(&self) -> Vec<String> {
+        std::iter::once(NO_DISK.to_string())
+            .chain(self.disks.iter().map(disk_choice))
+            .collect()
+    }
+
+    fn selected_disk(&self) -> Option<&BackupDisk> {
+        self.disk
+            .and_then(|id| self.disks.iter().find(|disk| disk.id() == id))
+    }
+
+    fn source_paths(&self) -> Vec<PathBuf> {
+        self.sources
+            .split(',')
+            .map(str::trim)
+            .filter(|source| !source.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    /// Directory the backup is created in: the target on the selected disk,
+    /// or the target itself without one.
+    fn target_dir(&self) -> PathBuf {
+        let target = self.target.trim();
+        match self.selected_disk() {
+            Some(disk) => disk.mount_path().join(target.trim_start_matches('/')),
+            None => PathBuf::from(target),
+        }
+    }
+
+    fn metadata(&self) -> BackupMetadata {
+        let name = self.name.trim();
+        let target_path = self.target_dir().join(format!(
+            "{}_{}",
+            name,
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+
+        let mut metadata = BackupMetadata::new(name, self.kind, self.source_paths(), target_path);
+        metadata.set_compression(self.compression.clone(), self.compression_level.clone());
+        metadata.set_hash_algorithm(self.checksum.then(|| self.hash_algorithm.clone()));
+        let note = self.note.trim();
+        metadata.set_note((!note.is_empty()).then(|| note.to_string()));
+
+        metadata
+    }
+}
+
+/// Disk choices carry part of the id, as labels of different disks can be
+/// the same.
+fn disk_choice(disk: &BackupDisk) -> String {
+    let id = disk.id().to_string();
+    format!("{} ({})", disk.label(), &id[..8])
+}
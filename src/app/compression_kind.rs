@@ -1,8 +1,9 @@
 use crate::ui::input::enumvariants::EnumVariants;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub enum CompressionKind {
+    #[default]
     None,
     Zip,
 }
@@ -33,6 +34,17 @@ impl EnumVariants for CompressionKind {
     }
 }
 
+impl From<&str> for CompressionKind {
+    fn from(value: &str) -> Self {
+        match value {
+            "None" => CompressionKind::None,
+            "Zip" => CompressionKind::Zip,
+            _ => CompressionKind::None,
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for CompressionKind {
     fn to_string(&self) -> String {
         match self {
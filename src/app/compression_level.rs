@@ -1,8 +1,9 @@
 use crate::ui::input::enumvariants::EnumVariants;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
 pub enum CompressionLevel {
+    #[default]
     Default,
     Best,
 }
@@ -22,8 +23,8 @@ impl EnumVariants for CompressionLevel {
 
     fn from_string(&self, s: String) -> Box<dyn EnumVariants> {
         Box::new(match s.as_str() {
-            "None" => CompressionLevel::Default,
-            "Zip" => CompressionLevel::Best,
+            "Default" => CompressionLevel::Default,
+            "Best" => CompressionLevel::Best,
             _ => CompressionLevel::Default,
         })
     }
@@ -33,6 +34,17 @@ impl EnumVariants for CompressionLevel {
     }
 }
 
+impl From<&str> for CompressionLevel {
+    fn from(value: &str) -> Self {
+        match value {
+            "Default" => CompressionLevel::Default,
+            "Best" => CompressionLevel::Best,
+            _ => CompressionLevel::Default,
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for CompressionLevel {
     fn to_string(&self) -> String {
         match self {
@@ -0,0 +1,393 @@
+use crate::{
+    app::{compression_kind::CompressionKind, settings::Settings},
+    backup::{
+        catalog::Catalog,
+        delete::{DeletePlan, DeleteReport, DeleteTask},
+        discovery::DiskDiscovery,
+        disk::BackupDisk,
+        kind::BackupKind,
+        metadata::BackupMetadata,
+        preflight::FreeSpacePolicy,
+        rotation,
+        scheduler::Scheduler,
+        synthetic::{SyntheticPlan, SyntheticTask},
+        task::BackupTask,
+        watcher::DiskWatcher,
+    },
+    config::Config,
+    error::SanupResult,
+};
+use chrono::Utc;
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// What happened during a tick that the front end has to react to.
+#[derive(Default)]
+pub struct TickOutcome {
+    /// Disks mounted since the last tick.
+    pub mounted: Vec<BackupDisk>,
+    /// Watched disks whose profile waits for the user to confirm the run.
+    pub to_confirm: Vec<BackupDisk>,
+    /// Deletes that finished since the last tick.
+    pub deleted: Vec<DeleteReport>,
+    /// Synthetic full backups that finished since the last tick.
+    pub synthesized: Vec<BackupMetadata>,
+    /// Deletes and synthetic backups that failed since the last tick, with
+    /// the backup they were about and the reason.
+    pub failed: Vec<(BackupMetadata, String)>,
+    /// Whether the catalog changed since the last tick.
+    pub catalog_changed: bool,
+}
+
+/// Runs backups, scheduled profiles and watched disks. Shared by the TUI and
+/// the daemon, which only differ in how they ask before running a watched
+/// disk's profile.
+pub struct Engine {
+    backups: Vec<BackupTask>,
+    deletes: Vec<DeleteTask>,
+    synthetics: Vec<SyntheticTask>,
+    catalog: Catalog,
+    catalog_path: PathBuf,
+    scheduler: Scheduler,
+    watcher: DiskWatcher,
+    /// Whether schedules and watched disks are handled here. Off while the
+    /// TUI is attached to a daemon that already does it.
+    automation: bool,
+}
+
+impl Engine {
+    pub fn new<P: AsRef<Path>>(catalog_path: P) -> Self {
+        let catalog_path = catalog_path.as_ref().to_path_buf();
+        let catalog = Catalog::load(&catalog_path).unwrap_or_else(|err| {
+            warn!("Failed to load backup catalog: {}", err);
+            Catalog::default()
+        });
+
+        Self {
+            backups: Vec::new(),
+            deletes: Vec::new(),
+            synthetics: Vec::new(),
+            scheduler: Scheduler::new(&catalog),
+            catalog,
+            catalog_path,
+            watcher: DiskWatcher::new(DiskDiscovery::default()),
+            automation: true,
+        }
+    }
+
+    pub fn backups(&self) -> &[BackupTask] {
+        &self.backups
+    }
+
+    pub fn backup(&self, id: Uuid) -> Option<&BackupTask> {
+        self.backups.iter().find(|backup| backup.id() == id)
+    }
+
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    pub fn automation(&self) -> bool {
+        self.automation
+    }
+
+    pub fn set_automation(&mut self, automation: bool) {
+        self.automation = automation;
+    }
+
+    pub fn tick(&mut self, settings: &Settings) -> TickOutcome {
+        let mut outcome = TickOutcome::default();
+
+        let mut finished = Vec::new();
+        for backup in &mut self.backups {
+            if backup.update() {
+                finished.push(backup.metadata().clone());
+            }
+        }
+        if !finished.is_empty() {
+            self.update_catalog(|catalog| {
+                for metadata in finished {
+                    catalog.record(metadata);
+                }
+            });
+            outcome.catalog_changed = true;
+        }
+
+        let (done, running) = self.deletes.drain(..).partition(DeleteTask::is_finished);
+        self.deletes = running;
+        for task in done {
+            let metadata = task.metadata().clone();
+            match task.join() {
+                Ok(report) => {
+                    self.update_catalog(|catalog| {
+                        catalog.remove(report.deleted.id());
+                        for metadata in &report.merged {
+                            catalog.record(metadata.clone());
+                        }
+                    });
+                    outcome.deleted.push(report);
+                }
+                Err(err) => {
+                    warn!("Failed to delete backup {}: {}", metadata.name(), err);
+                    outcome
+                        .failed
+                        .push((metadata, format!("Delete failed: {}", err)));
+                }
+            }
+            outcome.catalog_changed = true;
+        }
+
+        let (done, running) = self
+            .synthetics
+            .drain(..)
+            .partition(SyntheticTask::is_finished);
+        self.synthetics = running;
+        for task in done {
+            let tip = task.tip().clone();
+            match task.join() {
+                Ok(metadata) => {
+                    self.update_catalog(|catalog| catalog.record(metadata.clone()));
+                    outcome.synthesized.push(metadata);
+                    outcome.catalog_changed = true;
+                }
+                Err(err) => outcome
+                    .failed
+                    .push((tip, format!("Synthetic full backup failed: {}", err))),
+            }
+        }
+
+        if !self.automation {
+            return outcome;
+        }
+
+        for profile_id in self.scheduler.due(settings.profiles()) {
+            if self.is_profile_running(profile_id) {
+                info!(
+                    "Skipping scheduled run, profile {} is still running",
+                    profile_id
+                );
+            } else {
+                self.run_profile(settings, profile_id);
+            }
+        }
+
+        match self.watcher.poll() {
+            Ok(disks) => {
+                for disk in &disks {
+                    match settings.watched_disk_for(disk) {
+                        Some(watched_disk) if watched_disk.auto_run() => {
+                            self.run_watched_disk(settings, disk.clone())
+                        }
+                        Some(_) => outcome.to_confirm.push(disk.clone()),
+                        None => info!(
+                            "Disk {} mounted at {}",
+                            disk.label(),
+                            disk.mount_path().display()
+                        ),
+                    }
+                }
+                outcome.mounted = disks;
+            }
+            Err(err) => debug!("Failed to poll disks: {}", err),
+        }
+
+        outcome
+    }
+
+    /// Whether backup `id` is being deleted.
+    pub fn is_deleting(&self, id: Uuid) -> bool {
+        self.deletes.iter().any(|task| task.metadata().id() == id)
+    }
+
+    /// Deletes a backup in the background. With `merge`, backups depending
+    /// on it take over the files they need first, otherwise they make the
+    /// delete fail.
+    pub fn delete_backup(&mut self, id: Uuid, merge: bool) -> SanupResult<()> {
+        let disks = DiskDiscovery::default().disks().unwrap_or_default();
+        let plan = DeletePlan::new(&self.catalog, id, &disks, merge)?;
+        info!("Deleting backup {}", plan.metadata().name());
+
+        self.deletes.push(DeleteTask::spawn(plan)?);
+        Ok(())
+    }
+
+    /// Consolidates backup `id` and the chain it builds on into a new full
+    /// backup next to it, in the background. The old chain stays until a
+    /// prune or delete removes it.
+    pub fn synthesize(&mut self, id: Uuid) -> SanupResult<()> {
+        let disks = DiskDiscovery::default().disks().unwrap_or_default();
+        let plan = SyntheticPlan::new(&self.catalog, id, &disks)?;
+        info!("Synthesizing a full backup from {}", plan.tip().name());
+
+        self.synthetics.push(SyntheticTask::spawn(plan)?);
+        Ok(())
+    }
+
+    /// Applies `update` to the catalog on disk and saves it. The catalog is
+    /// read again first, as a daemon or another sanup may have changed it.
+    fn update_catalog<F: FnOnce(&mut Catalog)>(&mut self, update: F) {
+        match Catalog::load(&self.catalog_path) {
+            Ok(catalog) => self.catalog = catalog,
+            Err(err) => warn!("Failed to reload backup catalog: {}", err),
+        }
+
+        update(&mut self.catalog);
+        if let Err(err) = self.catalog.save(&self.catalog_path) {
+            warn!("Failed to save backup catalog: {}", err);
+        }
+    }
+
+    /// Starts a backup in the background. When the settings ask for it, the
+    /// worker checks free space on the target before it copies anything, so a
+    /// backup that does not fit shows up as started and then fails.
+    pub fn start_backup(&mut self, settings: &Settings, metadata: BackupMetadata) {
+        let target = metadata.target_path().to_path_buf();
+        let disk = BackupDisk::from_path(&target)
+            .unwrap_or_else(|_| BackupDisk::new(&target.to_string_lossy(), &target));
+
+        self.start_backup_on(settings, metadata, None, disk);
+    }
+
+    /// Starts a backup to `disk`, storing only the changes against `parent`
+    /// when there is one.
+    pub fn start_backup_on(
+        &mut self,
+        settings: &Settings,
+        metadata: BackupMetadata,
+        parent: Option<BackupMetadata>,
+        disk: BackupDisk,
+    ) {
+        let mut task = BackupTask::new(metadata);
+        if let Some(parent) = parent {
+            let ancestors = self.catalog.chain(&parent);
+            task.set_parent(parent, ancestors);
+        }
+        if let Err(err) = task.start(
+            disk,
+            FreeSpacePolicy::from_settings(settings),
+            settings.verify_after_backup(),
+        ) {
+            warn!("Failed to start backup {}: {}", task.metadata().name(), err);
+        }
+
+        self.backups.push(task);
+    }
+
+    pub fn run_watched_disk(&mut self, settings: &Settings, disk: BackupDisk) {
+        let Some(profile_id) = settings
+            .watched_disk_for(&disk)
+            .and_then(|watched_disk| watched_disk.backup_profile())
+        else {
+            warn!("Watched disk {} has no backup profile", disk.label());
+            return;
+        };
+
+        self.run_profile_on(settings, profile_id, disk);
+    }
+
+    fn is_profile_running(&self, profile_id: Uuid) -> bool {
+        self.backups.iter().any(|backup| {
+            backup.metadata().profile_id() == Some(profile_id) && !backup.status().is_finished()
+        })
+    }
+
+    /// Runs a profile on whichever of its rotation disks is mounted, or on
+    /// its target path when it does not rotate.
+    pub fn run_profile(&mut self, settings: &Settings, profile_id: Uuid) {
+        let Some(profile) = settings.profile(profile_id) else {
+            warn!("No backup profile with id {}", profile_id);
+            return;
+        };
+
+        let disk = if profile.target_disks().is_empty() {
+            let target = profile.target_path();
+            BackupDisk::from_path(target)
+                .unwrap_or_else(|_| BackupDisk::new(&target.to_string_lossy(), target))
+        } else {
+            let disks = DiskDiscovery::default().disks().unwrap_or_default();
+            match rotation::pick_disk(profile, &disks, &self.catalog) {
+                Some(disk) => disk.clone(),
+                None => {
+                    warn!("None of the disks of profile {} is mounted", profile.name());
+                    return;
+                }
+            }
+        };
+
+        self.run_profile_on(settings, profile_id, disk);
+    }
+
+    /// Runs a profile on `disk`. Incremental and differential runs build on
+    /// the chain of the profile on that disk and fall back to a full backup
+    /// when there is nothing to build on yet.
+    pub fn run_profile_on(&mut self, settings: &Settings, profile_id: Uuid, disk: BackupDisk) {
+        let Some(profile) = settings.profile(profile_id) else {
+            warn!("No backup profile with id {}", profile_id);
+            return;
+        };
+
+        let target_dir = profile
+            .target_on(&disk)
+            .or_else(|| {
+                settings
+                    .watched_disk_for(&disk)
+                    .map(|watched_disk| watched_disk.target_on(&disk, profile.target_path()))
+            })
+            .unwrap_or_else(|| profile.target_path().to_path_buf());
+
+        let mut metadata = profile.to_metadata_in(target_dir);
+        if profile.kind() == BackupKind::Compressed {
+            // Zip is the only compression there is, whether or not it is
+            // enabled by default for one-off backups.
+            metadata.set_compression(CompressionKind::Zip, settings.compression_level().clone());
+        }
+        let parent = self
+            .catalog
+            .parent_for(profile.id(), profile.kind(), disk.id())
+            .cloned();
+        if parent.is_none()
+            && matches!(
+                profile.kind(),
+                BackupKind::Incremental | BackupKind::Differential
+            )
+        {
+            info!(
+                "No backup of profile {} to build on found on disk {}, running a full backup",
+                profile.name(),
+                disk.label()
+            );
+            metadata.set_kind(BackupKind::Full);
+        }
+
+        info!(
+            "Running profile {} on disk {}",
+            profile.name(),
+            disk.label()
+        );
+        self.start_backup_on(settings, metadata, parent, disk);
+    }
+
+    /// Warns about rotation disks that went too long without a backup.
+    pub fn warn_overdue_disks(&self, settings: &Settings) {
+        let now = Utc::now();
+
+        for profile in settings.profiles() {
+            for disk in rotation::overdue_disks(profile, &self.catalog, now) {
+                match disk.last_backup_at {
+                    Some(last_backup_at) => warn!(
+                        "Disk {} of profile {} was last backed up {} days ago",
+                        disk.label,
+                        profile.name(),
+                        (now - last_backup_at).num_days()
+                    ),
+                    None => warn!(
+                        "Disk {} of profile {} was never backed up",
+                        disk.label,
+                        profile.name()
+                    ),
+                }
+            }
+        }
+    }
+}
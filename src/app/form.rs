@@ -0,0 +1,13 @@
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SanupForm {
+    CreateBackup,
+    FilterPreview,
+    ProfileRules,
+    NewProfile,
+    WatchDisk,
+    AddTargetDisk,
+    WatchedDiskMounted,
+    DeleteBackup,
+    FilterBackups,
+    SearchLogs,
+}
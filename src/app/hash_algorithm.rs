@@ -31,6 +31,16 @@ impl EnumVariants for HashAlgorithm {
     }
 }
 
+impl From<&str> for HashAlgorithm {
+    fn from(value: &str) -> Self {
+        match value {
+            "Sha256" => HashAlgorithm::Sha256,
+            _ => HashAlgorithm::Sha256,
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for HashAlgorithm {
     fn to_string(&self) -> String {
         match self {
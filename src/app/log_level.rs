@@ -1,4 +1,5 @@
 use crate::ui::input::enumvariants::EnumVariants;
+use log::LevelFilter;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -20,7 +21,10 @@ impl EnumVariants for LogLevel {
     }
 
     fn variants(&self) -> Vec<String> {
-        [""].iter().map(|s| s.to_string()).collect()
+        ["Error", "Warn", "Info", "Debug", "Trace"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
     }
 
     fn from_string(&self, s: String) -> Box<dyn EnumVariants> {
@@ -39,6 +43,20 @@ impl EnumVariants for LogLevel {
     }
 }
 
+impl From<&str> for LogLevel {
+    fn from(value: &str) -> Self {
+        match value {
+            "Error" => LogLevel::Error,
+            "Warn" => LogLevel::Warn,
+            "Info" => LogLevel::Info,
+            "Debug" => LogLevel::Debug,
+            "Trace" => LogLevel::Trace,
+            _ => LogLevel::Info,
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for LogLevel {
     fn to_string(&self) -> String {
         match self {
@@ -51,3 +69,15 @@ impl ToString for LogLevel {
         .to_string()
     }
 }
+
+impl From<&LogLevel> for LevelFilter {
+    fn from(value: &LogLevel) -> Self {
+        match value {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
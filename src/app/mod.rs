@@ -1,10 +1,14 @@
+pub mod backup_wizard;
 pub mod compression_kind;
 pub mod compression_level;
+pub mod engine;
 pub mod focus;
+pub mod form;
 pub mod hash_algorithm;
 pub mod log_level;
 pub mod sanup;
 pub mod settings;
+pub mod status_filter;
 pub mod tabs;
 pub mod theme;
 pub mod watched_disk;
@@ -1,24 +1,177 @@
 use crate::{
-    app::{focus::SanupFocus, settings::Settings, tabs::SanupTabs, theme::Theme},
-    backup::task::BackupTask,
-    ui::input::{
-        enumfield::EnumField, field::Field, inputfield::InputField, inputform::InputForm,
-        inputlist::InputList,
+    app::{
+        backup_wizard::{BackupWizard, WizardOutcome},
+        engine::Engine,
+        focus::SanupFocus,
+        form::SanupForm,
+        settings::Settings,
+        status_filter::StatusFilter,
+        tabs::SanupTabs,
+        watched_disk::WatchedDisk,
+    },
+    backup::{
+        catalog::Catalog,
+        delete,
+        discovery::DiskDiscovery,
+        disk::BackupDisk,
+        filter::BackupFilter,
+        frequency::Frequency,
+        kind::BackupKind,
+        profile::BackupProfile,
+        schedule::{Schedule, TimeWindow, parse_time},
+    },
+    config::Config,
+    daemon::{
+        client::DaemonClient,
+        protocol::{JobStatus, Request, Response, default_socket_path},
+    },
+    error::{SanupError, SanupResult},
+    logger::SanupLogger,
+    ui::{
+        backupview::{BackupView, HistoryFilter},
+        diskview::DiskView,
+        filebrowser::FileBrowser,
+        filterpreview::FilterPreviewView,
+        format_bytes,
+        input::{
+            boolfield::BoolField, enumfield::EnumField, field::Field, inputfield::InputField,
+            inputform::InputForm, inputlist::InputList, integerfield::IntegerField,
+            stringfield::StringField, value::Values,
+        },
+        logview::LogView,
+        processview::ProcessView,
     },
 };
+use chrono::{NaiveDate, Weekday};
+use log::{error, info, warn};
 use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+const DAEMON_POLL_RATE: Duration = Duration::from_secs(1);
 
 pub struct Sanup {
     pub title: &'static str,
     pub focus: SanupFocus,
     pub tabs: SanupTabs,
-    pub backups: Vec<BackupTask>,
+    pub engine: Engine,
     pub settings: Settings,
+    pub settings_path: PathBuf,
+    /// Why the settings file did not load. Nothing is saved over it then, so
+    /// the defaults used instead never replace what is in it.
+    pub settings_error: Option<String>,
+    pub form: SanupForm,
     pub input_form: InputForm,
     pub input_list: InputList,
+    pub filter_preview: Option<FilterPreviewView>,
+    /// The create-backup wizard while its forms are open.
+    pub wizard: Option<BackupWizard>,
+    pub browser: FileBrowser,
+    pub disks: DiskView,
+    pub backups: BackupView,
+    pub processes: ProcessView,
+    pub logs: LogView,
+    /// Watched disks that were plugged in and wait for the user to confirm
+    /// running their profile.
+    pub mounted_disks: VecDeque<BackupDisk>,
+    /// The daemon this TUI is attached to. While attached, the daemon runs
+    /// schedules and watched disks and its jobs are shown next to ours.
+    pub daemon: Option<DaemonClient>,
+    pub remote_jobs: Vec<JobStatus>,
+    pub last_daemon_poll: Option<Instant>,
 }
 
 impl Sanup {
+    pub fn on_tick(&mut self) {
+        let outcome = self.engine.tick(&self.settings);
+        if !outcome.mounted.is_empty() {
+            self.disks.refresh();
+            self.backups.set_labels(&self.settings, self.disks.disks());
+        }
+        self.mounted_disks.extend(outcome.to_confirm);
+        for report in &outcome.deleted {
+            let mut notice = format!(
+                "Deleted {}, reclaimed {}",
+                report.deleted.name(),
+                format_bytes(report.reclaimed_bytes)
+            );
+            if !report.merged.is_empty() {
+                notice.push_str(&format!(", merged into {} backups", report.merged.len()));
+            }
+            self.backups.set_notice(notice);
+        }
+        for metadata in &outcome.synthesized {
+            self.backups.set_notice(format!(
+                "Synthesized full backup {} with {} files",
+                metadata.target_path().display(),
+                metadata.file_count()
+            ));
+        }
+        for (metadata, reason) in &outcome.failed {
+            self.backups
+                .set_notice(format!("{}: {}", metadata.name(), reason));
+        }
+        if outcome.catalog_changed {
+            self.backups.refresh(self.engine.catalog());
+        }
+
+        self.poll_daemon();
+        self.processes.refresh(self.jobs(), self.daemon.is_some());
+        self.logs.refresh();
+
+        if let Some(filter_preview) = &mut self.filter_preview {
+            filter_preview.update();
+        }
+
+        self.browser.update();
+
+        if !self.focus.is_inputform()
+            && let Some(disk) = self.mounted_disks.front()
+        {
+            self.prompt_watched_disk(disk.clone());
+        }
+    }
+
+    /// Jobs started by this TUI followed by the ones running in the daemon.
+    pub fn jobs(&self) -> Vec<JobStatus> {
+        self.engine
+            .backups()
+            .iter()
+            .map(JobStatus::from)
+            .chain(self.remote_jobs.iter().cloned())
+            .collect()
+    }
+
+    /// Fetches the jobs of the attached daemon. Losing the daemon hands
+    /// schedules and watched disks back to this TUI.
+    fn poll_daemon(&mut self) {
+        let Some(daemon) = &mut self.daemon else {
+            return;
+        };
+        if self
+            .last_daemon_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < DAEMON_POLL_RATE)
+        {
+            return;
+        }
+        self.last_daemon_poll = Some(Instant::now());
+
+        match daemon.request(&Request::Status) {
+            Ok(Response::Status(jobs)) => self.remote_jobs = jobs,
+            Ok(response) => warn!("Unexpected daemon response: {:?}", response),
+            Err(err) => {
+                warn!("Lost connection to the daemon: {}", err);
+                self.daemon = None;
+                self.remote_jobs.clear();
+                self.engine.set_automation(true);
+            }
+        }
+    }
+
     pub fn on_key(&mut self, key: KeyEvent) {
         match self.focus {
             SanupFocus::Tabs => {
@@ -26,64 +179,921 @@ impl Sanup {
                     match c {
                         'l' => self.tabs.next(),
                         'h' => self.tabs.prev(),
-                        'j' => {
-                            if self.focus.is_tabs() {
-                                self.focus.to_body();
-                            }
-                        }
-                        'c' => {
-                            if self.tabs.is_backups() {
-                                self.input_form = InputForm::new(
-                                    "TEST INPUTFORM",
-                                    vec![
-                                        Field::Bool(InputField::new("BOOL")),
-                                        Field::Integer(InputField::new("INTEGER")),
-                                        Field::Float(InputField::new("FLOAT")),
-                                        Field::String(InputField::new("STRING")),
-                                        Field::Enum(InputField::new_with_value(
-                                            "ENUM",
-                                            EnumField::from(Theme::Dark),
-                                        )),
-                                    ]
-                                    .into(),
-                                );
-                                self.focus.to_inputform();
+                        'j' if self.focus.is_tabs() => {
+                            self.focus.to_body();
+                            if self.tabs.is_settings() {
+                                self.input_list.activate();
                             }
                         }
+                        'c' if self.tabs.is_backups() => self.open_backup_wizard(),
                         _ => {}
                     }
                 }
             }
             SanupFocus::Body => {
-                if let KeyCode::Char('k') = key.code {
+                if self.tabs.is_main() {
+                    self.on_main_key(key);
+                } else if self.tabs.is_disks() {
+                    self.on_disks_key(key);
+                } else if self.tabs.is_backups() {
+                    self.on_backups_key(key);
+                } else if self.tabs.is_processes() {
+                    self.on_processes_key(key);
+                } else if self.tabs.is_settings() {
+                    self.on_settings_key(key);
+                } else if self.tabs.is_logs() {
+                    self.on_logs_key(key);
+                } else if let KeyCode::Char('k') = key.code {
                     self.focus.to_tabs();
                 }
             }
             SanupFocus::InputForm => {
+                self.input_form.on_key(key);
+
                 if self.input_form.is_submitted() {
-                    //TODO Submitted Input Form
-                    self.focus.to_body();
+                    self.on_form_submitted();
                 } else if self.input_form.is_cancelled() {
-                    //TODO Cancelled Input Form
+                    self.on_form_cancelled();
+                }
+
+                // Multi-step forms open their next step instead of closing.
+                if !self.input_form.is_active() {
                     self.focus.to_body();
                 }
+            }
+        }
+    }
 
-                self.input_form.on_key(key);
+    fn on_main_key(&mut self, key: KeyEvent) {
+        if let Some(filter_preview) = &mut self.filter_preview {
+            if let KeyCode::Esc = key.code {
+                self.filter_preview = None;
+            } else {
+                filter_preview.on_key(key);
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('k') if self.browser.is_at_top() => self.focus.to_tabs(),
+            KeyCode::Char('.') => {
+                self.browser.toggle_hidden();
+                self.settings
+                    .set_show_hidden_files(self.browser.show_hidden());
+                self.input_list = settings_list(&self.settings);
+            }
+            KeyCode::Char('f') => {
+                let filter = BackupFilter::default();
+                self.open_form(
+                    SanupForm::FilterPreview,
+                    InputForm::new(
+                        "FILTER PREVIEW",
+                        vec![
+                            Field::String(InputField::new_with_value(
+                                "sources",
+                                StringField::from(join_paths(&self.browser.marks())),
+                            )),
+                            Field::String(InputField::new("exclude")),
+                            Field::String(InputField::new("include")),
+                            Field::Bool(InputField::new_with_value(
+                                "use_gitignore",
+                                BoolField::from(filter.use_gitignore()),
+                            )),
+                            Field::Bool(InputField::new_with_value(
+                                "use_sanupignore",
+                                BoolField::from(filter.use_sanupignore()),
+                            )),
+                            Field::String(InputField::new("save_to_profile")),
+                        ]
+                        .into(),
+                    )
+                    .with_lines(vec![
+                        "Patterns are comma separated, a profile named below keeps the filter"
+                            .to_string(),
+                    ]),
+                );
+            }
+            KeyCode::Char('r') if !self.settings.profiles().is_empty() => {
+                let profile = self.first_profile_name();
+                let rules = self
+                    .settings
+                    .profile_by_name(&profile)
+                    .map(|profile| profile.rules().clone())
+                    .unwrap_or_default();
+                self.open_form(
+                    SanupForm::ProfileRules,
+                    InputForm::new(
+                        "PROFILE RULES",
+                        vec![
+                            Field::String(InputField::new_with_value(
+                                "profile",
+                                StringField::from(profile),
+                            )),
+                            Field::Integer(InputField::new_with_value(
+                                "max_file_size_mb",
+                                IntegerField::from(rules.max_file_size_mb().unwrap_or(0) as i64),
+                            )),
+                            Field::Integer(InputField::new_with_value(
+                                "max_age_days",
+                                IntegerField::from(rules.max_age_days().unwrap_or(0) as i64),
+                            )),
+                            Field::Bool(InputField::new_with_value(
+                                "only_owned_by_me",
+                                BoolField::from(rules.only_owned_by_me()),
+                            )),
+                            Field::Bool(InputField::new_with_value(
+                                "skip_special_files",
+                                BoolField::from(rules.skip_special_files()),
+                            )),
+                        ]
+                        .into(),
+                    )
+                    .with_lines(vec!["A limit of 0 turns it off".to_string()]),
+                );
+            }
+            KeyCode::Char('p') if !self.browser.marks().is_empty() => {
+                self.open_form(
+                    SanupForm::NewProfile,
+                    InputForm::new(
+                        "NEW PROFILE",
+                        vec![
+                            Field::String(InputField::new("name")),
+                            Field::Enum(InputField::new_with_value(
+                                "kind",
+                                EnumField::from(BackupKind::Full),
+                            )),
+                            Field::String(InputField::new_with_value(
+                                "target_path",
+                                StringField::from(self.settings.default_backup_dir().to_path_buf()),
+                            )),
+                            Field::Enum(InputField::new_with_value(
+                                "schedule",
+                                EnumField::from(Frequency::Manual),
+                            )),
+                            Field::String(InputField::new_with_value(
+                                "at",
+                                StringField::from("02:00".to_string()),
+                            )),
+                            Field::String(InputField::new_with_value(
+                                "weekday",
+                                StringField::from("Sun".to_string()),
+                            )),
+                            Field::String(InputField::new("window")),
+                            Field::Bool(InputField::new("only_on_ac_power")),
+                        ]
+                        .into(),
+                    ),
+                );
+            }
+            _ => self.browser.on_key(key),
+        }
+    }
+
+    fn prompt_watched_disk(&mut self, disk: BackupDisk) {
+        let profile = self
+            .settings
+            .watched_disk_for(&disk)
+            .and_then(|watched_disk| watched_disk.backup_profile())
+            .and_then(|id| self.settings.profile(id))
+            .map(|profile| profile.name().to_string())
+            .unwrap_or_default();
+
+        self.open_form(
+            SanupForm::WatchedDiskMounted,
+            InputForm::new(
+                "WATCHED DISK PLUGGED IN",
+                vec![
+                    Field::String(InputField::new_with_value(
+                        "disk",
+                        StringField::from(disk.label().to_string()),
+                    )),
+                    Field::String(InputField::new_with_value(
+                        "profile",
+                        StringField::from(profile),
+                    )),
+                    Field::Bool(InputField::new_with_value("run", BoolField::from(true))),
+                ]
+                .into(),
+            ),
+        );
+    }
+
+    /// Writes the settings back so an attached daemon picks up the change.
+    /// Refuses to when the settings file did not load.
+    fn save_settings(&self) -> SanupResult<()> {
+        if let Some(err) = &self.settings_error {
+            return Err(SanupError::Other(format!(
+                "Not saving over {}, it did not load: {}",
+                self.settings_path.display(),
+                err
+            )));
+        }
+
+        self.settings.save(&self.settings_path)
+    }
+
+    fn on_disks_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('k') if self.disks.is_at_top() => self.focus.to_tabs(),
+            KeyCode::Char('w') if self.disks.selected().is_some() => {
+                self.open_form(
+                    SanupForm::WatchDisk,
+                    InputForm::new(
+                        "WATCH DISK",
+                        vec![
+                            Field::String(InputField::new_with_value(
+                                "profile",
+                                StringField::from(self.first_profile_name()),
+                            )),
+                            Field::Bool(InputField::new("auto_run")),
+                        ]
+                        .into(),
+                    ),
+                );
+            }
+            KeyCode::Char('a') if self.disks.selected().is_some() => {
+                self.open_form(
+                    SanupForm::AddTargetDisk,
+                    InputForm::new(
+                        "ADD DISK TO ROTATION",
+                        vec![
+                            Field::String(InputField::new_with_value(
+                                "profile",
+                                StringField::from(self.first_profile_name()),
+                            )),
+                            Field::String(InputField::new_with_value(
+                                "path",
+                                StringField::from("sanup".to_string()),
+                            )),
+                            Field::Integer(InputField::new("warn_after_days")),
+                        ]
+                        .into(),
+                    ),
+                );
+            }
+            _ => self.disks.on_key(key),
+        }
+    }
+
+    fn on_processes_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('k') if self.processes.is_at_top() => self.focus.to_tabs(),
+            KeyCode::Char(c @ ('p' | 'r' | 'c')) => {
+                let Some(id) = self.processes.selected_id() else {
+                    return;
+                };
+                let request = match c {
+                    'p' => Request::Pause(id),
+                    'r' => Request::Resume(id),
+                    _ => Request::Cancel(id),
+                };
+                self.control_job(id, request);
+            }
+            KeyCode::Char('L') => {
+                if let Some(job) = self.processes.selected() {
+                    let (id, name) = (job.id, job.name.clone());
+                    self.show_task_logs(id, name);
+                }
+            }
+            _ => self.processes.on_key(key),
+        }
+    }
+
+    /// Pauses, resumes or cancels a job, ours or the daemon's.
+    fn control_job(&mut self, id: Uuid, request: Request) {
+        if let Some(task) = self.engine.backup(id) {
+            match request {
+                Request::Pause(_) => task.pause(),
+                Request::Resume(_) => task.resume(),
+                Request::Cancel(_) => task.cancel(),
+                Request::Status => {}
+            }
+            return;
+        }
+
+        let Some(daemon) = &mut self.daemon else {
+            return;
+        };
+        match daemon.request(&request) {
+            Ok(Response::Ok) => self.last_daemon_poll = None,
+            Ok(Response::Error(err)) => self.processes.set_notice(err),
+            Ok(response) => warn!("Unexpected daemon response: {:?}", response),
+            Err(err) => self
+                .processes
+                .set_notice(format!("Failed to reach the daemon: {}", err)),
+        }
+    }
+
+    fn on_settings_key(&mut self, key: KeyEvent) {
+        self.input_list.on_key(key);
+
+        if self.input_list.is_submitted() {
+            self.save_settings_list();
+        } else if self.input_list.is_reset() {
+            self.input_list = settings_list(&self.settings);
+            self.input_list.activate();
+            self.input_list.set_notice("Changes reset".to_string());
+        } else if self.input_list.is_left() {
+            self.focus.to_tabs();
+        }
+    }
+
+    /// Applies the edited settings and writes them to the config file.
+    fn save_settings_list(&mut self) {
+        if let Err(err) = self.settings.update_from(&self.input_list.values()) {
+            self.input_list.activate();
+            self.input_list.set_notice(err.to_string());
+            return;
+        }
+
+        if self.browser.show_hidden() != self.settings.show_hidden_files() {
+            self.browser.toggle_hidden();
+        }
+
+        match self.save_settings() {
+            Ok(()) => {
+                if let Err(err) = SanupLogger::default().configure(&self.settings) {
+                    warn!("Failed to apply the log settings: {}", err);
+                }
+                info!("Saved settings to {}", self.settings_path.display());
+                self.input_list = settings_list(&self.settings);
+                self.input_list.activate();
+                self.input_list
+                    .set_notice(format!("Saved to {}", self.settings_path.display()));
+            }
+            Err(err) => {
+                self.input_list.activate();
+                self.input_list
+                    .set_notice(format!("Failed to save settings: {}", err));
+            }
+        }
+    }
+
+    fn on_backups_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('k') if self.backups.is_at_top() => self.focus.to_tabs(),
+            KeyCode::Char('c') => self.open_backup_wizard(),
+            KeyCode::Char('d') => {
+                let Some(backup) = self.backups.selected() else {
+                    return;
+                };
+                if self.engine.is_deleting(backup.id()) {
+                    return;
+                }
+
+                let dependents = delete::dependents(self.engine.catalog(), backup.id()).len();
+                let mut fields = vec![
+                    Field::String(InputField::new_with_value(
+                        "backup",
+                        StringField::from(backup.name().to_string()),
+                    )),
+                    Field::String(InputField::new_with_value(
+                        "dependents",
+                        StringField::from(dependents.to_string()),
+                    )),
+                ];
+                if dependents > 0 {
+                    fields.push(Field::Bool(InputField::new("merge")));
+                }
+                fields.push(Field::Bool(InputField::new("confirm")));
+
+                self.open_form(
+                    SanupForm::DeleteBackup,
+                    InputForm::new("DELETE BACKUP", fields.into()),
+                );
+            }
+            KeyCode::Char('s') => {
+                let Some(backup) = self.backups.selected() else {
+                    return;
+                };
+
+                let name = backup.name().to_string();
+                match self.engine.synthesize(backup.id()) {
+                    Ok(()) => self
+                        .backups
+                        .set_notice(format!("Synthesizing a full backup from {}", name)),
+                    Err(err) => self
+                        .backups
+                        .set_notice(format!("Not synthesizing from {}: {}", name, err)),
+                }
+            }
+            KeyCode::Char('f') => {
+                let filter = self.backups.filter();
+                let date =
+                    |date: Option<NaiveDate>| date.map(|date| date.to_string()).unwrap_or_default();
+
+                self.open_form(
+                    SanupForm::FilterBackups,
+                    InputForm::new(
+                        "FILTER BACKUPS",
+                        vec![
+                            Field::String(InputField::new_with_value(
+                                "profile",
+                                StringField::from(filter.profile.clone()),
+                            )),
+                            Field::String(InputField::new_with_value(
+                                "disk",
+                                StringField::from(filter.disk.clone()),
+                            )),
+                            Field::String(InputField::new_with_value(
+                                "from",
+                                StringField::from(date(filter.from)),
+                            )),
+                            Field::String(InputField::new_with_value(
+                                "to",
+                                StringField::from(date(filter.to)),
+                            )),
+                            Field::Enum(InputField::new_with_value(
+                                "status",
+                                EnumField::from(filter.status),
+                            )),
+                        ]
+                        .into(),
+                    ),
+                );
+            }
+            KeyCode::Char('F') => self.backups.set_filter(HistoryFilter::default()),
+            KeyCode::Char('L') => {
+                if let Some(backup) = self.backups.selected() {
+                    let (id, name) = (backup.id(), backup.name().to_string());
+                    self.show_task_logs(id, name);
+                }
+            }
+            _ => self.backups.on_key(key),
+        }
+    }
+
+    fn on_logs_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('k') if self.logs.is_at_top() => self.focus.to_tabs(),
+            KeyCode::Char('/') => {
+                self.open_form(
+                    SanupForm::SearchLogs,
+                    InputForm::new(
+                        "SEARCH LOGS",
+                        vec![Field::String(InputField::new_with_value(
+                            "text",
+                            StringField::from(self.logs.search().to_string()),
+                        ))]
+                        .into(),
+                    ),
+                );
+            }
+            _ => self.logs.on_key(key),
+        }
+    }
+
+    /// Opens the log viewer on what the tasks of a backup logged in this
+    /// session. Jobs of an attached daemon log in the daemon instead.
+    fn show_task_logs(&mut self, id: Uuid, name: String) {
+        self.logs.scope_to(id, name);
+        self.tabs = SanupTabs::Logs;
+        self.focus.to_body();
+    }
+
+    fn first_profile_name(&self) -> String {
+        self.settings
+            .profiles()
+            .first()
+            .map(|profile| profile.name().to_string())
+            .unwrap_or_default()
+    }
+
+    fn open_backup_wizard(&mut self) {
+        let wizard = BackupWizard::new(&self.settings, self.disks.disks(), &self.browser.marks());
+        self.open_form(SanupForm::CreateBackup, wizard.form());
+        self.wizard = Some(wizard);
+    }
+
+    fn open_form(&mut self, form: SanupForm, input_form: InputForm) {
+        self.form = form;
+        self.input_form = input_form;
+        self.focus.to_inputform();
+    }
+
+    fn on_form_submitted(&mut self) {
+        match self.form {
+            SanupForm::CreateBackup => {
+                let values = self.input_form.values();
+                let Some(wizard) = &mut self.wizard else {
+                    return;
+                };
+
+                match wizard.submit(&values) {
+                    WizardOutcome::Step(input_form) => self.input_form = input_form,
+                    WizardOutcome::Start { metadata, disk } => {
+                        info!("Starting backup {}", metadata.name());
+                        self.backups
+                            .set_notice(format!("Started backup {}", metadata.name()));
+                        match disk {
+                            Some(disk) => {
+                                self.engine
+                                    .start_backup_on(&self.settings, *metadata, None, disk)
+                            }
+                            None => self.engine.start_backup(&self.settings, *metadata),
+                        }
+                        self.wizard = None;
+                        self.input_form = InputForm::default();
+                    }
+                    WizardOutcome::Discarded => {
+                        self.wizard = None;
+                        self.input_form = InputForm::default();
+                    }
+                }
+            }
+            SanupForm::FilterPreview => {
+                let values = self.input_form.values();
+                let mut filter = BackupFilter::new(
+                    split_list(&values, "exclude"),
+                    split_list(&values, "include"),
+                );
+                filter.set_use_gitignore(values.get_bool("use_gitignore").unwrap_or(false));
+                filter.set_use_sanupignore(values.get_bool("use_sanupignore").unwrap_or(true));
+
+                let sources = split_list(&values, "sources")
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .collect();
+
+                let profile = values.get_string("save_to_profile").unwrap_or_default();
+                self.close_form_unless(|app| {
+                    if !profile.trim().is_empty() {
+                        app.save_profile_filter(profile.trim(), filter.clone())?;
+                    }
+                    app.filter_preview = Some(FilterPreviewView::spawn(filter, sources));
+                    Ok(())
+                });
+            }
+            SanupForm::NewProfile => {
+                let values = self.input_form.values();
+                self.close_form_unless(|app| app.create_profile(&values));
+            }
+            SanupForm::ProfileRules => {
+                let values = self.input_form.values();
+                self.close_form_unless(|app| app.set_profile_rules(&values));
+            }
+            SanupForm::WatchDisk => {
+                let values = self.input_form.values();
+                self.close_form_unless(|app| app.watch_disk(&values));
+            }
+            SanupForm::AddTargetDisk => {
+                let values = self.input_form.values();
+                self.close_form_unless(|app| app.add_target_disk(&values));
+            }
+            SanupForm::WatchedDiskMounted => {
+                let run = self.input_form.values().get_bool("run").unwrap_or(false);
+                if let Some(disk) = self.mounted_disks.pop_front()
+                    && run
+                {
+                    self.engine.run_watched_disk(&self.settings, disk);
+                }
+
+                self.input_form = InputForm::default();
+            }
+            SanupForm::DeleteBackup => {
+                let values = self.input_form.values();
+                let merge = values.get_bool("merge").unwrap_or(false);
+
+                if values.get_bool("confirm").unwrap_or(false)
+                    && let Some(backup) = self.backups.selected()
+                {
+                    let name = backup.name().to_string();
+                    match self.engine.delete_backup(backup.id(), merge) {
+                        Ok(()) => self.backups.set_notice(format!("Deleting {}", name)),
+                        Err(err) => self
+                            .backups
+                            .set_notice(format!("Not deleting {}: {}", name, err)),
+                    }
+                }
+
+                self.input_form = InputForm::default();
+            }
+            SanupForm::FilterBackups => {
+                let values = self.input_form.values();
+                let date = |title: &str| -> Result<Option<NaiveDate>, String> {
+                    let value = values.get_string(title).unwrap_or_default();
+                    let value = value.trim();
+                    if value.is_empty() {
+                        return Ok(None);
+                    }
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .map(Some)
+                        .map_err(|_| format!("{} is not a YYYY-MM-DD date", value))
+                };
+
+                match (date("from"), date("to")) {
+                    (Ok(from), Ok(to)) => self.backups.set_filter(HistoryFilter {
+                        profile: values.get_string("profile").unwrap_or_default(),
+                        disk: values.get_string("disk").unwrap_or_default(),
+                        from,
+                        to,
+                        status: StatusFilter::from(
+                            values.get_enum("status").unwrap_or_default().as_str(),
+                        ),
+                    }),
+                    (Err(reason), _) | (_, Err(reason)) => self.backups.set_notice(reason),
+                }
+
+                self.input_form = InputForm::default();
+            }
+            SanupForm::SearchLogs => {
+                let values = self.input_form.values();
+                self.logs
+                    .set_search(values.get_string("text").unwrap_or_default());
+
+                self.input_form = InputForm::default();
+            }
+        }
+
+        // Profiles and watched disks name the backups in the history.
+        self.backups.set_labels(&self.settings, self.disks.disks());
+    }
+
+    /// Closes the form once `apply` succeeds, otherwise keeps it open with
+    /// the reason.
+    fn close_form_unless(&mut self, apply: impl FnOnce(&mut Self) -> SanupResult<()>) {
+        match apply(self) {
+            Ok(()) => self.input_form = InputForm::default(),
+            Err(err) => self.input_form.reject(err.to_string()),
+        }
+    }
+
+    fn create_profile(&mut self, values: &Values) -> SanupResult<()> {
+        let name = values.get_string("name").unwrap_or_default();
+        let name = name.trim();
+        let kind = BackupKind::from(values.get_enum("kind").unwrap_or_default().as_str());
+        let target_path = values.get_string("target_path").unwrap_or_default();
+
+        self.settings.check_new_profile_name(name)?;
+        if target_path.trim().is_empty() {
+            return Err(SanupError::Other("The target path is required".to_string()));
+        }
+        let schedule = schedule_from_values(values)
+            .map_err(|reason| SanupError::Other(format!("Invalid schedule: {}", reason)))?;
+
+        let mut profile = BackupProfile::new(
+            name,
+            kind,
+            self.browser.marks(),
+            PathBuf::from(target_path.trim()),
+        );
+        profile.set_schedule(schedule);
+
+        let mut settings = self.settings.clone();
+        settings.add_profile(profile);
+        self.replace_settings(settings)?;
+        info!("Created backup profile {}", name);
+        self.browser.clear_marks();
+
+        Ok(())
+    }
+
+    fn watch_disk(&mut self, values: &Values) -> SanupResult<()> {
+        let name = values.get_string("profile").unwrap_or_default();
+        let auto_run = values.get_bool("auto_run").unwrap_or(false);
+
+        let disk = self.marked_disk()?;
+        let profile = self.profile_named(&name)?;
+
+        let mut settings = self.settings.clone();
+        settings.watch_disk(WatchedDisk::new(&disk, profile.id(), auto_run));
+        self.replace_settings(settings)?;
+        info!("Watching disk {} for profile {}", disk.label(), name);
+        self.disks.refresh();
+
+        Ok(())
+    }
+
+    fn add_target_disk(&mut self, values: &Values) -> SanupResult<()> {
+        let name = values.get_string("profile").unwrap_or_default();
+        let path = values.get_string("path").unwrap_or_default();
+        let warn_after_days = values
+            .get_integer("warn_after_days")
+            .filter(|days| *days > 0)
+            .map(|days| days as u64);
+
+        let disk = self.marked_disk()?;
+        self.profile_named(&name)?;
+
+        let mut settings = self.settings.clone();
+        if let Some(profile) = settings.profile_by_name_mut(&name) {
+            profile.add_target_disk(&disk, path.trim_start_matches('/'));
+            profile.set_rotation_warn_days(warn_after_days);
+        }
+        self.replace_settings(settings)?;
+        info!("Added disk {} to the rotation of {}", disk.label(), name);
+        self.disks.refresh();
+
+        Ok(())
+    }
+
+    fn set_profile_rules(&mut self, values: &Values) -> SanupResult<()> {
+        let name = values.get_string("profile").unwrap_or_default();
+        let name = name.trim();
+        let limit = |title| {
+            values
+                .get_integer(title)
+                .filter(|limit| *limit > 0)
+                .map(|limit| limit as u64)
+        };
+
+        let mut rules = self.profile_named(name)?.rules().clone();
+        rules.set_max_file_size_mb(limit("max_file_size_mb"));
+        rules.set_max_age_days(limit("max_age_days"));
+        rules.set_only_owned_by_me(values.get_bool("only_owned_by_me").unwrap_or_default());
+        rules.set_skip_special_files(values.get_bool("skip_special_files").unwrap_or_default());
+
+        let mut settings = self.settings.clone();
+        if let Some(profile) = settings.profile_by_name_mut(name) {
+            profile.set_rules(rules);
+        }
+        self.replace_settings(settings)?;
+        info!("Saved the selection rules of profile {}", name);
+
+        Ok(())
+    }
+
+    fn save_profile_filter(&mut self, name: &str, filter: BackupFilter) -> SanupResult<()> {
+        self.profile_named(name)?;
+
+        let mut settings = self.settings.clone();
+        if let Some(profile) = settings.profile_by_name_mut(name) {
+            profile.set_filter(filter);
+        }
+        self.replace_settings(settings)?;
+        info!("Saved the filter of profile {}", name);
+
+        Ok(())
+    }
+
+    /// The selected disk, with a marker written to it when it is removable so
+    /// it is recognized wherever it is mounted.
+    fn marked_disk(&self) -> SanupResult<BackupDisk> {
+        let mut disk = self
+            .disks
+            .selected()
+            .cloned()
+            .ok_or_else(|| SanupError::Other("No disk selected".to_string()))?;
+        if disk.removable() {
+            disk.write_marker().map_err(|err| {
+                SanupError::Other(format!(
+                    "Failed to write the disk marker to {}: {}",
+                    disk.label(),
+                    err
+                ))
+            })?;
+        }
+
+        Ok(disk)
+    }
+
+    fn profile_named(&self, name: &str) -> SanupResult<&BackupProfile> {
+        self.settings
+            .profile_by_name(name)
+            .ok_or_else(|| SanupError::Other(format!("No backup profile named {}", name)))
+    }
+
+    /// Saves `settings` and takes them over, leaving the current ones as
+    /// they are when saving fails.
+    fn replace_settings(&mut self, settings: Settings) -> SanupResult<()> {
+        let previous = std::mem::replace(&mut self.settings, settings);
+        if let Err(err) = self.save_settings() {
+            self.settings = previous;
+            return Err(SanupError::Other(format!(
+                "Failed to save settings: {}",
+                err
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn on_form_cancelled(&mut self) {
+        match self.form {
+            SanupForm::WatchedDiskMounted => {
+                self.mounted_disks.pop_front();
             }
+            SanupForm::CreateBackup => {
+                let values = self.input_form.values();
+                match self.wizard.as_mut().and_then(|wizard| wizard.back(&values)) {
+                    Some(input_form) => self.input_form = input_form,
+                    None => self.wizard = None,
+                }
+            }
+            _ => {}
         }
     }
 }
 
+/// Reads the schedule fields of the profile form.
+fn schedule_from_values(values: &Values) -> Result<Schedule, String> {
+    let frequency = Frequency::from(values.get_enum("schedule").unwrap_or_default().as_str());
+    let at = values.get_string("at").unwrap_or_default();
+    let weekday = values.get_string("weekday").unwrap_or_default();
+    let window = values.get_string("window").unwrap_or_default();
+
+    let mut schedule = Schedule::new(
+        frequency,
+        parse_time(&at).ok_or(format!("{} is not a HH:MM time", at))?,
+    );
+    schedule.set_weekday(
+        weekday
+            .trim()
+            .parse::<Weekday>()
+            .map_err(|_| format!("{} is not a weekday", weekday))?,
+    );
+    if !window.trim().is_empty() {
+        schedule.set_window(Some(
+            TimeWindow::parse(&window).ok_or(format!("{} is not a HH:MM-HH:MM window", window))?,
+        ));
+    }
+    schedule.set_only_on_ac_power(values.get_bool("only_on_ac_power").unwrap_or(false));
+
+    Ok(schedule)
+}
+
+/// Splits a comma separated form value into its trimmed, non-empty items.
+fn split_list(values: &Values, title: &str) -> Vec<String> {
+    values
+        .get_string(title)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn settings_list(settings: &Settings) -> InputList {
+    InputList::new(Some("Settings"), true, settings.clone().into())
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl Default for Sanup {
     fn default() -> Self {
+        let settings_path = Settings::default_path();
+        let (settings, settings_error) = match Settings::load(&settings_path) {
+            Ok(settings) => (settings, None),
+            Err(err) => {
+                error!("Failed to load {}: {}", settings_path.display(), err);
+                (Settings::default(), Some(err.to_string()))
+            }
+        };
+        let browser = FileBrowser::new(
+            dirs::home_dir().unwrap_or_else(|| PathBuf::from("/")),
+            settings.show_hidden_files(),
+        );
+
+        let mut input_list = settings_list(&settings);
+        if let Some(err) = &settings_error {
+            input_list.set_notice(format!(
+                "{} did not load, using defaults without saving: {}",
+                settings_path.display(),
+                err
+            ));
+        }
+
+        let mut engine = Engine::new(Catalog::default_path());
+        let disks = DiskView::new(DiskDiscovery::default());
+        let mut backups = BackupView::new(engine.catalog());
+        backups.set_labels(&settings, disks.disks());
+        let daemon = DaemonClient::connect(default_socket_path()).ok();
+        if daemon.is_some() {
+            info!("Attached to the running daemon");
+            engine.set_automation(false);
+        } else {
+            engine.warn_overdue_disks(&settings);
+        }
+
         Sanup {
             title: "Sanup",
             focus: SanupFocus::Tabs,
             tabs: SanupTabs::Main,
-            backups: Vec::new(),
-            settings: Settings::default(),
+            engine,
+            settings,
+            settings_path,
+            settings_error,
+            form: SanupForm::CreateBackup,
             input_form: InputForm::default(),
-            input_list: InputList::default(),
+            input_list,
+            filter_preview: None,
+            wizard: None,
+            browser,
+            disks,
+            backups,
+            processes: ProcessView::default(),
+            logs: LogView::new(SanupLogger::default().buffer()),
+            mounted_disks: VecDeque::new(),
+            daemon,
+            remote_jobs: Vec::new(),
+            last_daemon_poll: None,
         }
     }
 }
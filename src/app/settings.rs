@@ -4,7 +4,10 @@ use crate::{
         hash_algorithm::HashAlgorithm, log_level::LogLevel, theme::Theme,
         watched_disk::WatchedDisk,
     },
+    backup::{disk::BackupDisk, profile::BackupProfile},
     config::Config,
+    error::{SanupError, SanupResult},
+    paths,
     ui::input::{
         boolfield::BoolField,
         enumfield::EnumField,
@@ -12,12 +15,15 @@ use crate::{
         inputfield::InputField,
         integerfield::IntegerField,
         stringfield::StringField,
+        value::Values,
     },
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Settings {
     default_backup_dir: PathBuf,
     compression_enable: bool,
@@ -32,11 +38,220 @@ pub struct Settings {
     log_level: LogLevel,
     log_to_file: bool,
     log_file_dir: Option<PathBuf>,
+    log_max_size_mb: u64,
+    /// Log files are never rotated by age when 0.
+    log_max_age_days: u64,
+    log_max_files: u64,
+    log_to_syslog: bool,
     watched_disk: Vec<WatchedDisk>,
+    profiles: Vec<BackupProfile>,
 }
 
 impl Config for Settings {}
 
+impl Settings {
+    pub fn default_path() -> PathBuf {
+        paths::config_file()
+    }
+
+    pub fn default_backup_dir(&self) -> &Path {
+        &self.default_backup_dir
+    }
+
+    pub fn compression_enable(&self) -> bool {
+        self.compression_enable
+    }
+
+    pub fn compression_kind(&self) -> &CompressionKind {
+        &self.compression_kind
+    }
+
+    pub fn compression_level(&self) -> &CompressionLevel {
+        &self.compression_level
+    }
+
+    pub fn hash_algorithm(&self) -> &HashAlgorithm {
+        &self.hash_algorithm
+    }
+
+    pub fn check_free_space_before_backup(&self) -> bool {
+        self.check_free_space_before_backup
+    }
+
+    pub fn min_free_space_gb(&self) -> u64 {
+        self.min_free_space_gb
+    }
+
+    pub fn verify_after_backup(&self) -> bool {
+        self.verify_after_backup
+    }
+
+    pub fn log_level(&self) -> &LogLevel {
+        &self.log_level
+    }
+
+    pub fn log_to_file(&self) -> bool {
+        self.log_to_file
+    }
+
+    pub fn log_file_dir(&self) -> Option<&Path> {
+        self.log_file_dir.as_deref()
+    }
+
+    pub fn log_max_size_mb(&self) -> u64 {
+        self.log_max_size_mb
+    }
+
+    pub fn log_max_age_days(&self) -> u64 {
+        self.log_max_age_days
+    }
+
+    pub fn log_max_files(&self) -> u64 {
+        self.log_max_files
+    }
+
+    pub fn log_to_syslog(&self) -> bool {
+        self.log_to_syslog
+    }
+
+    pub fn show_hidden_files(&self) -> bool {
+        self.show_hidden_files
+    }
+
+    pub fn set_show_hidden_files(&mut self, show_hidden_files: bool) {
+        self.show_hidden_files = show_hidden_files;
+    }
+
+    pub fn profiles(&self) -> &[BackupProfile] {
+        &self.profiles
+    }
+
+    pub fn profile(&self, id: Uuid) -> Option<&BackupProfile> {
+        self.profiles.iter().find(|profile| profile.id() == id)
+    }
+
+    pub fn add_profile(&mut self, profile: BackupProfile) {
+        self.profiles.push(profile);
+    }
+
+    /// Fails unless `name` can name a new profile: it must not be empty, is
+    /// used in paths and unit names so it cannot hold a `/`, and no other
+    /// profile may have it, whatever the case.
+    pub fn check_new_profile_name(&self, name: &str) -> SanupResult<()> {
+        if name.trim().is_empty() {
+            return Err(SanupError::Other(
+                "The profile name is required".to_string(),
+            ));
+        }
+        if name.contains('/') {
+            return Err(SanupError::Other(format!(
+                "Profile name {} cannot contain a /",
+                name
+            )));
+        }
+        if self
+            .profiles
+            .iter()
+            .any(|profile| profile.name().eq_ignore_ascii_case(name.trim()))
+        {
+            return Err(SanupError::Other(format!(
+                "There already is a profile named {}",
+                name
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn profile_by_name(&self, name: &str) -> Option<&BackupProfile> {
+        self.profiles.iter().find(|profile| profile.name() == name)
+    }
+
+    pub fn profile_by_name_mut(&mut self, name: &str) -> Option<&mut BackupProfile> {
+        self.profiles
+            .iter_mut()
+            .find(|profile| profile.name() == name)
+    }
+
+    pub fn watched_disks(&self) -> &[WatchedDisk] {
+        &self.watched_disk
+    }
+
+    pub fn watched_disk_for(&self, disk: &BackupDisk) -> Option<&WatchedDisk> {
+        self.watched_disk
+            .iter()
+            .find(|watched_disk| watched_disk.matches(disk))
+    }
+
+    /// Takes the values edited in the settings tab. Profiles and watched
+    /// disks are not among them and stay as they are. Nothing is changed
+    /// when a value is invalid.
+    pub fn update_from(&mut self, values: &Values) -> SanupResult<()> {
+        let default_backup_dir = values.get_string("default_backup_dir").unwrap_or_default();
+        if default_backup_dir.trim().is_empty() {
+            return Err(SanupError::Other(
+                "default_backup_dir cannot be empty".to_string(),
+            ));
+        }
+
+        let min_free_space_gb = values.get_integer("min_free_space_gb").unwrap_or(0);
+        let min_free_space_gb = u64::try_from(min_free_space_gb).map_err(|_| {
+            SanupError::Other(format!(
+                "min_free_space_gb cannot be negative, got {}",
+                min_free_space_gb
+            ))
+        })?;
+
+        let non_negative = |title: &str| {
+            let value = values.get_integer(title).unwrap_or(0);
+            u64::try_from(value).map_err(|_| {
+                SanupError::Other(format!("{} cannot be negative, got {}", title, value))
+            })
+        };
+        let log_max_size_mb = non_negative("log_max_size_mb")?;
+        if log_max_size_mb == 0 {
+            return Err(SanupError::Other(
+                "log_max_size_mb has to be at least 1".to_string(),
+            ));
+        }
+        let log_max_age_days = non_negative("log_max_age_days")?;
+        let log_max_files = non_negative("log_max_files")?;
+
+        let log_file_dir = values.get_string("log_file_dir").unwrap_or_default();
+        let enum_value = |title: &str| values.get_enum(title).unwrap_or_default();
+
+        self.default_backup_dir = PathBuf::from(default_backup_dir.trim());
+        self.compression_enable = values.get_bool("compression_enable").unwrap_or(false);
+        self.compression_kind = CompressionKind::from(enum_value("compression_kind").as_str());
+        self.compression_level = CompressionLevel::from(enum_value("compression_level").as_str());
+        self.check_free_space_before_backup = values
+            .get_bool("check_free_space_before_backup")
+            .unwrap_or(false);
+        self.min_free_space_gb = min_free_space_gb;
+        self.verify_after_backup = values.get_bool("verify_after_backup").unwrap_or(false);
+        self.hash_algorithm = HashAlgorithm::from(enum_value("hash_algorithm").as_str());
+        self.theme = Theme::from(enum_value("theme").as_str());
+        self.show_hidden_files = values.get_bool("show_hidden_files").unwrap_or(false);
+        self.log_level = LogLevel::from(enum_value("log_level").as_str());
+        self.log_to_file = values.get_bool("log_to_file").unwrap_or(false);
+        self.log_file_dir =
+            (!log_file_dir.trim().is_empty()).then(|| PathBuf::from(log_file_dir.trim()));
+        self.log_max_size_mb = log_max_size_mb;
+        self.log_max_age_days = log_max_age_days;
+        self.log_max_files = log_max_files;
+        self.log_to_syslog = values.get_bool("log_to_syslog").unwrap_or(false);
+
+        Ok(())
+    }
+
+    /// Watches a disk, replacing the previous entry for the same disk.
+    pub fn watch_disk(&mut self, watched_disk: WatchedDisk) {
+        self.watched_disk
+            .retain(|existing| existing.disk_id() != watched_disk.disk_id());
+        self.watched_disk.push(watched_disk);
+    }
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Settings {
@@ -55,67 +270,86 @@ impl Default for Settings {
             log_level: LogLevel::Info,
             log_to_file: false,
             log_file_dir: None,
+            log_max_size_mb: 10,
+            log_max_age_days: 7,
+            log_max_files: 5,
+            log_to_syslog: false,
             watched_disk: Vec::new(),
+            profiles: Vec::new(),
         }
     }
 }
 
-impl Into<Fields> for Settings {
-    fn into(self) -> Fields {
-        let settings = Settings::default();
-
+impl From<Settings> for Fields {
+    fn from(value: Settings) -> Self {
         Fields::new(vec![
             Field::String(InputField::new_with_value(
                 "default_backup_dir",
-                StringField::from(settings.default_backup_dir),
+                StringField::from(value.default_backup_dir),
             )),
             Field::Bool(InputField::new_with_value(
                 "compression_enable",
-                BoolField::from(settings.compression_enable),
+                BoolField::from(value.compression_enable),
             )),
             Field::Enum(InputField::new_with_value(
                 "compression_kind",
-                EnumField::from(self.compression_kind),
+                EnumField::from(value.compression_kind),
             )),
             Field::Enum(InputField::new_with_value(
                 "compression_level",
-                EnumField::from(self.compression_level),
+                EnumField::from(value.compression_level),
             )),
             Field::Bool(InputField::new_with_value(
                 "check_free_space_before_backup",
-                BoolField::from(self.check_free_space_before_backup),
+                BoolField::from(value.check_free_space_before_backup),
             )),
             Field::Integer(InputField::new_with_value(
                 "min_free_space_gb",
-                IntegerField::from(self.min_free_space_gb as i64),
+                IntegerField::from(value.min_free_space_gb as i64),
             )),
             Field::Bool(InputField::new_with_value(
                 "verify_after_backup",
-                BoolField::from(self.verify_after_backup),
+                BoolField::from(value.verify_after_backup),
             )),
             Field::Enum(InputField::new_with_value(
                 "hash_algorithm",
-                EnumField::from(self.hash_algorithm),
+                EnumField::from(value.hash_algorithm),
             )),
             Field::Enum(InputField::new_with_value(
                 "theme",
-                EnumField::from(self.theme),
+                EnumField::from(value.theme),
             )),
             Field::Bool(InputField::new_with_value(
                 "show_hidden_files",
-                BoolField::from(self.show_hidden_files),
+                BoolField::from(value.show_hidden_files),
             )),
             Field::Enum(InputField::new_with_value(
                 "log_level",
-                EnumField::from(self.log_level),
+                EnumField::from(value.log_level),
             )),
             Field::Bool(InputField::new_with_value(
                 "log_to_file",
-                BoolField::from(self.log_to_file),
+                BoolField::from(value.log_to_file),
             )),
             Field::String(InputField::new_with_value(
                 "log_file_dir",
-                StringField::from(self.log_file_dir),
+                StringField::from(value.log_file_dir),
+            )),
+            Field::Integer(InputField::new_with_value(
+                "log_max_size_mb",
+                IntegerField::from(value.log_max_size_mb as i64),
+            )),
+            Field::Integer(InputField::new_with_value(
+                "log_max_age_days",
+                IntegerField::from(value.log_max_age_days as i64),
+            )),
+            Field::Integer(InputField::new_with_value(
+                "log_max_files",
+                IntegerField::from(value.log_max_files as i64),
+            )),
+            Field::Bool(InputField::new_with_value(
+                "log_to_syslog",
+                BoolField::from(value.log_to_syslog),
             )),
         ])
     }
@@ -0,0 +1,76 @@
+use crate::{backup::status::BackupStatus, ui::input::enumvariants::EnumVariants};
+
+/// Which backups the history shows by their status.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusFilter {
+    #[default]
+    Any,
+    Completed,
+    Failed,
+    Cancelled,
+    /// Pending, running or paused.
+    Unfinished,
+}
+
+impl StatusFilter {
+    pub fn matches(&self, status: &BackupStatus) -> bool {
+        match self {
+            StatusFilter::Any => true,
+            StatusFilter::Completed => *status == BackupStatus::Completed,
+            StatusFilter::Failed => matches!(status, BackupStatus::Failed { .. }),
+            StatusFilter::Cancelled => *status == BackupStatus::Cancelled,
+            StatusFilter::Unfinished => !status.is_finished(),
+        }
+    }
+}
+
+impl EnumVariants for StatusFilter {
+    fn default(&self) -> Box<dyn EnumVariants> {
+        Box::new(StatusFilter::Any)
+    }
+
+    fn longest(&self) -> String {
+        StatusFilter::Unfinished.to_string()
+    }
+
+    fn variants(&self) -> Vec<String> {
+        ["Any", "Completed", "Failed", "Cancelled", "Unfinished"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn from_string(&self, s: String) -> Box<dyn EnumVariants> {
+        Box::new(StatusFilter::from(s.as_str()))
+    }
+
+    fn clone_box(&self) -> Box<dyn EnumVariants> {
+        Box::new(*self)
+    }
+}
+
+impl From<&str> for StatusFilter {
+    fn from(value: &str) -> Self {
+        match value {
+            "Completed" => StatusFilter::Completed,
+            "Failed" => StatusFilter::Failed,
+            "Cancelled" => StatusFilter::Cancelled,
+            "Unfinished" => StatusFilter::Unfinished,
+            _ => StatusFilter::Any,
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
+impl ToString for StatusFilter {
+    fn to_string(&self) -> String {
+        match self {
+            StatusFilter::Any => "Any",
+            StatusFilter::Completed => "Completed",
+            StatusFilter::Failed => "Failed",
+            StatusFilter::Cancelled => "Cancelled",
+            StatusFilter::Unfinished => "Unfinished",
+        }
+        .to_string()
+    }
+}
@@ -6,6 +6,8 @@ pub enum SanupTabs {
     Main,
     Processes,
     Backups,
+    Disks,
+    Logs,
 }
 
 impl SanupTabs {
@@ -15,6 +17,8 @@ impl SanupTabs {
             Self::Main.to_string(),
             Self::Processes.to_string(),
             Self::Backups.to_string(),
+            Self::Disks.to_string(),
+            Self::Logs.to_string(),
         ]
     }
 
@@ -24,6 +28,8 @@ impl SanupTabs {
             Self::Main => 1,
             Self::Processes => 2,
             Self::Backups => 3,
+            Self::Disks => 4,
+            Self::Logs => 5,
         }
     }
 
@@ -32,16 +38,20 @@ impl SanupTabs {
             Self::Settings => *self = Self::Main,
             Self::Main => *self = Self::Processes,
             Self::Processes => *self = Self::Backups,
-            Self::Backups => *self = Self::Settings,
+            Self::Backups => *self = Self::Disks,
+            Self::Disks => *self = Self::Logs,
+            Self::Logs => *self = Self::Settings,
         }
     }
 
     pub fn prev(&mut self) {
         match self {
-            Self::Settings => *self = Self::Backups,
+            Self::Settings => *self = Self::Logs,
             Self::Main => *self = Self::Settings,
             Self::Processes => *self = Self::Main,
             Self::Backups => *self = Self::Processes,
+            Self::Disks => *self = Self::Backups,
+            Self::Logs => *self = Self::Disks,
         }
     }
 
@@ -60,6 +70,14 @@ impl SanupTabs {
     pub fn is_backups(&self) -> bool {
         *self == Self::Backups
     }
+
+    pub fn is_disks(&self) -> bool {
+        *self == Self::Disks
+    }
+
+    pub fn is_logs(&self) -> bool {
+        *self == Self::Logs
+    }
 }
 
 impl Display for SanupTabs {
@@ -72,6 +90,8 @@ impl Display for SanupTabs {
                 Self::Main => "Main",
                 Self::Processes => "Processes",
                 Self::Backups => "Backups",
+                Self::Disks => "Disks",
+                Self::Logs => "Logs",
             }
         )
     }
@@ -42,6 +42,20 @@ impl EnumVariants for Theme {
     }
 }
 
+impl From<&str> for Theme {
+    fn from(value: &str) -> Self {
+        match value {
+            "Dark" => Theme::Dark,
+            "Kanagawa" => Theme::Kanagawa,
+            "Nord" => Theme::Nord,
+            "Dracula" => Theme::Dracula,
+            "TokyoNight" => Theme::TokyoNight,
+            _ => Theme::Dark,
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for Theme {
     fn to_string(&self) -> String {
         match self {
@@ -1,11 +1,84 @@
+use crate::backup::disk::BackupDisk;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+/// A disk that runs `backup_profile` when it gets plugged in. The disk is
+/// recognised by its disk id, `mount_path` only records where it was mounted
+/// when it started being watched.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct WatchedDisk {
     id: Uuid,
     label: Option<String>,
     mount_path: PathBuf,
     backup_profile: Option<Uuid>,
+    #[serde(default)]
+    disk_id: Option<Uuid>,
+    #[serde(default)]
+    fs_uuid: Option<String>,
+    /// Runs the profile without asking first.
+    #[serde(default)]
+    auto_run: bool,
+}
+
+impl WatchedDisk {
+    pub fn new(disk: &BackupDisk, backup_profile: Uuid, auto_run: bool) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            label: Some(disk.label().to_string()),
+            mount_path: disk.mount_path().to_path_buf(),
+            backup_profile: Some(backup_profile),
+            disk_id: Some(disk.id()),
+            fs_uuid: disk.fs_uuid().map(str::to_string),
+            auto_run,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn mount_path(&self) -> &Path {
+        &self.mount_path
+    }
+
+    pub fn backup_profile(&self) -> Option<Uuid> {
+        self.backup_profile
+    }
+
+    pub fn disk_id(&self) -> Option<Uuid> {
+        self.disk_id
+    }
+
+    pub fn fs_uuid(&self) -> Option<&str> {
+        self.fs_uuid.as_deref()
+    }
+
+    pub fn auto_run(&self) -> bool {
+        self.auto_run
+    }
+
+    /// Entries written before disk ids were recorded fall back to the
+    /// filesystem UUID, then to the mount path.
+    pub fn matches(&self, disk: &BackupDisk) -> bool {
+        match (&self.disk_id, &self.fs_uuid) {
+            (Some(disk_id), _) => disk.id() == *disk_id,
+            (None, Some(fs_uuid)) => disk.fs_uuid() == Some(fs_uuid.as_str()),
+            (None, None) => disk.mount_path() == self.mount_path,
+        }
+    }
+
+    /// Moves `target` from where the disk was mounted when it started being
+    /// watched to where it is mounted now. Targets that are not on the disk
+    /// are put in a `sanup` directory at its root.
+    pub fn target_on(&self, disk: &BackupDisk, target: &Path) -> PathBuf {
+        match target.strip_prefix(&self.mount_path) {
+            Ok(relative) => disk.mount_path().join(relative),
+            Err(_) => disk.mount_path().join("sanup"),
+        }
+    }
 }
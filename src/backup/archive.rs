@@ -0,0 +1,197 @@
+use crate::{
+    app::compression_level::CompressionLevel,
+    backup::layout::{BackupLayout, relative_to_root},
+    error::{SanupError, SanupResult},
+};
+use chrono::{DateTime as ChronoDateTime, Datelike, Local, Timelike};
+use std::{
+    fs::{self, File, Permissions},
+    io::{self, BufReader, BufWriter, Read},
+    os::unix::fs::{MetadataExt, PermissionsExt, symlink},
+    path::{Path, PathBuf},
+};
+use zip::{
+    CompressionMethod, DateTime, ZipArchive, ZipWriter, read::ZipFile, write::SimpleFileOptions,
+};
+
+/// Writes the entries of a compressed backup into its zip archive, each under
+/// its original path with the root stripped, like `data/` holds them.
+pub struct ArchiveWriter {
+    zip: ZipWriter<BufWriter<File>>,
+    options: SimpleFileOptions,
+}
+
+impl ArchiveWriter {
+    pub fn create(layout: &BackupLayout, level: &CompressionLevel) -> SanupResult<Self> {
+        let level = match level {
+            CompressionLevel::Default => None,
+            CompressionLevel::Best => Some(9),
+        };
+
+        Ok(Self {
+            zip: ZipWriter::new(BufWriter::new(File::create(layout.archive_path())?)),
+            options: SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(level),
+        })
+    }
+
+    pub fn add_dir(&mut self, original: &Path) -> SanupResult<()> {
+        let mode = fs::symlink_metadata(original)?.mode();
+        self.zip.add_directory(
+            entry_name(original)?,
+            self.options.unix_permissions(mode & 0o7777),
+        )?;
+
+        Ok(())
+    }
+
+    pub fn add_file(&mut self, original: &Path) -> SanupResult<()> {
+        let mut file = File::open(original)?;
+        let metadata = file.metadata()?;
+        let mut options = self
+            .options
+            .unix_permissions(metadata.mode() & 0o7777)
+            .large_file(metadata.len() >= u32::MAX as u64);
+        if let Some(modified) = zip_time(metadata.mtime()) {
+            options = options.last_modified_time(modified);
+        }
+
+        self.zip.start_file(entry_name(original)?, options)?;
+        io::copy(&mut file, &mut self.zip)?;
+
+        Ok(())
+    }
+
+    pub fn add_symlink(&mut self, original: &Path) -> SanupResult<()> {
+        let target = fs::read_link(original)?;
+        let target = target.to_str().ok_or_else(|| {
+            SanupError::Other(format!(
+                "Link target of {} is not valid UTF-8",
+                original.display()
+            ))
+        })?;
+        self.zip
+            .add_symlink(entry_name(original)?, target, self.options)?;
+
+        Ok(())
+    }
+
+    /// Writes the central directory, without it the archive cannot be read.
+    pub fn finish(self) -> SanupResult<()> {
+        let mut writer = self.zip.finish()?;
+        io::Write::flush(&mut writer)?;
+
+        Ok(())
+    }
+}
+
+/// The zip archive of a compressed backup, read back by original path.
+pub struct Archive {
+    zip: ZipArchive<BufReader<File>>,
+}
+
+impl Archive {
+    /// The archive of the backup in `layout`, `None` when it is not a
+    /// compressed backup.
+    pub fn open_in(layout: &BackupLayout) -> SanupResult<Option<Self>> {
+        let path = layout.archive_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            zip: ZipArchive::new(BufReader::new(File::open(path)?))?,
+        }))
+    }
+
+    /// Original paths of the entries at or below `path`, directories before
+    /// what they contain.
+    pub fn under(&self, path: &Path) -> Vec<PathBuf> {
+        self.zip
+            .file_names()
+            .map(|name| Path::new("/").join(name.trim_end_matches('/')))
+            .filter(|original| original.starts_with(path))
+            .collect()
+    }
+
+    /// The stored file at `original`, `None` when the archive does not have
+    /// it.
+    pub fn file(&mut self, original: &Path) -> SanupResult<Option<ZipFile<'_, BufReader<File>>>> {
+        let name = entry_name(original)?;
+        let Some(idx) = self
+            .zip
+            .index_for_name(&name)
+            .or_else(|| self.zip.index_for_name(&format!("{}/", name)))
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(self.zip.by_index(idx)?))
+    }
+
+    /// Recreates the entry at `original` at `destination`. Returns the size of
+    /// regular files, which get their permissions back but not their
+    /// modification time, that is kept in the index.
+    pub fn extract(&mut self, original: &Path, destination: &Path) -> SanupResult<Option<u64>> {
+        let mut stored = self.file(original)?.ok_or_else(|| {
+            SanupError::Other(format!("{} is not in the archive", original.display()))
+        })?;
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if stored.is_dir() {
+            fs::create_dir_all(destination)?;
+            return Ok(None);
+        }
+
+        if stored.is_symlink() {
+            let mut target = String::new();
+            stored.read_to_string(&mut target)?;
+            if fs::symlink_metadata(destination).is_ok() {
+                fs::remove_file(destination)?;
+            }
+            symlink(target, destination)?;
+            return Ok(None);
+        }
+
+        let size = io::copy(&mut stored, &mut File::create(destination)?)?;
+        if let Some(mode) = stored.unix_mode() {
+            fs::set_permissions(destination, Permissions::from_mode(mode & 0o7777))?;
+        }
+
+        Ok(Some(size))
+    }
+}
+
+/// Name of the entry holding `original`. Zip names are text, so paths that
+/// are not valid UTF-8 cannot be stored.
+fn entry_name(original: &Path) -> SanupResult<String> {
+    relative_to_root(original)
+        .to_str()
+        .map(str::to_string)
+        .ok_or_else(|| {
+            SanupError::Other(format!(
+                "{} is not valid UTF-8, it cannot be stored in an archive",
+                original.display()
+            ))
+        })
+}
+
+/// The local time zip entries are dated with, `None` outside of the years
+/// zip can hold.
+fn zip_time(mtime: i64) -> Option<DateTime> {
+    let time = ChronoDateTime::from_timestamp(mtime, 0)?.with_timezone(&Local);
+
+    DateTime::from_date_and_time(
+        u16::try_from(time.year()).ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
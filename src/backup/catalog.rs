@@ -0,0 +1,115 @@
+use crate::{
+    backup::{kind::BackupKind, metadata::BackupMetadata},
+    config::Config,
+    paths,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+pub const CATALOG_FILE: &str = "catalog.toml";
+
+/// Metadata of every backup sanup has run, so history and chains are known
+/// without the target disks being mounted.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Catalog {
+    #[serde(default)]
+    backups: Vec<BackupMetadata>,
+}
+
+impl Config for Catalog {}
+
+impl Catalog {
+    pub fn default_path() -> PathBuf {
+        paths::data_dir().join(CATALOG_FILE)
+    }
+
+    pub fn backups(&self) -> &[BackupMetadata] {
+        &self.backups
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&BackupMetadata> {
+        self.backups.iter().find(|backup| backup.id() == id)
+    }
+
+    /// Adds a backup, replacing an earlier record of the same backup.
+    pub fn record(&mut self, metadata: BackupMetadata) {
+        match self
+            .backups
+            .iter_mut()
+            .find(|backup| backup.id() == metadata.id())
+        {
+            Some(backup) => *backup = metadata,
+            None => self.backups.push(metadata),
+        }
+    }
+
+    pub fn remove(&mut self, id: Uuid) -> Option<BackupMetadata> {
+        let idx = self.backups.iter().position(|backup| backup.id() == id)?;
+        Some(self.backups.remove(idx))
+    }
+
+    /// The backups `metadata` builds on, nearest parent first.
+    pub fn chain(&self, metadata: &BackupMetadata) -> Vec<BackupMetadata> {
+        let mut chain: Vec<BackupMetadata> = Vec::new();
+        let mut parent_id = metadata.parent_id();
+
+        while let Some(id) = parent_id {
+            let Some(parent) = self.get(id) else {
+                break;
+            };
+            if chain.iter().any(|backup| backup.id() == id) {
+                break;
+            }
+            parent_id = parent.parent_id();
+            chain.push(parent.clone());
+        }
+
+        chain
+    }
+
+    /// Completed backups of a profile on one disk, oldest first.
+    pub fn on_disk(&self, profile_id: Uuid, disk_id: Uuid) -> Vec<&BackupMetadata> {
+        let mut backups: Vec<&BackupMetadata> = self
+            .backups
+            .iter()
+            .filter(|backup| backup.profile_id() == Some(profile_id))
+            .filter(|backup| backup.disk_id() == Some(disk_id))
+            .filter(|backup| backup.is_completed())
+            .collect();
+        backups.sort_by_key(|backup| backup.created_at());
+        backups
+    }
+
+    /// The backup a new run of `kind` should be based on. Chains never cross
+    /// disks: every disk in a rotation has its own full backup to build on.
+    /// Compressed backups are never built on, their files are in an archive
+    /// other backups cannot point into.
+    pub fn parent_for(
+        &self,
+        profile_id: Uuid,
+        kind: BackupKind,
+        disk_id: Uuid,
+    ) -> Option<&BackupMetadata> {
+        let backups = self.on_disk(profile_id, disk_id);
+
+        match kind {
+            BackupKind::Incremental => backups
+                .into_iter()
+                .rev()
+                .find(|backup| backup.kind() != BackupKind::Compressed),
+            BackupKind::Differential => backups
+                .into_iter()
+                .rev()
+                .find(|backup| backup.kind() == BackupKind::Full),
+            BackupKind::Full | BackupKind::Mirror | BackupKind::Compressed => None,
+        }
+    }
+
+    pub fn last_backup_at(&self, profile_id: Uuid, disk_id: Uuid) -> Option<DateTime<Utc>> {
+        self.on_disk(profile_id, disk_id)
+            .last()
+            .map(|backup| backup.created_at())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{
+    app::hash_algorithm::HashAlgorithm,
+    backup::{archive::Archive, index::FileIndex, layout::BackupLayout},
+    error::{SanupError, SanupResult},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, Read},
+};
+
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Hashes the files a backup stores itself, in the order of its index, each
+/// by its original path followed by its content. Files kept in a parent
+/// backup are left out since their data is covered by the parent's checksum.
+/// For compressed backups it is the content in their archive that is hashed,
+/// so the checksum does not depend on how it was compressed.
+///
+/// The result is the algorithm name and the hex digest, e.g. `sha256:ab12…`.
+pub fn checksum(
+    layout: &BackupLayout,
+    index: &FileIndex,
+    algorithm: &HashAlgorithm,
+) -> SanupResult<String> {
+    let mut hasher = match algorithm {
+        HashAlgorithm::Sha256 => Sha256::new(),
+    };
+    let mut archive = Archive::open_in(layout)?;
+
+    for (path, entry) in index.iter() {
+        if entry.stored_in.is_some() {
+            continue;
+        }
+
+        hasher.update(path.as_os_str().as_encoded_bytes());
+        match archive.as_mut() {
+            Some(archive) => {
+                let file = archive.file(path)?.ok_or_else(|| {
+                    SanupError::Other(format!("{} is not in the archive", path.display()))
+                })?;
+                hash_content(&mut hasher, file)?;
+            }
+            None => hash_content(&mut hasher, File::open(layout.stored_path(path))?)?,
+        }
+    }
+
+    let digest: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    Ok(format!(
+        "{}:{}",
+        algorithm.to_string().to_lowercase(),
+        digest
+    ))
+}
+
+fn hash_content(hasher: &mut Sha256, mut file: impl Read) -> io::Result<()> {
+    let mut buf = [0; BUF_SIZE];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..read]);
+    }
+}
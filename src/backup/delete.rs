@@ -0,0 +1,314 @@
+use crate::{
+    backup::{
+        catalog::Catalog, disk::BackupDisk, index::FileIndex, kind::BackupKind,
+        layout::BackupLayout, metadata::BackupMetadata, status::BackupStatus,
+    },
+    config::Config,
+    error::{SanupError, SanupResult},
+};
+use log::info;
+use std::{
+    ffi::OsString,
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+};
+use uuid::Uuid;
+
+/// Backups that build on backup `id`, directly or further down the chain.
+/// Failed and cancelled runs do not count, nothing can be restored from them.
+pub fn dependents(catalog: &Catalog, id: Uuid) -> Vec<&BackupMetadata> {
+    catalog
+        .backups()
+        .iter()
+        .filter(|backup| {
+            !matches!(
+                backup.status(),
+                BackupStatus::Failed { .. } | BackupStatus::Cancelled
+            )
+        })
+        .filter(|backup| catalog.chain(backup).iter().any(|parent| parent.id() == id))
+        .collect()
+}
+
+/// A backup that builds directly on the deleted one and takes over the files
+/// it needs from it.
+struct Merge {
+    metadata: BackupMetadata,
+    root: PathBuf,
+    /// Backups further down the chain, which point at the merged one from
+    /// now on.
+    descendants: Vec<PathBuf>,
+}
+
+/// Everything a delete needs, gathered from the catalog up front so the
+/// worker only touches the file system.
+pub struct DeletePlan {
+    metadata: BackupMetadata,
+    root: PathBuf,
+    merges: Vec<Merge>,
+}
+
+impl DeletePlan {
+    /// Plans deleting backup `id`. Backups that depend on it make this fail
+    /// unless `merge` is set, in which case the files they need are moved
+    /// into the backups that build directly on it.
+    pub fn new(
+        catalog: &Catalog,
+        id: Uuid,
+        disks: &[BackupDisk],
+        merge: bool,
+    ) -> SanupResult<Self> {
+        let metadata = catalog
+            .get(id)
+            .ok_or_else(|| SanupError::Other(format!("No backup with id {}", id)))?
+            .clone();
+        let root = metadata.locate(disks)?;
+
+        let dependents = dependents(catalog, id);
+        if !dependents.is_empty() && !merge {
+            return Err(SanupError::Other(format!(
+                "{} backups depend on {}, merge it into them to delete it",
+                dependents.len(),
+                metadata.name()
+            )));
+        }
+
+        let mut merges = Vec::new();
+        for child in dependents
+            .iter()
+            .filter(|backup| backup.parent_id() == Some(id))
+        {
+            let descendants = dependents
+                .iter()
+                .filter(|backup| {
+                    catalog
+                        .chain(backup)
+                        .iter()
+                        .any(|parent| parent.id() == child.id())
+                })
+                .map(|backup| backup.locate(disks))
+                .collect::<SanupResult<Vec<_>>>()?;
+
+            merges.push(Merge {
+                metadata: (*child).clone(),
+                root: child.locate(disks)?,
+                descendants,
+            });
+        }
+
+        Ok(Self {
+            metadata,
+            root,
+            merges,
+        })
+    }
+
+    pub fn metadata(&self) -> &BackupMetadata {
+        &self.metadata
+    }
+}
+
+/// What a finished delete did.
+pub struct DeleteReport {
+    pub deleted: BackupMetadata,
+    /// Dependent backups after taking over files from the deleted one.
+    pub merged: Vec<BackupMetadata>,
+    pub reclaimed_bytes: u64,
+}
+
+/// A delete running in its own thread.
+pub struct DeleteTask {
+    metadata: BackupMetadata,
+    handler: JoinHandle<SanupResult<DeleteReport>>,
+}
+
+impl DeleteTask {
+    pub fn spawn(plan: DeletePlan) -> SanupResult<Self> {
+        let metadata = plan.metadata.clone();
+        let handler = thread::Builder::new()
+            .name(format!("delete-{}", metadata.id()))
+            .spawn(move || run(plan))?;
+
+        Ok(Self { metadata, handler })
+    }
+
+    pub fn metadata(&self) -> &BackupMetadata {
+        &self.metadata
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handler.is_finished()
+    }
+
+    /// Waits for the delete and returns its outcome.
+    pub fn join(self) -> SanupResult<DeleteReport> {
+        self.handler
+            .join()
+            .map_err(|_| SanupError::Other("The delete worker panicked".to_string()))?
+    }
+}
+
+fn run(plan: DeletePlan) -> SanupResult<DeleteReport> {
+    let layout = BackupLayout::new(&plan.root);
+
+    let mut staged = Staged::default();
+    let merged = match plan
+        .merges
+        .into_iter()
+        .map(|merge| merge_into(&plan.metadata, &layout, merge, &mut staged))
+        .collect::<SanupResult<Vec<_>>>()
+    {
+        Ok(merged) => merged,
+        Err(err) => {
+            staged.discard();
+            return Err(err);
+        }
+    };
+    staged.commit()?;
+    for metadata in &merged {
+        info!(
+            "Merged {} into {}",
+            plan.root.display(),
+            metadata.target_path().display()
+        );
+    }
+
+    let reclaimed_bytes = remove_backup_dir(&plan.root)?;
+    info!(
+        "Deleted backup {}, reclaimed {} bytes",
+        plan.root.display(),
+        reclaimed_bytes
+    );
+
+    Ok(DeleteReport {
+        deleted: plan.metadata,
+        merged,
+        reclaimed_bytes,
+    })
+}
+
+/// Files a merge writes next to the ones they replace. Nothing a backup reads
+/// changes until every merge of a delete is staged, so a failing one leaves
+/// all dependents as they were.
+#[derive(Default)]
+struct Staged {
+    /// Staged file and the file it replaces.
+    rewrites: Vec<(PathBuf, PathBuf)>,
+    /// Data files given to dependents, which nothing refers to until the
+    /// rewrites are committed.
+    added: Vec<PathBuf>,
+}
+
+impl Staged {
+    fn save<C: Config>(&mut self, config: &C, path: PathBuf) -> SanupResult<()> {
+        let mut staged = OsString::from(path.as_os_str());
+        staged.push(".merging");
+        let staged = PathBuf::from(staged);
+
+        config.save(&staged)?;
+        self.rewrites.push((staged, path));
+        Ok(())
+    }
+
+    fn commit(self) -> SanupResult<()> {
+        for (staged, path) in self.rewrites {
+            fs::rename(staged, path)?;
+        }
+        Ok(())
+    }
+
+    fn discard(self) {
+        for path in self
+            .rewrites
+            .into_iter()
+            .map(|(staged, _)| staged)
+            .chain(self.added)
+        {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Gives `merge` its own copy of the files it takes from the deleted backup,
+/// hard linked where the file system allows, and hooks it onto the deleted
+/// backup's parent. Its index and manifest and those of the backups below it
+/// are only staged.
+fn merge_into(
+    deleted: &BackupMetadata,
+    layout: &BackupLayout,
+    merge: Merge,
+    staged: &mut Staged,
+) -> SanupResult<BackupMetadata> {
+    let Merge {
+        mut metadata,
+        root,
+        descendants,
+    } = merge;
+    let child_layout = BackupLayout::new(&root);
+
+    let mut index = FileIndex::load(child_layout.index_path())?;
+    for file in index.stored_in(deleted.id()) {
+        let from = layout.stored_path(&file);
+        let to = child_layout.stored_path(&file);
+        if to.exists() {
+            continue;
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::hard_link(&from, &to).is_err() {
+            fs::copy(&from, &to)?;
+        }
+        staged.added.push(to);
+    }
+    index.move_storage(deleted.id(), None);
+    staged.save(&index, child_layout.index_path())?;
+
+    for descendant in descendants {
+        let descendant_layout = BackupLayout::new(&descendant);
+        let mut index = FileIndex::load(descendant_layout.index_path())?;
+        index.move_storage(deleted.id(), Some(metadata.id()));
+        staged.save(&index, descendant_layout.index_path())?;
+    }
+
+    metadata.set_parent_id(deleted.parent_id());
+    if deleted.parent_id().is_none() {
+        metadata.set_kind(BackupKind::Full);
+    }
+    staged.save(&metadata, child_layout.manifest_path())?;
+
+    Ok(metadata)
+}
+
+/// Removes a backup directory and returns the space this freed. Files still
+/// linked from another backup do not count.
+pub fn remove_backup_dir(root: &Path) -> SanupResult<u64> {
+    if !root.exists() {
+        return Ok(0);
+    }
+
+    let reclaimed_bytes = unlinked_size(root)?;
+    fs::remove_dir_all(root)?;
+
+    Ok(reclaimed_bytes)
+}
+
+fn unlinked_size(path: &Path) -> SanupResult<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(if metadata.nlink() == 1 {
+            metadata.len()
+        } else {
+            0
+        });
+    }
+
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += unlinked_size(&entry?.path())?;
+    }
+
+    Ok(size)
+}
@@ -0,0 +1,249 @@
+use crate::{backup::disk::BackupDisk, error::SanupResult};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const SECTOR_SIZE: u64 = 512;
+
+/// One line of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountEntry {
+    pub device_number: String,
+    pub mount_path: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+}
+
+/// A block device or partition found in `/sys/block`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockDevice {
+    pub name: String,
+    pub device_number: String,
+    pub size_bytes: u64,
+    pub removable: bool,
+    pub uuid: Option<String>,
+    pub label: Option<String>,
+}
+
+/// Finds mounted filesystems and block devices.
+///
+/// The locations of `mountinfo`, `/sys/block` and `/dev/disk` can be replaced,
+/// so recorded fixtures can be fed in instead of the running system.
+pub struct DiskDiscovery {
+    mountinfo: PathBuf,
+    sys_block: PathBuf,
+    dev_disk: PathBuf,
+}
+
+impl DiskDiscovery {
+    pub fn new<P: AsRef<Path>>(mountinfo: P, sys_block: P, dev_disk: P) -> Self {
+        Self {
+            mountinfo: mountinfo.as_ref().to_path_buf(),
+            sys_block: sys_block.as_ref().to_path_buf(),
+            dev_disk: dev_disk.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn mounts(&self) -> SanupResult<Vec<MountEntry>> {
+        Ok(parse_mountinfo(&fs::read_to_string(&self.mountinfo)?))
+    }
+
+    /// Lists whole disks and their partitions. A partition is removable when
+    /// the disk it belongs to is.
+    pub fn block_devices(&self) -> SanupResult<Vec<BlockDevice>> {
+        let uuids = self.read_links("by-uuid");
+        let labels = self.read_links("by-label");
+        let mut devices = Vec::new();
+
+        let mut disks: Vec<PathBuf> = fs::read_dir(&self.sys_block)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        disks.sort();
+
+        for disk in disks {
+            let removable = read_trimmed(disk.join("removable")).as_deref() == Some("1")
+                || fs::canonicalize(&disk)
+                    .is_ok_and(|path| path.to_string_lossy().contains("/usb"));
+
+            let mut paths = vec![disk.clone()];
+            if let Ok(read_dir) = fs::read_dir(&disk) {
+                let mut partitions: Vec<PathBuf> = read_dir
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.join("partition").exists())
+                    .collect();
+                partitions.sort();
+                paths.extend(partitions);
+            }
+
+            for path in paths {
+                let Some(name) = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                else {
+                    continue;
+                };
+
+                devices.push(BlockDevice {
+                    device_number: read_trimmed(path.join("dev")).unwrap_or_default(),
+                    size_bytes: read_trimmed(path.join("size"))
+                        .and_then(|size| size.parse::<u64>().ok())
+                        .unwrap_or(0)
+                        * SECTOR_SIZE,
+                    removable,
+                    uuid: uuids.get(&name).cloned(),
+                    label: labels.get(&name).cloned(),
+                    name,
+                });
+            }
+        }
+
+        Ok(devices)
+    }
+
+    /// Mounted filesystems that live on a block device, as backup disks.
+    /// Capacity and free space are queried from the mount path.
+    pub fn disks(&self) -> SanupResult<Vec<BackupDisk>> {
+        let devices = self.block_devices()?;
+        let by_number: HashMap<&str, &BlockDevice> = devices
+            .iter()
+            .map(|device| (device.device_number.as_str(), device))
+            .collect();
+
+        let mut disks = Vec::new();
+        for mount in self.mounts()? {
+            let Some(device) = by_number.get(mount.device_number.as_str()) else {
+                continue;
+            };
+
+            let label = device.label.clone().unwrap_or_else(|| {
+                mount
+                    .mount_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| mount.mount_path.to_string_lossy().to_string())
+            });
+
+            let mut disk = BackupDisk::new(&label, &mount.mount_path);
+            disk.set_device(&mount.source, &mount.fs_type, device.removable);
+            disk.set_fs_uuid(device.uuid.clone());
+            let _ = disk.refresh_space();
+
+            disks.push(disk);
+        }
+
+        Ok(disks)
+    }
+
+    /// The mounted disk `path` lives on, the one with the longest mount path
+    /// containing it.
+    pub fn disk_for<P: AsRef<Path>>(&self, path: P) -> SanupResult<Option<BackupDisk>> {
+        let path = path.as_ref();
+
+        Ok(self
+            .disks()?
+            .into_iter()
+            .filter(|disk| path.starts_with(disk.mount_path()))
+            .max_by_key(|disk| disk.mount_path().components().count()))
+    }
+
+    /// Removable block devices that are not mounted anywhere.
+    pub fn unmounted_removable(&self) -> SanupResult<Vec<BlockDevice>> {
+        let mounts = self.mounts()?;
+
+        Ok(self
+            .block_devices()?
+            .into_iter()
+            .filter(|device| device.removable)
+            .filter(|device| {
+                !mounts
+                    .iter()
+                    .any(|mount| mount.device_number == device.device_number)
+            })
+            .collect())
+    }
+
+    /// Maps device names to the names of the `/dev/disk/<kind>` links that
+    /// point at them.
+    fn read_links(&self, kind: &str) -> HashMap<String, String> {
+        let Ok(read_dir) = fs::read_dir(self.dev_disk.join(kind)) else {
+            return HashMap::new();
+        };
+
+        read_dir
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let target = fs::read_link(entry.path()).ok()?;
+                let device = target.file_name()?.to_string_lossy().to_string();
+                let name = unescape_udev(&entry.file_name().to_string_lossy());
+                Some((device, name))
+            })
+            .collect()
+    }
+}
+
+impl Default for DiskDiscovery {
+    fn default() -> Self {
+        Self::new("/proc/self/mountinfo", "/sys/block", "/dev/disk")
+    }
+}
+
+/// Parses `/proc/self/mountinfo`, see `proc_pid_mountinfo(5)`.
+pub fn parse_mountinfo(content: &str) -> Vec<MountEntry> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (mount, filesystem) = line.split_once(" - ")?;
+            let mount: Vec<&str> = mount.split(' ').collect();
+            let mut filesystem = filesystem.split(' ');
+
+            Some(MountEntry {
+                device_number: mount.get(2)?.to_string(),
+                mount_path: PathBuf::from(unescape_mountinfo(mount.get(4)?)),
+                fs_type: filesystem.next()?.to_string(),
+                source: unescape_mountinfo(filesystem.next()?),
+            })
+        })
+        .collect()
+}
+
+/// Mountinfo escapes space, tab, newline and backslash as `\ooo`.
+fn unescape_mountinfo(value: &str) -> String {
+    unescape(value, "\\", 3, 8)
+}
+
+/// udev escapes unsafe characters in link names as `\xHH`.
+fn unescape_udev(value: &str) -> String {
+    unescape(value, "\\x", 2, 16)
+}
+
+fn unescape(value: &str, prefix: &str, len: usize, radix: u32) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i..].starts_with(prefix.as_bytes()) {
+            let start = i + prefix.len();
+            if let Some(code) = bytes.get(start..start + len)
+                && let Ok(code) = std::str::from_utf8(code)
+                && let Ok(byte) = u8::from_str_radix(code, radix)
+            {
+                result.push(byte);
+                i = start + len;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&result).to_string()
+}
+
+fn read_trimmed<P: AsRef<Path>>(path: P) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+}
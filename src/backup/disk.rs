@@ -1,11 +1,183 @@
-use std::path::PathBuf;
+use crate::{
+    backup::{discovery::DiskDiscovery, preflight::filesystem_space},
+    config::Config,
+    error::SanupResult,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 use uuid::Uuid;
 
-pub struct BackupDisk {
+/// Written to the root of removable disks sanup backs up to, so the disk
+/// keeps its identity across mount points and machines.
+pub const MARKER_FILE: &str = ".sanup-disk.toml";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DiskMarker {
     id: Uuid,
+    label: String,
+    created_at: DateTime<Utc>,
+}
+
+impl Config for DiskMarker {}
+
+impl DiskMarker {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+impl Default for DiskMarker {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            label: String::new(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// A mounted filesystem backups are written to.
+///
+/// The id follows the physical disk rather than where it is mounted: it is
+/// taken from the marker file at the disk root, else derived from the
+/// filesystem UUID, and only as a last resort from the mount path.
+#[derive(Clone)]
+pub struct BackupDisk {
     label: String,
     mount_path: PathBuf,
+    device: String,
+    fs_type: String,
+    fs_uuid: Option<String>,
+    removable: bool,
+    marker: Option<DiskMarker>,
     total_capacity_bytes: u64,
     free_space_bytes: u64,
 }
+
+impl BackupDisk {
+    pub fn new<P: AsRef<Path>>(label: &str, mount_path: P) -> Self {
+        let mount_path = mount_path.as_ref().to_path_buf();
+        let marker_path = mount_path.join(MARKER_FILE);
+        let marker = marker_path
+            .exists()
+            .then(|| DiskMarker::load(&marker_path).ok())
+            .flatten();
+
+        Self {
+            label: label.to_string(),
+            mount_path,
+            device: String::new(),
+            fs_type: String::new(),
+            fs_uuid: None,
+            removable: false,
+            marker,
+            total_capacity_bytes: 0,
+            free_space_bytes: 0,
+        }
+    }
+
+    /// Finds the mounted disk `path` lives on, with its capacity and free
+    /// space filled in. Falls back to treating `path` itself as the disk
+    /// when it is not on a discovered block device.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> SanupResult<Self> {
+        let path = path.as_ref();
+
+        let disk = DiskDiscovery::default().disk_for(path).ok().flatten();
+        let mut disk = disk.unwrap_or_else(|| Self::new(&path.to_string_lossy(), path));
+        disk.refresh_space()?;
+        Ok(disk)
+    }
+
+    pub fn refresh_space(&mut self) -> SanupResult<()> {
+        let space = filesystem_space(&self.mount_path)?;
+        self.total_capacity_bytes = space.total_bytes;
+        self.free_space_bytes = space.free_bytes;
+        Ok(())
+    }
+
+    pub fn set_device(&mut self, device: &str, fs_type: &str, removable: bool) {
+        self.device = device.to_string();
+        self.fs_type = fs_type.to_string();
+        self.removable = removable;
+    }
+
+    pub fn set_fs_uuid(&mut self, fs_uuid: Option<String>) {
+        self.fs_uuid = fs_uuid;
+    }
+
+    /// Writes the marker file to the disk root, keeping the current id. Does
+    /// nothing if the disk already has one.
+    pub fn write_marker(&mut self) -> SanupResult<()> {
+        if self.marker.is_some() {
+            return Ok(());
+        }
+
+        let marker = DiskMarker {
+            id: self.id(),
+            label: self.label.clone(),
+            created_at: Utc::now(),
+        };
+        marker.save(self.mount_path.join(MARKER_FILE))?;
+        self.marker = Some(marker);
+
+        Ok(())
+    }
+
+    pub fn id(&self) -> Uuid {
+        if let Some(marker) = &self.marker {
+            return marker.id();
+        }
+
+        let name = match &self.fs_uuid {
+            Some(fs_uuid) => format!("fs-uuid:{}", fs_uuid),
+            None => format!("mount:{}", self.mount_path.to_string_lossy()),
+        };
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+    }
+
+    pub fn marker(&self) -> Option<&DiskMarker> {
+        self.marker.as_ref()
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn mount_path(&self) -> &Path {
+        &self.mount_path
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    pub fn fs_type(&self) -> &str {
+        &self.fs_type
+    }
+
+    pub fn fs_uuid(&self) -> Option<&str> {
+        self.fs_uuid.as_deref()
+    }
+
+    pub fn removable(&self) -> bool {
+        self.removable
+    }
+
+    pub fn total_capacity_bytes(&self) -> u64 {
+        self.total_capacity_bytes
+    }
+
+    pub fn free_space_bytes(&self) -> u64 {
+        self.free_space_bytes
+    }
+}
@@ -0,0 +1,175 @@
+use crate::error::SanupResult;
+use ignore::{
+    Walk, WalkBuilder,
+    gitignore::{Gitignore, GitignoreBuilder},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+pub const SANUPIGNORE_FILE: &str = ".sanupignore";
+
+/// Gitignore-style selection of the files inside the backup sources.
+///
+/// `exclude` and `include` take gitignore patterns relative to each source.
+/// Includes are applied after excludes, like a `!pattern` line, so
+/// `exclude = ["*.iso"]` with `include = ["keep.iso"]` still backs up
+/// `keep.iso`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BackupFilter {
+    exclude: Vec<String>,
+    include: Vec<String>,
+    use_gitignore: bool,
+    use_sanupignore: bool,
+}
+
+impl BackupFilter {
+    pub fn new(exclude: Vec<String>, include: Vec<String>) -> Self {
+        Self {
+            exclude,
+            include,
+            ..Self::default()
+        }
+    }
+
+    pub fn exclude(&self) -> &[String] {
+        &self.exclude
+    }
+
+    pub fn include(&self) -> &[String] {
+        &self.include
+    }
+
+    pub fn use_gitignore(&self) -> bool {
+        self.use_gitignore
+    }
+
+    pub fn use_sanupignore(&self) -> bool {
+        self.use_sanupignore
+    }
+
+    pub fn set_use_gitignore(&mut self, use_gitignore: bool) {
+        self.use_gitignore = use_gitignore;
+    }
+
+    pub fn set_use_sanupignore(&mut self, use_sanupignore: bool) {
+        self.use_sanupignore = use_sanupignore;
+    }
+
+    pub fn matcher<P: AsRef<Path>>(&self, source: P) -> SanupResult<Gitignore> {
+        let mut builder = GitignoreBuilder::new(source);
+
+        for pattern in &self.exclude {
+            builder.add_line(None, pattern)?;
+        }
+        for pattern in &self.include {
+            builder.add_line(None, &format!("!{}", pattern))?;
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Walks `source` in file name order, skipping everything the filter
+    /// excludes. Excluded directories are not descended into.
+    pub fn walk<P: AsRef<Path>>(&self, source: P) -> SanupResult<Walk> {
+        let source = source.as_ref();
+        let matcher = self.matcher(source)?;
+
+        let mut builder = WalkBuilder::new(source);
+        builder
+            .standard_filters(false)
+            .git_ignore(self.use_gitignore)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(move |entry| {
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                !matcher.matched(entry.path(), is_dir).is_ignore()
+            });
+
+        if self.use_sanupignore {
+            builder.add_custom_ignore_filename(SANUPIGNORE_FILE);
+        }
+
+        Ok(builder.build())
+    }
+
+    pub fn preview(&self, sources: &[PathBuf]) -> SanupResult<FilterPreview> {
+        let mut preview = FilterPreview::default();
+
+        for source in sources {
+            let mut included = HashSet::new();
+            for entry in self.walk(source)?.flatten() {
+                included.insert(entry.path().to_path_buf());
+            }
+
+            let all = WalkBuilder::new(source)
+                .standard_filters(false)
+                .sort_by_file_name(|a, b| a.cmp(b))
+                .build();
+
+            for entry in all.flatten() {
+                let path = entry.path();
+                let size = entry
+                    .metadata()
+                    .ok()
+                    .filter(|metadata| metadata.is_file())
+                    .map(|metadata| metadata.len());
+
+                if included.contains(path) {
+                    if let Some(size) = size {
+                        preview.included_files += 1;
+                        preview.included_bytes += size;
+                    }
+                    continue;
+                }
+
+                let matched = path
+                    .ancestors()
+                    .find(|ancestor| preview.matches.contains_key(*ancestor))
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| path.to_path_buf());
+                let excluded = preview.matches.entry(matched).or_default();
+
+                if let Some(size) = size {
+                    excluded.files += 1;
+                    excluded.bytes += size;
+                    preview.excluded_files += 1;
+                    preview.excluded_bytes += size;
+                }
+            }
+        }
+
+        Ok(preview)
+    }
+}
+
+impl Default for BackupFilter {
+    fn default() -> Self {
+        Self {
+            exclude: Vec::new(),
+            include: Vec::new(),
+            use_gitignore: false,
+            use_sanupignore: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExcludedSize {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// What a filter does to a set of sources: totals of the files that are kept
+/// and the excluded paths (the topmost path of every excluded subtree).
+#[derive(Debug, Clone, Default)]
+pub struct FilterPreview {
+    pub included_files: u64,
+    pub included_bytes: u64,
+    pub excluded_files: u64,
+    pub excluded_bytes: u64,
+    pub matches: BTreeMap<PathBuf, ExcludedSize>,
+}
@@ -0,0 +1,61 @@
+use crate::ui::input::enumvariants::EnumVariants;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Frequency {
+    /// Only run when asked to.
+    #[default]
+    Manual,
+    Hourly,
+    Daily,
+    Weekly,
+}
+
+impl EnumVariants for Frequency {
+    fn default(&self) -> Box<dyn EnumVariants> {
+        Box::new(Frequency::Manual)
+    }
+
+    fn longest(&self) -> String {
+        Frequency::Manual.to_string()
+    }
+
+    fn variants(&self) -> Vec<String> {
+        ["Manual", "Hourly", "Daily", "Weekly"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn from_string(&self, s: String) -> Box<dyn EnumVariants> {
+        Box::new(Frequency::from(s.as_str()))
+    }
+
+    fn clone_box(&self) -> Box<dyn EnumVariants> {
+        Box::new(*self)
+    }
+}
+
+impl From<&str> for Frequency {
+    fn from(value: &str) -> Self {
+        match value {
+            "Hourly" => Frequency::Hourly,
+            "Daily" => Frequency::Daily,
+            "Weekly" => Frequency::Weekly,
+            _ => Frequency::Manual,
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
+impl ToString for Frequency {
+    fn to_string(&self) -> String {
+        match self {
+            Frequency::Manual => "Manual",
+            Frequency::Hourly => "Hourly",
+            Frequency::Daily => "Daily",
+            Frequency::Weekly => "Weekly",
+        }
+        .to_string()
+    }
+}
@@ -0,0 +1,112 @@
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::Metadata,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+/// Size and modification time of a regular file when it was backed up, and
+/// which backup holds its data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub size: u64,
+    pub mtime: i64,
+    pub mtime_nsec: i64,
+    /// `None` when the file is stored in the backup the index belongs to,
+    /// otherwise the id of the earlier backup in the chain that has it.
+    #[serde(default)]
+    pub stored_in: Option<Uuid>,
+}
+
+impl IndexEntry {
+    pub fn new(metadata: &Metadata) -> Self {
+        Self {
+            size: metadata.len(),
+            mtime: metadata.mtime(),
+            mtime_nsec: metadata.mtime_nsec(),
+            stored_in: None,
+        }
+    }
+
+    /// A file counts as unchanged when size and modification time match,
+    /// like `rsync` assumes by default.
+    pub fn is_unchanged(&self, other: &IndexEntry) -> bool {
+        self.size == other.size && self.mtime == other.mtime && self.mtime_nsec == other.mtime_nsec
+    }
+}
+
+/// Every regular file a backup covers, by original path. Incremental and
+/// differential backups only copy files that changed since their parent and
+/// point at the backup holding the data for the rest.
+#[derive(Serialize, Deserialize, Default)]
+pub struct FileIndex {
+    #[serde(default)]
+    files: BTreeMap<PathBuf, IndexEntry>,
+}
+
+impl Config for FileIndex {}
+
+impl FileIndex {
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&IndexEntry> {
+        self.files.get(path.as_ref())
+    }
+
+    pub fn insert(&mut self, path: PathBuf, entry: IndexEntry) {
+        self.files.insert(path, entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&PathBuf, &IndexEntry)> {
+        self.files.iter()
+    }
+
+    /// Files at or below `path`.
+    pub fn under<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> impl Iterator<Item = (&'a PathBuf, &'a IndexEntry)> {
+        self.files
+            .range(path.to_path_buf()..)
+            .take_while(move |(file, _)| file.starts_with(path))
+    }
+
+    /// Files whose data is in backup `id`.
+    pub fn stored_in(&self, id: Uuid) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|(_, entry)| entry.stored_in == Some(id))
+            .map(|(file, _)| file.clone())
+            .collect()
+    }
+
+    /// Points the files stored in backup `from` at `to` instead.
+    pub fn move_storage(&mut self, from: Uuid, to: Option<Uuid>) {
+        for entry in self.files.values_mut() {
+            if entry.stored_in == Some(from) {
+                entry.stored_in = to;
+            }
+        }
+    }
+
+    /// Ids of the other backups this index points into.
+    pub fn referenced_backups(&self) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = self
+            .files
+            .values()
+            .filter_map(|entry| entry.stored_in)
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+}
@@ -1,3 +1,7 @@
+use crate::ui::input::enumvariants::EnumVariants;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BackupKind {
     Full,
     Incremental,
@@ -5,3 +9,61 @@ pub enum BackupKind {
     Mirror,
     Compressed,
 }
+
+impl EnumVariants for BackupKind {
+    fn default(&self) -> Box<dyn EnumVariants> {
+        Box::new(BackupKind::Full)
+    }
+
+    fn longest(&self) -> String {
+        BackupKind::Differential.to_string()
+    }
+
+    fn variants(&self) -> Vec<String> {
+        [
+            "Full",
+            "Incremental",
+            "Differential",
+            "Mirror",
+            "Compressed",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    fn from_string(&self, s: String) -> Box<dyn EnumVariants> {
+        Box::new(BackupKind::from(s.as_str()))
+    }
+
+    fn clone_box(&self) -> Box<dyn EnumVariants> {
+        Box::new(*self)
+    }
+}
+
+impl From<&str> for BackupKind {
+    fn from(value: &str) -> Self {
+        match value {
+            "Full" => BackupKind::Full,
+            "Incremental" => BackupKind::Incremental,
+            "Differential" => BackupKind::Differential,
+            "Mirror" => BackupKind::Mirror,
+            "Compressed" => BackupKind::Compressed,
+            _ => BackupKind::Full,
+        }
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
+impl ToString for BackupKind {
+    fn to_string(&self) -> String {
+        match self {
+            BackupKind::Full => "Full",
+            BackupKind::Incremental => "Incremental",
+            BackupKind::Differential => "Differential",
+            BackupKind::Mirror => "Mirror",
+            BackupKind::Compressed => "Compressed",
+        }
+        .to_string()
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::{Component, Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "backup.toml";
+pub const DATA_DIR: &str = "data";
+pub const INDEX_FILE: &str = "index.toml";
+pub const ARCHIVE_FILE: &str = "data.zip";
+
+/// Directory structure of a single backup on the target.
+///
+/// Every source is stored under `data/` by its absolute path with the root
+/// stripped, so `/etc` ends up in `data/etc` and `~/Documents` in
+/// `data/home/<user>/Documents`. The mapping only depends on the source path,
+/// which keeps it stable across runs and lets each source be restored alone.
+/// Compressed backups hold the same tree in `data.zip` instead of `data/`.
+pub struct BackupLayout {
+    root: PathBuf,
+}
+
+impl BackupLayout {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.root.join(MANIFEST_FILE)
+    }
+
+    pub fn index_path(&self) -> PathBuf {
+        self.root.join(INDEX_FILE)
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.root.join(DATA_DIR)
+    }
+
+    pub fn archive_path(&self) -> PathBuf {
+        self.root.join(ARCHIVE_FILE)
+    }
+
+    pub fn stored_path<P: AsRef<Path>>(&self, original: P) -> PathBuf {
+        self.data_dir().join(relative_to_root(original))
+    }
+
+    pub fn original_path<P: AsRef<Path>>(&self, stored: P) -> Option<PathBuf> {
+        stored
+            .as_ref()
+            .strip_prefix(self.data_dir())
+            .ok()
+            .map(|relative| Path::new("/").join(relative))
+    }
+}
+
+pub fn relative_to_root<P: AsRef<Path>>(path: P) -> PathBuf {
+    path.as_ref()
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+/// Makes the sources absolute, drops duplicates and sources already covered
+/// by another source, so nothing is copied twice.
+pub fn normalize_sources(sources: &[PathBuf]) -> Vec<PathBuf> {
+    let mut sources: Vec<PathBuf> = sources
+        .iter()
+        .map(|source| source.canonicalize().unwrap_or_else(|_| source.clone()))
+        .collect();
+
+    sources.sort();
+    sources.dedup();
+
+    let mut normalized: Vec<PathBuf> = Vec::new();
+    for source in sources {
+        if !normalized.iter().any(|parent| source.starts_with(parent)) {
+            normalized.push(source);
+        }
+    }
+
+    normalized
+}
@@ -1 +1,26 @@
-pub struct Message {}
+use crate::backup::metadata::BackupMetadata;
+
+pub enum Message {
+    Pause,
+    Resume,
+    Cancel,
+}
+
+pub enum WorkerEvent {
+    Progress {
+        progress: f32,
+        current_file: String,
+        done_bytes: u64,
+        total_bytes: u64,
+    },
+    /// A file could not be backed up, the backup goes on.
+    FileFailed(String),
+    Paused,
+    Resumed,
+    Completed(Box<BackupMetadata>),
+    Failed {
+        metadata: Box<BackupMetadata>,
+        reason: String,
+    },
+    Cancelled(Box<BackupMetadata>),
+}
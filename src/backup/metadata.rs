@@ -1,23 +1,419 @@
-use std::path::PathBuf;
-
+use crate::{
+    app::{
+        compression_kind::CompressionKind, compression_level::CompressionLevel,
+        hash_algorithm::HashAlgorithm,
+    },
+    backup::{
+        disk::BackupDisk,
+        filter::BackupFilter,
+        kind::BackupKind,
+        rules::{SelectionRules, SkipReason, SkippedCounts},
+        status::BackupStatus,
+        verify::Verification,
+    },
+    config::Config,
+    error::{SanupError, SanupResult},
+};
 use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-use crate::backup::kind::BackupKind;
-
+#[derive(Serialize, Deserialize, Clone)]
 pub struct BackupMetadata {
     id: Uuid,
     name: String,
     kind: BackupKind,
-    source_path: PathBuf,
+    /// Profile the backup was run from, if any.
+    #[serde(default)]
+    profile_id: Option<Uuid>,
+    /// Backup this one only stores the changes against. Full backups have
+    /// none, incremental ones point at the previous backup on the same disk
+    /// and differential ones at the last full backup there.
+    #[serde(default)]
+    parent_id: Option<Uuid>,
+    source_paths: Vec<PathBuf>,
     target_path: PathBuf,
+    /// Disk the backup was written to and where on it, so the backup can be
+    /// found again when the disk is mounted somewhere else.
+    #[serde(default)]
+    disk_id: Option<Uuid>,
+    #[serde(default)]
+    disk_path: Option<PathBuf>,
+    #[serde(default)]
+    filter: BackupFilter,
+    #[serde(default)]
+    rules: SelectionRules,
+    estimated_size_bytes: Option<u64>,
     file_count: u64,
     total_size_bytes: u64,
+    /// Compressed backups are stored as one archive instead of a copy of the
+    /// files.
+    #[serde(default)]
+    compression: CompressionKind,
+    #[serde(default)]
+    compression_level: CompressionLevel,
+    /// Algorithm the stored files are hashed with once copied, if any.
+    #[serde(default)]
+    hash_algorithm: Option<HashAlgorithm>,
     archive_checksum: Option<String>,
+    /// When the backed up state was taken. Synthetic full backups keep the
+    /// one of the backup they consolidate, when they were built is in
+    /// `started_at` and `finished_at`.
     created_at: DateTime<Utc>,
     started_at: Option<DateTime<Utc>>,
     finished_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "duration_ms")]
     duration: Option<Duration>,
     failed_files: Vec<String>,
+    #[serde(default)]
+    skipped: SkippedCounts,
+    /// Files that were unchanged since the parent and not copied again.
+    #[serde(default)]
+    unchanged_files: u64,
+    #[serde(default)]
+    status: BackupStatus,
+    /// Backup whose content this synthetic full backup consolidates. Only
+    /// that backup is superseded by it, the ones it builds on hold earlier
+    /// states and are still kept or removed by the retention rules.
+    #[serde(default)]
+    synthesized_from: Option<Uuid>,
+    #[serde(default)]
+    verification: Option<Verification>,
     note: Option<String>,
 }
+
+impl Config for BackupMetadata {}
+
+impl BackupMetadata {
+    pub fn new<P: AsRef<Path>>(
+        name: &str,
+        kind: BackupKind,
+        source_paths: Vec<PathBuf>,
+        target_path: P,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            source_paths,
+            target_path: target_path.as_ref().to_path_buf(),
+            ..Self::default()
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> BackupKind {
+        self.kind
+    }
+
+    pub fn profile_id(&self) -> Option<Uuid> {
+        self.profile_id
+    }
+
+    pub fn parent_id(&self) -> Option<Uuid> {
+        self.parent_id
+    }
+
+    pub fn source_paths(&self) -> &[PathBuf] {
+        &self.source_paths
+    }
+
+    pub fn target_path(&self) -> &Path {
+        &self.target_path
+    }
+
+    pub fn disk_id(&self) -> Option<Uuid> {
+        self.disk_id
+    }
+
+    pub fn disk_path(&self) -> Option<&Path> {
+        self.disk_path.as_deref()
+    }
+
+    /// Where the backup is now if it lives on `disk`.
+    pub fn located_on(&self, disk: &BackupDisk) -> Option<PathBuf> {
+        if self.disk_id != Some(disk.id()) {
+            return None;
+        }
+
+        self.disk_path
+            .as_ref()
+            .map(|disk_path| disk.mount_path().join(disk_path))
+    }
+
+    /// Where the backup is now: on whichever of the mounted `disks` it was
+    /// made to, else at its original target path. Fails when its disk is not
+    /// mounted and nothing is at the target path.
+    pub fn locate(&self, disks: &[BackupDisk]) -> SanupResult<PathBuf> {
+        if let Some(path) = disks.iter().find_map(|disk| self.located_on(disk)) {
+            return Ok(path);
+        }
+
+        if self.disk_id.is_none() || self.target_path.exists() {
+            Ok(self.target_path.clone())
+        } else {
+            Err(SanupError::Other(format!(
+                "Backup {} is on a disk that is not mounted",
+                self.name
+            )))
+        }
+    }
+
+    pub fn filter(&self) -> &BackupFilter {
+        &self.filter
+    }
+
+    pub fn rules(&self) -> &SelectionRules {
+        &self.rules
+    }
+
+    pub fn estimated_size_bytes(&self) -> Option<u64> {
+        self.estimated_size_bytes
+    }
+
+    pub fn file_count(&self) -> u64 {
+        self.file_count
+    }
+
+    pub fn total_size_bytes(&self) -> u64 {
+        self.total_size_bytes
+    }
+
+    pub fn compression(&self) -> &CompressionKind {
+        &self.compression
+    }
+
+    pub fn compression_level(&self) -> &CompressionLevel {
+        &self.compression_level
+    }
+
+    pub fn hash_algorithm(&self) -> Option<&HashAlgorithm> {
+        self.hash_algorithm.as_ref()
+    }
+
+    pub fn archive_checksum(&self) -> Option<&str> {
+        self.archive_checksum.as_deref()
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.started_at
+    }
+
+    pub fn finished_at(&self) -> Option<DateTime<Utc>> {
+        self.finished_at
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    pub fn failed_files(&self) -> &[String] {
+        &self.failed_files
+    }
+
+    pub fn skipped(&self) -> &SkippedCounts {
+        &self.skipped
+    }
+
+    pub fn unchanged_files(&self) -> u64 {
+        self.unchanged_files
+    }
+
+    pub fn status(&self) -> &BackupStatus {
+        &self.status
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.status == BackupStatus::Completed
+    }
+
+    pub fn synthesized_from(&self) -> Option<Uuid> {
+        self.synthesized_from
+    }
+
+    pub fn verification(&self) -> Option<&Verification> {
+        self.verification.as_ref()
+    }
+
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    pub fn set_kind(&mut self, kind: BackupKind) {
+        self.kind = kind;
+    }
+
+    pub fn set_profile_id(&mut self, profile_id: Option<Uuid>) {
+        self.profile_id = profile_id;
+    }
+
+    pub fn set_parent_id(&mut self, parent_id: Option<Uuid>) {
+        self.parent_id = parent_id;
+    }
+
+    pub fn set_source_paths(&mut self, source_paths: Vec<PathBuf>) {
+        self.source_paths = source_paths;
+    }
+
+    /// Points the backup at where it is now, e.g. on a disk mounted somewhere
+    /// else than when it was made.
+    pub fn set_target_path(&mut self, target_path: PathBuf) {
+        self.target_path = target_path;
+    }
+
+    /// Records the disk the target is on.
+    pub fn set_disk(&mut self, disk: &BackupDisk) {
+        self.disk_id = Some(disk.id());
+        self.disk_path = self
+            .target_path
+            .strip_prefix(disk.mount_path())
+            .ok()
+            .map(Path::to_path_buf);
+    }
+
+    /// Records the disk the target is on without the disk at hand.
+    pub fn set_disk_location(&mut self, disk_id: Option<Uuid>, disk_path: Option<PathBuf>) {
+        self.disk_id = disk_id;
+        self.disk_path = disk_path;
+    }
+
+    pub fn set_filter(&mut self, filter: BackupFilter) {
+        self.filter = filter;
+    }
+
+    pub fn set_rules(&mut self, rules: SelectionRules) {
+        self.rules = rules;
+    }
+
+    pub fn set_estimated_size_bytes(&mut self, estimated_size_bytes: Option<u64>) {
+        self.estimated_size_bytes = estimated_size_bytes;
+    }
+
+    pub fn set_created_at(&mut self, created_at: DateTime<Utc>) {
+        self.created_at = created_at;
+    }
+
+    pub fn set_synthesized_from(&mut self, synthesized_from: Option<Uuid>) {
+        self.synthesized_from = synthesized_from;
+    }
+
+    pub fn set_verification(&mut self, verification: Option<Verification>) {
+        self.verification = verification;
+    }
+
+    pub fn set_compression(&mut self, compression: CompressionKind, level: CompressionLevel) {
+        self.compression = compression;
+        self.compression_level = level;
+    }
+
+    pub fn set_hash_algorithm(&mut self, hash_algorithm: Option<HashAlgorithm>) {
+        self.hash_algorithm = hash_algorithm;
+    }
+
+    pub fn set_archive_checksum(&mut self, archive_checksum: Option<String>) {
+        self.archive_checksum = archive_checksum;
+    }
+
+    pub fn set_note(&mut self, note: Option<String>) {
+        self.note = note;
+    }
+
+    pub fn set_status(&mut self, status: BackupStatus) {
+        self.status = status;
+    }
+
+    pub fn start(&mut self) {
+        self.started_at = Some(Utc::now());
+    }
+
+    pub fn finish(&mut self) {
+        let finished_at = Utc::now();
+        self.duration = self.started_at.map(|started_at| finished_at - started_at);
+        self.finished_at = Some(finished_at);
+    }
+
+    pub fn add_file(&mut self, size: u64) {
+        self.file_count += 1;
+        self.total_size_bytes += size;
+    }
+
+    pub fn add_unchanged_file(&mut self) {
+        self.unchanged_files += 1;
+    }
+
+    pub fn add_failed_file(&mut self, file: String) {
+        self.failed_files.push(file);
+    }
+
+    pub fn add_skipped(&mut self, reason: SkipReason) {
+        self.skipped.add(reason);
+    }
+}
+
+impl Default for BackupMetadata {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: String::new(),
+            kind: BackupKind::Full,
+            profile_id: None,
+            parent_id: None,
+            source_paths: Vec::new(),
+            target_path: PathBuf::new(),
+            disk_id: None,
+            disk_path: None,
+            filter: BackupFilter::default(),
+            rules: SelectionRules::default(),
+            estimated_size_bytes: None,
+            file_count: 0,
+            total_size_bytes: 0,
+            compression: CompressionKind::None,
+            compression_level: CompressionLevel::Default,
+            hash_algorithm: None,
+            archive_checksum: None,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            duration: None,
+            failed_files: Vec::new(),
+            skipped: SkippedCounts::default(),
+            unchanged_files: 0,
+            status: BackupStatus::Pending,
+            synthesized_from: None,
+            verification: None,
+            note: None,
+        }
+    }
+}
+
+mod duration_ms {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&duration.num_milliseconds()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<i64>::deserialize(deserializer)?.map(Duration::milliseconds))
+    }
+}
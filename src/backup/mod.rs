@@ -1,7 +1,29 @@
+pub mod archive;
+pub mod catalog;
+pub mod checksum;
+pub mod delete;
+pub mod discovery;
 pub mod disk;
+pub mod filter;
+pub mod frequency;
+pub mod index;
 pub mod kind;
+pub mod layout;
 pub mod message;
 pub mod metadata;
+pub mod power;
+pub mod preflight;
+pub mod profile;
+pub mod restore;
+pub mod retention;
+pub mod rotation;
+pub mod rules;
+pub mod scan;
+pub mod schedule;
+pub mod scheduler;
 pub mod status;
+pub mod synthetic;
 pub mod task;
+pub mod verify;
+pub mod watcher;
 pub mod worker;
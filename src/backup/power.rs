@@ -0,0 +1,28 @@
+use std::{fs, path::Path};
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Whether the machine runs on mains power, read from sysfs. Machines without
+/// a mains supply entry, like most desktops, count as plugged in.
+pub fn on_ac_power() -> bool {
+    on_ac_power_in(POWER_SUPPLY_DIR)
+}
+
+pub fn on_ac_power_in<P: AsRef<Path>>(power_supply_dir: P) -> bool {
+    let Ok(read_dir) = fs::read_dir(power_supply_dir) else {
+        return true;
+    };
+
+    let mains: Vec<bool> = read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            fs::read_to_string(path.join("type")).is_ok_and(|kind| kind.trim() == "Mains")
+        })
+        .map(|path| {
+            fs::read_to_string(path.join("online")).is_ok_and(|online| online.trim() == "1")
+        })
+        .collect();
+
+    mains.is_empty() || mains.contains(&true)
+}
@@ -0,0 +1,102 @@
+use crate::{app::settings::Settings, error::SanupResult, ui::format_bytes};
+use nix::sys::statvfs::statvfs;
+use std::{fmt::Display, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilesystemSpace {
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+}
+
+/// Queries the filesystem `path` lives on. The path does not have to exist
+/// yet, the closest existing ancestor is used instead.
+pub fn filesystem_space<P: AsRef<Path>>(path: P) -> SanupResult<FilesystemSpace> {
+    let path = path.as_ref();
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(path);
+
+    let stat = statvfs(existing).map_err(std::io::Error::from)?;
+    let fragment_size = stat.fragment_size() as u64;
+
+    Ok(FilesystemSpace {
+        total_bytes: stat.blocks() as u64 * fragment_size,
+        free_bytes: stat.blocks_available() as u64 * fragment_size,
+    })
+}
+
+/// Free space that has to be left on the target after a backup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeSpacePolicy {
+    pub reserve_bytes: u64,
+}
+
+impl FreeSpacePolicy {
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        settings
+            .check_free_space_before_backup()
+            .then_some(FreeSpacePolicy {
+                reserve_bytes: settings
+                    .min_free_space_gb()
+                    .saturating_mul(1024 * 1024 * 1024),
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpaceVerdict {
+    Enough,
+    /// The backup fits but leaves less than the configured reserve.
+    BelowReserve,
+    /// The backup does not fit, running it would fail with ENOSPC.
+    Insufficient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpaceCheck {
+    pub required_bytes: u64,
+    pub free_bytes: u64,
+    pub reserve_bytes: u64,
+    pub verdict: SpaceVerdict,
+}
+
+impl SpaceCheck {
+    pub fn new(required_bytes: u64, free_bytes: u64, policy: FreeSpacePolicy) -> Self {
+        let verdict = if required_bytes > free_bytes {
+            SpaceVerdict::Insufficient
+        } else if free_bytes - required_bytes < policy.reserve_bytes {
+            SpaceVerdict::BelowReserve
+        } else {
+            SpaceVerdict::Enough
+        };
+
+        Self {
+            required_bytes,
+            free_bytes,
+            reserve_bytes: policy.reserve_bytes,
+            verdict,
+        }
+    }
+}
+
+impl Display for SpaceCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} required, {} free, {} reserved",
+            format_bytes(self.required_bytes),
+            format_bytes(self.free_bytes),
+            format_bytes(self.reserve_bytes)
+        )
+    }
+}
+
+pub fn check_free_space<P: AsRef<Path>>(
+    target: P,
+    required_bytes: u64,
+    policy: FreeSpacePolicy,
+) -> SanupResult<SpaceCheck> {
+    let space = filesystem_space(target)?;
+    Ok(SpaceCheck::new(required_bytes, space.free_bytes, policy))
+}
@@ -0,0 +1,177 @@
+use crate::backup::{
+    disk::BackupDisk, filter::BackupFilter, kind::BackupKind, metadata::BackupMetadata,
+    retention::Retention, rules::SelectionRules, schedule::Schedule,
+};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// One disk of a rotation and where on it the profile keeps its backups.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TargetDisk {
+    pub disk_id: Uuid,
+    pub label: String,
+    /// Relative to the disk root.
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BackupProfile {
+    id: Uuid,
+    name: String,
+    kind: BackupKind,
+    source_paths: Vec<PathBuf>,
+    target_path: PathBuf,
+    #[serde(default)]
+    filter: BackupFilter,
+    #[serde(default)]
+    rules: SelectionRules,
+    /// Disks the profile rotates between. When empty, backups go to
+    /// `target_path`.
+    #[serde(default)]
+    target_disks: Vec<TargetDisk>,
+    /// Warn when a rotation disk went this many days without a backup.
+    #[serde(default)]
+    rotation_warn_days: Option<u64>,
+    #[serde(default)]
+    schedule: Schedule,
+    /// Which backups a prune keeps. `None` keeps all of them.
+    #[serde(default)]
+    retention: Option<Retention>,
+}
+
+impl BackupProfile {
+    pub fn new<P: AsRef<Path>>(
+        name: &str,
+        kind: BackupKind,
+        source_paths: Vec<PathBuf>,
+        target_path: P,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            kind,
+            source_paths,
+            target_path: target_path.as_ref().to_path_buf(),
+            filter: BackupFilter::default(),
+            rules: SelectionRules::default(),
+            target_disks: Vec::new(),
+            rotation_warn_days: None,
+            schedule: Schedule::default(),
+            retention: None,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> BackupKind {
+        self.kind
+    }
+
+    pub fn source_paths(&self) -> &[PathBuf] {
+        &self.source_paths
+    }
+
+    pub fn target_path(&self) -> &Path {
+        &self.target_path
+    }
+
+    pub fn filter(&self) -> &BackupFilter {
+        &self.filter
+    }
+
+    pub fn rules(&self) -> &SelectionRules {
+        &self.rules
+    }
+
+    pub fn target_disks(&self) -> &[TargetDisk] {
+        &self.target_disks
+    }
+
+    pub fn rotation_warn_days(&self) -> Option<u64> {
+        self.rotation_warn_days
+    }
+
+    /// Where backups of this profile go on `disk`, if it is one of the
+    /// rotation disks.
+    pub fn target_on(&self, disk: &BackupDisk) -> Option<PathBuf> {
+        self.target_disks
+            .iter()
+            .find(|target_disk| target_disk.disk_id == disk.id())
+            .map(|target_disk| disk.mount_path().join(&target_disk.path))
+    }
+
+    /// Adds `disk` to the rotation, replacing an earlier entry for it.
+    pub fn add_target_disk<P: AsRef<Path>>(&mut self, disk: &BackupDisk, path: P) {
+        self.target_disks
+            .retain(|target_disk| target_disk.disk_id != disk.id());
+        self.target_disks.push(TargetDisk {
+            disk_id: disk.id(),
+            label: disk.label().to_string(),
+            path: path.as_ref().to_path_buf(),
+        });
+    }
+
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    pub fn set_schedule(&mut self, schedule: Schedule) {
+        self.schedule = schedule;
+    }
+
+    pub fn retention(&self) -> Option<&Retention> {
+        self.retention.as_ref()
+    }
+
+    pub fn set_retention(&mut self, retention: Option<Retention>) {
+        self.retention = retention;
+    }
+
+    pub fn set_rotation_warn_days(&mut self, rotation_warn_days: Option<u64>) {
+        self.rotation_warn_days = rotation_warn_days;
+    }
+
+    pub fn set_rules(&mut self, rules: SelectionRules) {
+        self.rules = rules;
+    }
+
+    pub fn set_filter(&mut self, filter: BackupFilter) {
+        self.filter = filter;
+    }
+
+    /// Describes a new run of this profile. Every run gets its own directory
+    /// inside the profile target, named after the profile and start time.
+    pub fn to_metadata(&self) -> BackupMetadata {
+        self.to_metadata_in(&self.target_path)
+    }
+
+    /// Like `to_metadata`, but with the run directory placed in `target_dir`
+    /// instead of the profile target.
+    pub fn to_metadata_in<P: AsRef<Path>>(&self, target_dir: P) -> BackupMetadata {
+        let target_path = target_dir.as_ref().join(format!(
+            "{}_{}",
+            self.name,
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+
+        let mut metadata = BackupMetadata::new(
+            &self.name,
+            self.kind,
+            self.source_paths.clone(),
+            target_path,
+        );
+        metadata.set_profile_id(Some(self.id));
+        metadata.set_filter(self.filter.clone());
+        metadata.set_rules(self.rules.clone());
+
+        metadata
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    backup::{
+        archive::Archive,
+        index::{FileIndex, IndexEntry},
+        layout::{BackupLayout, relative_to_root},
+        metadata::BackupMetadata,
+        worker::{copy_mtime, copy_special},
+    },
+    config::Config,
+    error::{SanupError, SanupResult},
+};
+use log::warn;
+use nix::sys::{stat::utimes, time::TimeVal};
+use std::{
+    env, fs,
+    os::unix::fs::symlink,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Default)]
+pub struct RestoreSummary {
+    pub file_count: u64,
+    pub total_size_bytes: u64,
+    pub failed_files: Vec<String>,
+}
+
+/// Restores `paths` from a backup. Every path has to be a source of the
+/// backup or lie inside one; an empty list restores all sources. Paths are
+/// resolved with [`resolve_path`].
+///
+/// Without `to` files go back to their original location, otherwise they are
+/// placed under `to` by their full original path, like `tar -C` would.
+///
+/// Incremental and differential backups only hold the files that changed,
+/// the rest is read from the earlier backups in `chain`. Compressed backups
+/// are extracted from their archive.
+pub fn restore(
+    metadata: &BackupMetadata,
+    chain: &[BackupMetadata],
+    paths: &[PathBuf],
+    to: Option<&Path>,
+) -> SanupResult<RestoreSummary> {
+    restore_with_progress(metadata, chain, paths, to, &mut |_, _| {})
+}
+
+/// Like [`restore`], calling `on_file` with the destination and the totals so
+/// far after every regular file.
+pub fn restore_with_progress(
+    metadata: &BackupMetadata,
+    chain: &[BackupMetadata],
+    paths: &[PathBuf],
+    to: Option<&Path>,
+    on_file: &mut dyn FnMut(&Path, &RestoreSummary),
+) -> SanupResult<RestoreSummary> {
+    let layout = BackupLayout::new(metadata.target_path());
+    let index = FileIndex::load(layout.index_path())?;
+    let mut archive = Archive::open_in(&layout)?;
+    let paths = if paths.is_empty() {
+        metadata.source_paths().to_vec()
+    } else {
+        paths
+            .iter()
+            .map(|path| resolve_path(path))
+            .collect::<SanupResult<_>>()?
+    };
+    let to = to.map(resolve_path).transpose()?;
+    let to = to.as_deref();
+
+    let mut summary = RestoreSummary::default();
+
+    for path in paths {
+        if !metadata
+            .source_paths()
+            .iter()
+            .any(|source| path.starts_with(source))
+        {
+            return Err(SanupError::Other(format!(
+                "{} is not part of backup {}",
+                path.display(),
+                metadata.name()
+            )));
+        }
+
+        let destination_of = |original: &Path| match to {
+            Some(to) => to.join(relative_to_root(original)),
+            None => original.to_path_buf(),
+        };
+
+        if let Some(archive) = archive.as_mut() {
+            restore_archived(
+                archive,
+                &index,
+                &path,
+                &destination_of,
+                &mut summary,
+                on_file,
+            );
+            continue;
+        }
+
+        let stored = layout.stored_path(&path);
+        if fs::symlink_metadata(&stored).is_ok() || index.under(&path).next().is_none() {
+            restore_tree(&stored, &destination_of(&path), &mut summary, on_file);
+        }
+
+        for (file, entry) in index.under(&path) {
+            let Some(stored_in) = entry.stored_in else {
+                continue;
+            };
+
+            let destination = destination_of(file);
+            let result = match chain.iter().find(|backup| backup.id() == stored_in) {
+                Some(backup) => restore_entry(
+                    &BackupLayout::new(backup.target_path()).stored_path(file),
+                    &destination,
+                    &mut summary,
+                    on_file,
+                ),
+                None => Err(SanupError::Other(format!(
+                    "backup {} holding it is missing",
+                    stored_in
+                ))),
+            };
+
+            if let Err(err) = result {
+                warn!("Failed to restore {}: {}", destination.display(), err);
+                summary
+                    .failed_files
+                    .push(destination.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// `path` made absolute against the working directory. Its parent is
+/// canonicalized where it exists, so a path through a symlinked directory
+/// matches the canonical sources a backup records, while a symlink itself is
+/// left alone. Paths with `..` are refused.
+pub fn resolve_path(path: &Path) -> SanupResult<PathBuf> {
+    if path
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return Err(SanupError::Other(format!(
+            "{} cannot contain ..",
+            path.display()
+        )));
+    }
+
+    let path: PathBuf = if path.is_absolute() {
+        path.components().collect()
+    } else {
+        env::current_dir()?.join(path).components().collect()
+    };
+    let resolved = path
+        .parent()
+        .zip(path.file_name())
+        .and_then(|(parent, name)| Some(parent.canonicalize().ok()?.join(name)));
+
+    Ok(resolved.unwrap_or(path))
+}
+
+/// Extracts the entries at or below `path` from the archive of a compressed
+/// backup. Regular files get their modification time from the index.
+fn restore_archived(
+    archive: &mut Archive,
+    index: &FileIndex,
+    path: &Path,
+    destination_of: &dyn Fn(&Path) -> PathBuf,
+    summary: &mut RestoreSummary,
+    on_file: &mut dyn FnMut(&Path, &RestoreSummary),
+) {
+    for original in archive.under(path) {
+        let destination = destination_of(&original);
+        let result = archive.extract(&original, &destination).and_then(|size| {
+            let Some(size) = size else {
+                return Ok(());
+            };
+            summary.total_size_bytes += size;
+            summary.file_count += 1;
+            if let Some(entry) = index.get(&original) {
+                set_mtime(&destination, entry)?;
+            }
+            on_file(&destination, summary);
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            warn!("Failed to restore {}: {}", destination.display(), err);
+            summary
+                .failed_files
+                .push(destination.to_string_lossy().to_string());
+        }
+    }
+}
+
+fn set_mtime(destination: &Path, entry: &IndexEntry) -> SanupResult<()> {
+    let mtime = TimeVal::new(entry.mtime, entry.mtime_nsec / 1000);
+    utimes(destination, &mtime, &mtime).map_err(std::io::Error::from)?;
+
+    Ok(())
+}
+
+fn restore_tree(
+    stored: &Path,
+    destination: &Path,
+    summary: &mut RestoreSummary,
+    on_file: &mut dyn FnMut(&Path, &RestoreSummary),
+) {
+    if let Err(err) = restore_entry(stored, destination, summary, on_file) {
+        warn!("Failed to restore {}: {}", destination.display(), err);
+        summary
+            .failed_files
+            .push(destination.to_string_lossy().to_string());
+    }
+}
+
+fn restore_entry(
+    stored: &Path,
+    destination: &Path,
+    summary: &mut RestoreSummary,
+    on_file: &mut dyn FnMut(&Path, &RestoreSummary),
+) -> SanupResult<()> {
+    let file_type = fs::symlink_metadata(stored)?.file_type();
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if file_type.is_symlink() {
+        if fs::symlink_metadata(destination).is_ok() {
+            fs::remove_file(destination)?;
+        }
+        symlink(fs::read_link(stored)?, destination)?;
+    } else if file_type.is_dir() {
+        fs::create_dir_all(destination)?;
+        for entry in fs::read_dir(stored)? {
+            let entry = entry?;
+            restore_tree(
+                &entry.path(),
+                &destination.join(entry.file_name()),
+                summary,
+                on_file,
+            );
+        }
+    } else if file_type.is_file() {
+        summary.total_size_bytes += fs::copy(stored, destination)?;
+        summary.file_count += 1;
+        copy_mtime(stored, destination)?;
+        on_file(destination, summary);
+    } else {
+        copy_special(stored, destination)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,291 @@
+use crate::backup::{
+    catalog::Catalog, delete::remove_backup_dir, disk::BackupDisk, metadata::BackupMetadata,
+};
+use chrono::{DateTime, Datelike, Local, NaiveDate, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Which backups of a profile survive a prune, grandfather-father-son style.
+/// Every rule keeps the newest backup of each of its periods, so `keep_daily
+/// = 7` keeps the last backup of each of the past 7 days.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Retention {
+    keep_last: u32,
+    keep_daily: u32,
+    keep_weekly: u32,
+    keep_monthly: u32,
+    /// `None` keeps one backup of every year.
+    keep_yearly: Option<u32>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            keep_last: 3,
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 12,
+            keep_yearly: None,
+        }
+    }
+}
+
+impl Retention {
+    pub fn new(
+        keep_last: u32,
+        keep_daily: u32,
+        keep_weekly: u32,
+        keep_monthly: u32,
+        keep_yearly: Option<u32>,
+    ) -> Self {
+        Self {
+            keep_last,
+            keep_daily,
+            keep_weekly,
+            keep_monthly,
+            keep_yearly,
+        }
+    }
+
+    pub fn keep_last(&self) -> u32 {
+        self.keep_last
+    }
+
+    pub fn keep_daily(&self) -> u32 {
+        self.keep_daily
+    }
+
+    pub fn keep_weekly(&self) -> u32 {
+        self.keep_weekly
+    }
+
+    pub fn keep_monthly(&self) -> u32 {
+        self.keep_monthly
+    }
+
+    pub fn keep_yearly(&self) -> Option<u32> {
+        self.keep_yearly
+    }
+}
+
+/// Why a backup is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepReason {
+    Last,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+    /// A kept backup builds on it.
+    Dependency,
+}
+
+#[allow(clippy::to_string_trait_impl)]
+impl ToString for KeepReason {
+    fn to_string(&self) -> String {
+        match self {
+            KeepReason::Last => "last".to_string(),
+            KeepReason::Daily => "daily".to_string(),
+            KeepReason::Weekly => "weekly".to_string(),
+            KeepReason::Monthly => "monthly".to_string(),
+            KeepReason::Yearly => "yearly".to_string(),
+            KeepReason::Dependency => "needed by a kept backup".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Period {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    /// Numbers the periods so consecutive ones differ by one.
+    fn number(&self, date: NaiveDate) -> i64 {
+        match self {
+            Period::Day => date.num_days_from_ce() as i64,
+            Period::Week => {
+                (date.num_days_from_ce() - date.weekday().num_days_from_monday() as i32) as i64 / 7
+            }
+            Period::Month => date.year() as i64 * 12 + date.month0() as i64,
+            Period::Year => date.year() as i64,
+        }
+    }
+}
+
+/// What a prune keeps and removes.
+#[derive(Default)]
+pub struct PrunePlan {
+    pub keep: Vec<(BackupMetadata, Vec<KeepReason>)>,
+    pub remove: Vec<BackupMetadata>,
+}
+
+impl PrunePlan {
+    pub fn reclaimable_bytes(&self) -> u64 {
+        self.remove
+            .iter()
+            .map(|backup| backup.total_size_bytes())
+            .sum()
+    }
+}
+
+/// Applies `retention` to the backups of a profile. Every disk keeps its own
+/// history, as chains never cross disks. The newest completed backup of a
+/// disk is always kept so the next run has something to build on, and no
+/// backup a kept one depends on is ever removed. Failed and cancelled runs
+/// are always removed, and so are backups a synthetic full backup replaced
+/// unless something still builds on them. Only the backup the synthetic one
+/// was made from counts as replaced, not the chain below it.
+pub fn plan(
+    catalog: &Catalog,
+    profile_id: Uuid,
+    retention: &Retention,
+    now: DateTime<Utc>,
+) -> PrunePlan {
+    let mut backups: Vec<&BackupMetadata> = catalog
+        .backups()
+        .iter()
+        .filter(|backup| backup.profile_id() == Some(profile_id))
+        .collect();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at()));
+
+    let superseded: HashSet<Uuid> = catalog
+        .backups()
+        .iter()
+        .filter(|backup| backup.is_completed())
+        .filter_map(|backup| backup.synthesized_from())
+        .collect();
+
+    let today = now.with_timezone(&Local).date_naive();
+    let rules = [
+        (Period::Day, Some(retention.keep_daily), KeepReason::Daily),
+        (
+            Period::Week,
+            Some(retention.keep_weekly),
+            KeepReason::Weekly,
+        ),
+        (
+            Period::Month,
+            Some(retention.keep_monthly),
+            KeepReason::Monthly,
+        ),
+        (Period::Year, retention.keep_yearly, KeepReason::Yearly),
+    ];
+
+    let disks: Vec<Option<Uuid>> = backups
+        .iter()
+        .map(|backup| backup.disk_id())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut plan = PrunePlan::default();
+    for disk_id in disks {
+        let (completed, replaced): (Vec<&BackupMetadata>, Vec<&BackupMetadata>) = backups
+            .iter()
+            .filter(|backup| backup.disk_id() == disk_id)
+            .partition(|backup| backup.is_completed() && !superseded.contains(&backup.id()));
+        plan.remove.extend(replaced.into_iter().cloned());
+
+        let mut reasons: Vec<Vec<KeepReason>> = vec![Vec::new(); completed.len()];
+        for reason in reasons.iter_mut().take(retention.keep_last.max(1) as usize) {
+            reason.push(KeepReason::Last);
+        }
+
+        for (period, count, reason) in rules {
+            let current = period.number(today);
+            let mut seen = HashSet::new();
+
+            for (idx, backup) in completed.iter().enumerate() {
+                let number = period.number(backup.created_at().with_timezone(&Local).date_naive());
+                let in_range = count.is_none_or(|count| current - number < count as i64);
+                if in_range && seen.insert(number) {
+                    reasons[idx].push(reason);
+                }
+            }
+        }
+
+        for (backup, reasons) in completed.into_iter().zip(reasons) {
+            if reasons.is_empty() {
+                plan.remove.push(backup.clone());
+            } else {
+                plan.keep.push((backup.clone(), reasons));
+            }
+        }
+    }
+
+    let needed: HashSet<Uuid> = plan
+        .keep
+        .iter()
+        .flat_map(|(backup, _)| catalog.chain(backup))
+        .map(|backup| backup.id())
+        .collect();
+    let (dependencies, remove) = plan
+        .remove
+        .into_iter()
+        .partition(|backup| needed.contains(&backup.id()));
+    plan.remove = remove;
+    plan.keep.extend(
+        dependencies
+            .into_iter()
+            .map(|backup: BackupMetadata| (backup, vec![KeepReason::Dependency])),
+    );
+
+    plan.keep.sort_by_key(|(backup, _)| backup.created_at());
+    plan.remove.sort_by_key(|backup| backup.created_at());
+    plan
+}
+
+/// What a prune did.
+#[derive(Default)]
+pub struct PruneSummary {
+    pub removed: Vec<BackupMetadata>,
+    /// Backups whose disk is not mounted, they stay in the catalog until the
+    /// next prune with the disk plugged in.
+    pub unreachable: Vec<BackupMetadata>,
+    /// Backups that could not be removed, with the reason. They stay in the
+    /// catalog unless their directory is gone anyway.
+    pub failed: Vec<(BackupMetadata, String)>,
+    pub reclaimed_bytes: u64,
+}
+
+/// Deletes the backups the plan removes from their disks and the catalog.
+/// A backup that cannot be removed does not stop the others, so the catalog
+/// always matches what is left on disk. Saving it is up to the caller.
+pub fn prune(plan: &PrunePlan, catalog: &mut Catalog, disks: &[BackupDisk]) -> PruneSummary {
+    let mut summary = PruneSummary::default();
+
+    for backup in plan.remove.iter().rev() {
+        let Ok(root) = backup.locate(disks) else {
+            warn!(
+                "Backup {} is on a disk that is not mounted, not removing it",
+                backup.target_path().display()
+            );
+            summary.unreachable.push(backup.clone());
+            continue;
+        };
+
+        match remove_backup_dir(&root) {
+            Ok(reclaimed_bytes) => {
+                summary.reclaimed_bytes += reclaimed_bytes;
+                info!("Removed backup {}", root.display());
+                catalog.remove(backup.id());
+                summary.removed.push(backup.clone());
+            }
+            Err(err) => {
+                warn!("Failed to remove backup {}: {}", root.display(), err);
+                if !root.exists() {
+                    catalog.remove(backup.id());
+                }
+                summary.failed.push((backup.clone(), err.to_string()));
+            }
+        }
+    }
+
+    summary
+}
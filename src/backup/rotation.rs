@@ -0,0 +1,52 @@
+use crate::backup::{catalog::Catalog, disk::BackupDisk, profile::BackupProfile};
+use chrono::{DateTime, TimeDelta, Utc};
+use uuid::Uuid;
+
+/// A rotation disk that went too long without a backup.
+pub struct OverdueDisk {
+    pub disk_id: Uuid,
+    pub label: String,
+    /// `None` when the disk never got a backup of the profile.
+    pub last_backup_at: Option<DateTime<Utc>>,
+}
+
+/// Picks the disk for the next run of a rotating profile: of its disks that
+/// are mounted, the one that went longest without a backup.
+pub fn pick_disk<'a>(
+    profile: &BackupProfile,
+    disks: &'a [BackupDisk],
+    catalog: &Catalog,
+) -> Option<&'a BackupDisk> {
+    disks
+        .iter()
+        .filter(|disk| profile.target_on(disk).is_some())
+        .min_by_key(|disk| catalog.last_backup_at(profile.id(), disk.id()))
+}
+
+/// Rotation disks of `profile` without a backup in the last
+/// `rotation_warn_days` days.
+pub fn overdue_disks(
+    profile: &BackupProfile,
+    catalog: &Catalog,
+    now: DateTime<Utc>,
+) -> Vec<OverdueDisk> {
+    let Some(days) = profile.rotation_warn_days() else {
+        return Vec::new();
+    };
+    let limit = now - TimeDelta::days(days as i64);
+
+    profile
+        .target_disks()
+        .iter()
+        .filter_map(|target_disk| {
+            let last_backup_at = catalog.last_backup_at(profile.id(), target_disk.disk_id);
+            last_backup_at
+                .is_none_or(|last_backup_at| last_backup_at < limit)
+                .then(|| OverdueDisk {
+                    disk_id: target_disk.disk_id,
+                    label: target_disk.label.clone(),
+                    last_backup_at,
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,123 @@
+use chrono::{Duration, Utc};
+use nix::unistd::getuid;
+use serde::{Deserialize, Serialize};
+use std::{fs::Metadata, os::unix::fs::MetadataExt};
+
+/// Per profile rules that skip files by their metadata rather than by path.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SelectionRules {
+    max_file_size_mb: Option<u64>,
+    max_age_days: Option<u64>,
+    only_owned_by_me: bool,
+    skip_special_files: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    TooLarge,
+    TooOld,
+    NotOwned,
+    SpecialFile,
+}
+
+impl SelectionRules {
+    pub fn max_file_size_mb(&self) -> Option<u64> {
+        self.max_file_size_mb
+    }
+
+    pub fn max_age_days(&self) -> Option<u64> {
+        self.max_age_days
+    }
+
+    pub fn only_owned_by_me(&self) -> bool {
+        self.only_owned_by_me
+    }
+
+    pub fn skip_special_files(&self) -> bool {
+        self.skip_special_files
+    }
+
+    pub fn set_max_file_size_mb(&mut self, max_file_size_mb: Option<u64>) {
+        self.max_file_size_mb = max_file_size_mb;
+    }
+
+    pub fn set_max_age_days(&mut self, max_age_days: Option<u64>) {
+        self.max_age_days = max_age_days;
+    }
+
+    pub fn set_only_owned_by_me(&mut self, only_owned_by_me: bool) {
+        self.only_owned_by_me = only_owned_by_me;
+    }
+
+    pub fn set_skip_special_files(&mut self, skip_special_files: bool) {
+        self.skip_special_files = skip_special_files;
+    }
+
+    /// Returns why an entry has to be skipped, directories are never skipped
+    /// so their content is still checked file by file.
+    pub fn check(&self, metadata: &Metadata) -> Option<SkipReason> {
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            return None;
+        }
+
+        if self.only_owned_by_me && metadata.uid() != getuid().as_raw() {
+            return Some(SkipReason::NotOwned);
+        }
+
+        if !file_type.is_file() && !file_type.is_symlink() {
+            return self.skip_special_files.then_some(SkipReason::SpecialFile);
+        }
+
+        if let Some(max_file_size_mb) = self.max_file_size_mb
+            && metadata.len() > max_file_size_mb.saturating_mul(1024 * 1024)
+        {
+            return Some(SkipReason::TooLarge);
+        }
+
+        if let Some(max_age_days) = self.max_age_days {
+            let oldest = Utc::now() - Duration::days(max_age_days as i64);
+            if metadata.mtime() < oldest.timestamp() {
+                return Some(SkipReason::TooOld);
+            }
+        }
+
+        None
+    }
+}
+
+impl Default for SelectionRules {
+    fn default() -> Self {
+        Self {
+            max_file_size_mb: None,
+            max_age_days: None,
+            only_owned_by_me: false,
+            skip_special_files: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct SkippedCounts {
+    pub too_large: u64,
+    pub too_old: u64,
+    pub not_owned: u64,
+    pub special_file: u64,
+}
+
+impl SkippedCounts {
+    pub fn add(&mut self, reason: SkipReason) {
+        match reason {
+            SkipReason::TooLarge => self.too_large += 1,
+            SkipReason::TooOld => self.too_old += 1,
+            SkipReason::NotOwned => self.not_owned += 1,
+            SkipReason::SpecialFile => self.special_file += 1,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.too_large + self.too_old + self.not_owned + self.special_file
+    }
+}
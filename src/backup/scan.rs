@@ -0,0 +1,170 @@
+use crate::backup::{
+    filter::BackupFilter,
+    index::{FileIndex, IndexEntry},
+    rules::SelectionRules,
+};
+use ignore::WalkBuilder;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirStats {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+impl DirStats {
+    fn add(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        self.files += 1;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanResult {
+    pub total: DirStats,
+    /// Files that differ from the parent index the scan was run against,
+    /// what a backup building on that parent stores. Without a parent this
+    /// is the same as `total`.
+    pub changed: DirStats,
+    pub dirs: HashMap<PathBuf, DirStats>,
+}
+
+impl ScanResult {
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<DirStats> {
+        self.dirs.get(path.as_ref()).copied()
+    }
+}
+
+/// Adds up file sizes and counts per directory, like `ncdu`.
+///
+/// Without a filter every file below the sources is counted. With one, only
+/// what a backup with that filter and rules would copy is counted, which
+/// makes the total usable as the estimated size of the backup.
+pub struct SizeScan {
+    sources: Vec<PathBuf>,
+    selection: Option<(BackupFilter, SelectionRules)>,
+}
+
+impl SizeScan {
+    pub fn new(sources: Vec<PathBuf>) -> Self {
+        Self {
+            sources,
+            selection: None,
+        }
+    }
+
+    pub fn with_selection(mut self, filter: BackupFilter, rules: SelectionRules) -> Self {
+        self.selection = Some((filter, rules));
+        self
+    }
+
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    pub fn run(&self) -> ScanResult {
+        self.run_against(None)
+    }
+
+    /// Scans like [`SizeScan::run`], also counting the files that changed
+    /// since `parent` was taken.
+    pub fn run_against(&self, parent: Option<&FileIndex>) -> ScanResult {
+        let mut result = ScanResult::default();
+
+        for source in &self.sources {
+            let walk = match &self.selection {
+                Some((filter, _)) => match filter.walk(source) {
+                    Ok(walk) => walk,
+                    Err(_) => continue,
+                },
+                None => WalkBuilder::new(source).standard_filters(false).build(),
+            };
+
+            for entry in walk.flatten() {
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if !metadata.is_file() {
+                    continue;
+                }
+                if let Some((_, rules)) = &self.selection
+                    && rules.check(&metadata).is_some()
+                {
+                    continue;
+                }
+
+                let bytes = metadata.len();
+                result.total.add(bytes);
+                if parent
+                    .and_then(|parent| parent.get(entry.path()))
+                    .is_none_or(|previous| !previous.is_unchanged(&IndexEntry::new(&metadata)))
+                {
+                    result.changed.add(bytes);
+                }
+
+                for ancestor in entry
+                    .path()
+                    .ancestors()
+                    .skip(1)
+                    .take_while(|ancestor| ancestor.starts_with(source))
+                {
+                    result
+                        .dirs
+                        .entry(ancestor.to_path_buf())
+                        .or_default()
+                        .add(bytes);
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn spawn(self) -> ScanHandle {
+        let (tx, rx) = mpsc::channel();
+        let sources = self.sources.clone();
+
+        thread::spawn(move || {
+            let _ = tx.send(self.run());
+        });
+
+        ScanHandle {
+            sources,
+            rx,
+            result: None,
+        }
+    }
+}
+
+pub struct ScanHandle {
+    sources: Vec<PathBuf>,
+    rx: Receiver<ScanResult>,
+    result: Option<ScanResult>,
+}
+
+impl ScanHandle {
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    pub fn update(&mut self) {
+        if self.result.is_none()
+            && let Ok(result) = self.rx.try_recv()
+        {
+            self.result = Some(result);
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.result.is_some()
+    }
+
+    pub fn result(&self) -> Option<&ScanResult> {
+        self.result.as_ref()
+    }
+}
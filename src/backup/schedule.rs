@@ -0,0 +1,175 @@
+use crate::backup::frequency::Frequency;
+use chrono::{Datelike, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// How late a run may start when missed runs are not caught up.
+const MISSED_GRACE: TimeDelta = TimeDelta::minutes(5);
+
+/// Hours of the day runs may start in. The window wraps around midnight when
+/// `end` is before `start`, so `22:00-06:00` covers the night.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Parses `HH:MM-HH:MM`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.split_once('-')?;
+        Some(Self {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        })
+    }
+}
+
+/// When a profile runs on its own. All times are local wall clock times, so
+/// a slot the clock skips when DST starts runs as soon as the clock passes
+/// it, and one it repeats when DST ends only runs once.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Schedule {
+    frequency: Frequency,
+    /// Time of day for daily and weekly runs, hourly runs use the minute.
+    at: NaiveTime,
+    weekday: Weekday,
+    window: Option<TimeWindow>,
+    only_on_ac_power: bool,
+    /// Runs missed while sanup was not running are made up for on the next
+    /// start.
+    catch_up: bool,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            frequency: Frequency::Manual,
+            at: NaiveTime::MIN,
+            weekday: Weekday::Sun,
+            window: None,
+            only_on_ac_power: false,
+            catch_up: true,
+        }
+    }
+}
+
+impl Schedule {
+    pub fn new(frequency: Frequency, at: NaiveTime) -> Self {
+        Self {
+            frequency,
+            at,
+            ..Self::default()
+        }
+    }
+
+    pub fn frequency(&self) -> Frequency {
+        self.frequency
+    }
+
+    pub fn at(&self) -> NaiveTime {
+        self.at
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        self.weekday
+    }
+
+    pub fn window(&self) -> Option<TimeWindow> {
+        self.window
+    }
+
+    pub fn only_on_ac_power(&self) -> bool {
+        self.only_on_ac_power
+    }
+
+    pub fn catch_up(&self) -> bool {
+        self.catch_up
+    }
+
+    pub fn set_weekday(&mut self, weekday: Weekday) {
+        self.weekday = weekday;
+    }
+
+    pub fn set_window(&mut self, window: Option<TimeWindow>) {
+        self.window = window;
+    }
+
+    pub fn set_only_on_ac_power(&mut self, only_on_ac_power: bool) {
+        self.only_on_ac_power = only_on_ac_power;
+    }
+
+    pub fn set_catch_up(&mut self, catch_up: bool) {
+        self.catch_up = catch_up;
+    }
+
+    /// The latest time a run was scheduled for, at or before `now`.
+    pub fn previous_slot(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let today = now.date();
+
+        let slot = match self.frequency {
+            Frequency::Manual => return None,
+            Frequency::Hourly => {
+                let slot = today
+                    .and_hms_opt(now.hour(), self.at.minute(), 0)
+                    .unwrap_or(now);
+                if slot > now {
+                    slot - TimeDelta::hours(1)
+                } else {
+                    slot
+                }
+            }
+            Frequency::Daily => {
+                let slot = today.and_time(self.at);
+                if slot > now {
+                    slot - TimeDelta::days(1)
+                } else {
+                    slot
+                }
+            }
+            Frequency::Weekly => {
+                let days_back = (now.weekday().num_days_from_monday() + 7
+                    - self.weekday.num_days_from_monday())
+                    % 7;
+                let slot = (today - TimeDelta::days(days_back as i64)).and_time(self.at);
+                if slot > now {
+                    slot - TimeDelta::weeks(1)
+                } else {
+                    slot
+                }
+            }
+        };
+
+        Some(slot)
+    }
+
+    /// Whether a run is due at `now`, given when the profile last ran. A run
+    /// outside the time window waits until the window opens.
+    pub fn is_due(&self, last_run: NaiveDateTime, now: NaiveDateTime) -> bool {
+        let Some(slot) = self.previous_slot(now) else {
+            return false;
+        };
+
+        if last_run >= slot {
+            return false;
+        }
+        if !self.catch_up && now - slot > MISSED_GRACE {
+            return false;
+        }
+
+        self.window.is_none_or(|window| window.contains(now.time()))
+    }
+}
+
+/// Parses `HH:MM`.
+pub fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
@@ -0,0 +1,86 @@
+use crate::backup::{catalog::Catalog, power::on_ac_power, profile::BackupProfile};
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use log::{debug, info};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Decides when scheduled profiles run.
+///
+/// The last run of every profile is taken from the catalog on start, so runs
+/// missed while sanup was closed are caught up. Profiles that never ran wait
+/// for their next slot instead of starting right away.
+pub struct Scheduler {
+    last_runs: HashMap<Uuid, NaiveDateTime>,
+    last_check: Option<Instant>,
+}
+
+impl Scheduler {
+    pub fn new(catalog: &Catalog) -> Self {
+        let mut last_runs: HashMap<Uuid, NaiveDateTime> = HashMap::new();
+
+        for backup in catalog.backups() {
+            let Some(profile_id) = backup.profile_id() else {
+                continue;
+            };
+            let started_at = backup
+                .started_at()
+                .unwrap_or(backup.created_at())
+                .with_timezone(&Local)
+                .naive_local();
+
+            let last_run = last_runs.entry(profile_id).or_insert(started_at);
+            *last_run = (*last_run).max(started_at);
+        }
+
+        Self {
+            last_runs,
+            last_check: None,
+        }
+    }
+
+    /// Profiles that are due now. They count as run from here on, whether
+    /// the backup can actually start or not, so a missing disk does not
+    /// retrigger the run on every check.
+    pub fn due(&mut self, profiles: &[BackupProfile]) -> Vec<Uuid> {
+        if self
+            .last_check
+            .is_some_and(|last_check| last_check.elapsed() < CHECK_INTERVAL)
+        {
+            return Vec::new();
+        }
+        self.last_check = Some(Instant::now());
+
+        let now = Local::now().naive_local();
+        let mut due = Vec::new();
+
+        for profile in profiles {
+            let schedule = profile.schedule();
+            let last_run = *self.last_runs.entry(profile.id()).or_insert(now);
+
+            if !schedule.is_due(last_run, now) {
+                continue;
+            }
+            if schedule.only_on_ac_power() && !on_ac_power() {
+                debug!("Holding back {} until on AC power", profile.name());
+                continue;
+            }
+
+            if schedule
+                .previous_slot(now)
+                .is_some_and(|slot| now - slot > TimeDelta::minutes(1))
+            {
+                info!("Catching up missed run of {}", profile.name());
+            }
+
+            self.last_runs.insert(profile.id(), now);
+            due.push(profile.id());
+        }
+
+        due
+    }
+}
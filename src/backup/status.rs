@@ -1,8 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub enum BackupStatus {
+    #[default]
     Pending,
-    Running { progress: f32, current_file: String },
+    Running {
+        progress: f32,
+        current_file: String,
+    },
     Paused,
     Completed,
-    Failed { reason: String },
+    Failed {
+        reason: String,
+    },
     Cancelled,
 }
+
+impl BackupStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            BackupStatus::Completed | BackupStatus::Failed { .. } | BackupStatus::Cancelled
+        )
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    backup::{
+        catalog::Catalog, disk::BackupDisk, index::FileIndex, kind::BackupKind,
+        layout::BackupLayout, metadata::BackupMetadata, status::BackupStatus,
+    },
+    config::Config,
+    error::{SanupError, SanupResult},
+};
+use chrono::Local;
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+};
+use uuid::Uuid;
+
+/// Everything needed to consolidate a chain, gathered from the catalog up
+/// front so the worker only touches the file system.
+pub struct SyntheticPlan {
+    tip: BackupMetadata,
+    tip_root: PathBuf,
+    /// Where the backups of the chain are now, by id.
+    chain_roots: HashMap<Uuid, PathBuf>,
+    metadata: BackupMetadata,
+}
+
+impl SyntheticPlan {
+    /// Plans a synthetic full backup with the same content as backup `id`,
+    /// next to it on its disk.
+    pub fn new(catalog: &Catalog, id: Uuid, disks: &[BackupDisk]) -> SanupResult<Self> {
+        let tip = catalog
+            .get(id)
+            .ok_or_else(|| SanupError::Other(format!("No backup with id {}", id)))?
+            .clone();
+        if !tip.is_completed() {
+            return Err(SanupError::Other(format!(
+                "Backup {} did not complete",
+                tip.name()
+            )));
+        }
+        if tip.parent_id().is_none() {
+            return Err(SanupError::Other(format!(
+                "Backup {} does not build on another backup",
+                tip.name()
+            )));
+        }
+
+        let tip_root = tip.locate(disks)?;
+        let mut chain_roots = HashMap::new();
+        for backup in catalog.chain(&tip) {
+            chain_roots.insert(backup.id(), backup.locate(disks)?);
+        }
+
+        let root = tip_root.parent().unwrap_or(&tip_root).join(format!(
+            "{}_{}",
+            tip.name(),
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+        let mut metadata = BackupMetadata::new(
+            tip.name(),
+            BackupKind::Full,
+            tip.source_paths().to_vec(),
+            root,
+        );
+        metadata.set_profile_id(tip.profile_id());
+        metadata.set_filter(tip.filter().clone());
+        metadata.set_rules(tip.rules().clone());
+        metadata.set_synthesized_from(Some(tip.id()));
+        // Retention sorts backups into periods by this, the synthetic backup
+        // takes the tip's place rather than today's.
+        metadata.set_created_at(tip.created_at());
+        metadata.set_disk_location(
+            tip.disk_id(),
+            tip.disk_path()
+                .and_then(Path::parent)
+                .zip(metadata.target_path().file_name())
+                .map(|(parent, name)| parent.join(name)),
+        );
+
+        Ok(Self {
+            tip,
+            tip_root,
+            chain_roots,
+            metadata,
+        })
+    }
+
+    pub fn tip(&self) -> &BackupMetadata {
+        &self.tip
+    }
+}
+
+/// A synthetic full backup being built in its own thread.
+pub struct SyntheticTask {
+    tip: BackupMetadata,
+    handler: JoinHandle<SanupResult<BackupMetadata>>,
+}
+
+impl SyntheticTask {
+    pub fn spawn(plan: SyntheticPlan) -> SanupResult<Self> {
+        let tip = plan.tip.clone();
+        let handler = thread::Builder::new()
+            .name(format!("synthetic-{}", tip.id()))
+            .spawn(move || run(plan))?;
+
+        Ok(Self { tip, handler })
+    }
+
+    /// The backup whose content is consolidated.
+    pub fn tip(&self) -> &BackupMetadata {
+        &self.tip
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handler.is_finished()
+    }
+
+    /// Waits for the synthetic backup and returns its metadata.
+    pub fn join(self) -> SanupResult<BackupMetadata> {
+        self.handler
+            .join()
+            .map_err(|_| SanupError::Other("The synthetic backup worker panicked".to_string()))?
+    }
+}
+
+/// Builds the synthetic backup out of the tip's own entries and the files it
+/// takes from the chain. Data is hard linked where the file system allows, so
+/// it takes next to no extra space. A failed run leaves nothing behind.
+fn run(plan: SyntheticPlan) -> SanupResult<BackupMetadata> {
+    let SyntheticPlan {
+        tip: _,
+        tip_root,
+        chain_roots,
+        mut metadata,
+    } = plan;
+    let layout = BackupLayout::new(metadata.target_path());
+    let tip_layout = BackupLayout::new(&tip_root);
+
+    metadata.start();
+    let result = build(&tip_layout, &chain_roots, &layout, &mut metadata);
+    metadata.finish();
+
+    match result {
+        Ok(()) => {
+            metadata.set_status(BackupStatus::Completed);
+            metadata.save(layout.manifest_path())?;
+            info!(
+                "Synthesized full backup {} from {}",
+                layout.root().display(),
+                tip_root.display()
+            );
+            Ok(metadata)
+        }
+        Err(err) => {
+            warn!(
+                "Failed to synthesize a full backup from {}: {}",
+                tip_root.display(),
+                err
+            );
+            let _ = fs::remove_dir_all(layout.root());
+            Err(err)
+        }
+    }
+}
+
+fn build(
+    tip_layout: &BackupLayout,
+    chain_roots: &HashMap<Uuid, PathBuf>,
+    layout: &BackupLayout,
+    metadata: &mut BackupMetadata,
+) -> SanupResult<()> {
+    fs::create_dir_all(layout.data_dir())?;
+    link_tree(&tip_layout.data_dir(), &layout.data_dir())?;
+
+    let mut index = FileIndex::load(tip_layout.index_path())?;
+    for (file, entry) in index.iter() {
+        if let Some(id) = entry.stored_in {
+            let root = chain_roots.get(&id).ok_or_else(|| {
+                SanupError::Other(format!(
+                    "{} is stored in backup {}, which is not in the chain",
+                    file.display(),
+                    id
+                ))
+            })?;
+            let target = layout.stored_path(file);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            link_file(&BackupLayout::new(root).stored_path(file), &target)?;
+        }
+        metadata.add_file(entry.size);
+    }
+
+    for id in index.referenced_backups() {
+        index.move_storage(id, None);
+    }
+    metadata.set_estimated_size_bytes(Some(metadata.total_size_bytes()));
+    index.save(layout.index_path())?;
+
+    Ok(())
+}
+
+/// Recreates the directory tree at `from` under `to`, linking everything in
+/// it.
+fn link_tree(from: &Path, to: &Path) -> SanupResult<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            link_tree(&entry.path(), &target)?;
+        } else {
+            link_file(&entry.path(), &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Hard links `from` to `to`, falling back to copying across file systems.
+fn link_file(from: &Path, to: &Path) -> SanupResult<()> {
+    if fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if fs::symlink_metadata(from)?.file_type().is_symlink() {
+        symlink(fs::read_link(from)?, to)?;
+    } else {
+        fs::copy(from, to)?;
+    }
+
+    Ok(())
+}
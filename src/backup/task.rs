@@ -1,9 +1,237 @@
-use crate::backup::{metadata::BackupMetadata, status::BackupStatus, worker::BackupWorker};
+use crate::{
+    backup::{
+        disk::BackupDisk,
+        message::{Message, WorkerEvent},
+        metadata::BackupMetadata,
+        preflight::FreeSpacePolicy,
+        status::BackupStatus,
+        worker::BackupWorker,
+    },
+    error::SanupResult,
+};
+use log::warn;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// How far a backup got, for throughput and ETA. Time spent paused does not
+/// count as elapsed.
+#[derive(Default)]
+pub struct TaskProgress {
+    pub done_bytes: u64,
+    pub total_bytes: u64,
+    started_at: Option<Instant>,
+    finished_at: Option<Instant>,
+    paused_at: Option<Instant>,
+    paused_for: Duration,
+}
+
+impl TaskProgress {
+    pub fn elapsed(&self) -> Duration {
+        let Some(started_at) = self.started_at else {
+            return Duration::ZERO;
+        };
+        let end = self
+            .finished_at
+            .or(self.paused_at)
+            .unwrap_or_else(Instant::now);
+
+        end.saturating_duration_since(started_at)
+            .saturating_sub(self.paused_for)
+    }
+
+    /// From 0.0 to 1.0.
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            (self.done_bytes as f32 / self.total_bytes as f32).min(1.0)
+        }
+    }
+
+    fn pause(&mut self) {
+        self.paused_at.get_or_insert_with(Instant::now);
+    }
+
+    fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_for += paused_at.elapsed();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.resume();
+        self.finished_at = Some(Instant::now());
+    }
+}
+
 pub struct BackupTask {
     id: Uuid,
     metadata: BackupMetadata,
     status: BackupStatus,
+    /// The backup this one builds on first, then the ones that builds on.
+    chain: Vec<BackupMetadata>,
     worker: Option<BackupWorker>,
+    progress: TaskProgress,
+    /// Files that could not be backed up so far.
+    failed_files: Vec<String>,
+}
+
+impl BackupTask {
+    pub fn new(metadata: BackupMetadata) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            metadata,
+            status: BackupStatus::Pending,
+            chain: Vec::new(),
+            worker: None,
+            progress: TaskProgress::default(),
+            failed_files: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn metadata(&self) -> &BackupMetadata {
+        &self.metadata
+    }
+
+    pub fn status(&self) -> &BackupStatus {
+        &self.status
+    }
+
+    pub fn progress(&self) -> &TaskProgress {
+        &self.progress
+    }
+
+    pub fn failed_files(&self) -> &[String] {
+        &self.failed_files
+    }
+
+    pub fn worker(&self) -> Option<&BackupWorker> {
+        self.worker.as_ref()
+    }
+
+    /// Makes the backup store only the changes against `parent`, which builds
+    /// on the backups of `ancestors`.
+    pub fn set_parent(&mut self, parent: BackupMetadata, ancestors: Vec<BackupMetadata>) {
+        self.metadata.set_parent_id(Some(parent.id()));
+        self.chain = vec![parent];
+        self.chain.extend(ancestors);
+    }
+
+    /// Starts copying in a worker thread. Removable disks get a marker file
+    /// first, so the backup stays tied to the disk wherever it is mounted.
+    /// With `verify` the stored files are checked once copied.
+    pub fn start(
+        &mut self,
+        mut disk: BackupDisk,
+        policy: Option<FreeSpacePolicy>,
+        verify: bool,
+    ) -> SanupResult<()> {
+        if disk.removable()
+            && let Err(err) = disk.write_marker()
+        {
+            warn!("Failed to write disk marker to {}: {}", disk.label(), err);
+        }
+        self.metadata.set_disk(&disk);
+
+        self.worker = Some(BackupWorker::spawn(
+            self.metadata.clone(),
+            std::mem::take(&mut self.chain),
+            disk,
+            policy,
+            verify,
+        )?);
+        self.status = BackupStatus::Running {
+            progress: 0.0,
+            current_file: String::new(),
+        };
+        self.progress.started_at = Some(Instant::now());
+
+        Ok(())
+    }
+
+    pub fn pause(&self) {
+        self.send(Message::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.send(Message::Resume);
+    }
+
+    pub fn cancel(&self) {
+        self.send(Message::Cancel);
+    }
+
+    fn send(&self, message: Message) {
+        if let Some(worker) = &self.worker {
+            worker.send(message);
+        }
+    }
+
+    /// Applies the events reported by the worker since the last call.
+    /// Returns `true` when the backup finished during this call.
+    pub fn update(&mut self) -> bool {
+        let Some(worker) = &self.worker else {
+            return false;
+        };
+        let was_finished = self.status.is_finished();
+
+        for event in worker.events() {
+            match event {
+                WorkerEvent::Progress {
+                    progress,
+                    current_file,
+                    done_bytes,
+                    total_bytes,
+                } => {
+                    self.status = BackupStatus::Running {
+                        progress,
+                        current_file,
+                    };
+                    self.progress.done_bytes = done_bytes;
+                    self.progress.total_bytes = total_bytes;
+                }
+                WorkerEvent::FileFailed(file) => self.failed_files.push(file),
+                WorkerEvent::Paused => {
+                    self.status = BackupStatus::Paused;
+                    self.progress.pause();
+                }
+                WorkerEvent::Resumed => {
+                    self.status = BackupStatus::Running {
+                        progress: self.progress.fraction(),
+                        current_file: String::new(),
+                    };
+                    self.progress.resume();
+                }
+                WorkerEvent::Completed(metadata) => {
+                    self.metadata = *metadata;
+                    self.status = BackupStatus::Completed;
+                }
+                WorkerEvent::Failed { metadata, reason } => {
+                    self.metadata = *metadata;
+                    self.status = BackupStatus::Failed { reason };
+                }
+                WorkerEvent::Cancelled(metadata) => {
+                    self.metadata = *metadata;
+                    self.status = BackupStatus::Cancelled;
+                }
+            }
+        }
+
+        if !was_finished && self.status.is_finished() {
+            self.progress.finish();
+            if self.status == BackupStatus::Completed {
+                self.progress.done_bytes = self.progress.total_bytes;
+            }
+            self.failed_files = self.metadata.failed_files().to_vec();
+        }
+        if self.status.is_finished() && worker.is_finished() {
+            self.worker = None;
+        }
+
+        !was_finished && self.status.is_finished()
+    }
 }
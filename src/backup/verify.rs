@@ -0,0 +1,261 @@
+use crate::{
+    backup::{
+        archive::Archive,
+        index::{FileIndex, IndexEntry},
+        layout::BackupLayout,
+    },
+    config::Config,
+    error::SanupResult,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+/// Outcome of the last verification of a backup, kept in its metadata.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    verified_at: DateTime<Utc>,
+    checked: u64,
+    problems: u64,
+}
+
+impl Verification {
+    pub fn verified_at(&self) -> DateTime<Utc> {
+        self.verified_at
+    }
+
+    pub fn checked(&self) -> u64 {
+        self.checked
+    }
+
+    pub fn problems(&self) -> u64 {
+        self.problems
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.problems == 0
+    }
+}
+
+/// What is wrong with a file of a backup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    Missing,
+    NotAFile,
+    SizeMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    /// The stored data differs from the unchanged source file.
+    ContentMismatch,
+    Unreadable(String),
+}
+
+#[allow(clippy::to_string_trait_impl)]
+impl ToString for Problem {
+    fn to_string(&self) -> String {
+        match self {
+            Problem::Missing => "missing".to_string(),
+            Problem::NotAFile => "not a regular file".to_string(),
+            Problem::SizeMismatch { expected, actual } => {
+                format!("size is {} bytes, expected {}", actual, expected)
+            }
+            Problem::ContentMismatch => "content differs from the source".to_string(),
+            Problem::Unreadable(err) => format!("unreadable: {}", err),
+        }
+    }
+}
+
+/// What a verification found.
+#[derive(Default)]
+pub struct VerifyReport {
+    pub checked: u64,
+    /// Source files that changed since the backup, so their content could
+    /// not be compared.
+    pub changed_sources: u64,
+    pub problems: Vec<(PathBuf, Problem)>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn verification(&self) -> Verification {
+        Verification {
+            verified_at: Utc::now(),
+            checked: self.checked,
+            problems: self.problems.len() as u64,
+        }
+    }
+}
+
+/// Checks that every file in the index of the backup at `root` is stored
+/// with the size it was backed up with, in the backup itself or in the
+/// backup of `chain_roots` holding it.
+///
+/// With `against_source` the stored data is also compared byte for byte
+/// with source files that did not change since. Files in the archive of a
+/// compressed backup are always read in full, which checks their CRC.
+pub fn verify(
+    root: &Path,
+    chain_roots: &HashMap<Uuid, PathBuf>,
+    against_source: bool,
+) -> SanupResult<VerifyReport> {
+    let layout = BackupLayout::new(root);
+    let index = FileIndex::load(layout.index_path())?;
+    let mut archive = Archive::open_in(&layout)?;
+    let mut report = VerifyReport::default();
+
+    for (file, entry) in index.iter() {
+        report.checked += 1;
+
+        if entry.stored_in.is_none()
+            && let Some(archive) = archive.as_mut()
+        {
+            if let Some(problem) =
+                verify_archived(archive, file, entry, against_source, &mut report)
+            {
+                report.problems.push((file.clone(), problem));
+            }
+            continue;
+        }
+
+        let stored = match entry.stored_in {
+            None => layout.stored_path(file),
+            Some(id) => match chain_roots.get(&id) {
+                Some(chain_root) => BackupLayout::new(chain_root).stored_path(file),
+                None => {
+                    report.problems.push((
+                        file.clone(),
+                        Problem::Unreadable(format!("backup {} holding it is missing", id)),
+                    ));
+                    continue;
+                }
+            },
+        };
+
+        let metadata = match fs::symlink_metadata(&stored) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                report.problems.push((file.clone(), Problem::Missing));
+                continue;
+            }
+            Err(err) => {
+                report
+                    .problems
+                    .push((file.clone(), Problem::Unreadable(err.to_string())));
+                continue;
+            }
+        };
+
+        if !metadata.is_file() {
+            report.problems.push((file.clone(), Problem::NotAFile));
+            continue;
+        }
+        if metadata.len() != entry.size {
+            report.problems.push((
+                file.clone(),
+                Problem::SizeMismatch {
+                    expected: entry.size,
+                    actual: metadata.len(),
+                },
+            ));
+            continue;
+        }
+
+        if !against_source {
+            continue;
+        }
+
+        if !source_unchanged(file, entry) {
+            report.changed_sources += 1;
+            continue;
+        }
+
+        match File::open(&stored).and_then(|stored| same_content(stored, File::open(file)?)) {
+            Ok(true) => {}
+            Ok(false) => report
+                .problems
+                .push((file.clone(), Problem::ContentMismatch)),
+            Err(err) => report
+                .problems
+                .push((file.clone(), Problem::Unreadable(err.to_string()))),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Checks a file stored in the archive of a compressed backup, reading it to
+/// the end so a corrupted entry fails its CRC.
+fn verify_archived(
+    archive: &mut Archive,
+    file: &Path,
+    entry: &IndexEntry,
+    against_source: bool,
+    report: &mut VerifyReport,
+) -> Option<Problem> {
+    let mut stored = match archive.file(file) {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return Some(Problem::Missing),
+        Err(err) => return Some(Problem::Unreadable(err.to_string())),
+    };
+
+    if !stored.is_file() {
+        return Some(Problem::NotAFile);
+    }
+    if stored.size() != entry.size {
+        return Some(Problem::SizeMismatch {
+            expected: entry.size,
+            actual: stored.size(),
+        });
+    }
+
+    let compared = against_source && source_unchanged(file, entry);
+    if against_source && !compared {
+        report.changed_sources += 1;
+    }
+    let result = if compared {
+        File::open(file).and_then(|source| same_content(&mut stored, source))
+    } else {
+        io::copy(&mut stored, &mut io::sink()).map(|_| true)
+    };
+
+    match result {
+        Ok(true) => None,
+        Ok(false) => Some(Problem::ContentMismatch),
+        Err(err) => Some(Problem::Unreadable(err.to_string())),
+    }
+}
+
+/// Whether the source file is still as it was backed up.
+fn source_unchanged(file: &Path, entry: &IndexEntry) -> bool {
+    fs::symlink_metadata(file)
+        .is_ok_and(|source| source.is_file() && entry.is_unchanged(&IndexEntry::new(&source)))
+}
+
+/// Compares two streams of the same length.
+fn same_content(a: impl Read, b: impl Read) -> io::Result<bool> {
+    let mut a = BufReader::new(a);
+    let mut b = BufReader::new(b);
+    let mut buf_a = vec![0; 64 * 1024];
+    let mut buf_b = vec![0; 64 * 1024];
+
+    loop {
+        let read = a.read(&mut buf_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buf_b[..read])?;
+        if buf_a[..read] != buf_b[..read] {
+            return Ok(false);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{
+    backup::{discovery::DiskDiscovery, disk::BackupDisk},
+    error::SanupResult,
+};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Notices disks being mounted by polling discovery.
+///
+/// Disks are told apart by their disk id, mount paths change from one
+/// plug-in to the next. Disks that are already mounted on the first poll are
+/// not reported, so starting sanup does not kick off backups on its own.
+pub struct DiskWatcher {
+    discovery: DiskDiscovery,
+    mounted: Option<HashSet<Uuid>>,
+    last_poll: Option<Instant>,
+}
+
+impl DiskWatcher {
+    pub fn new(discovery: DiskDiscovery) -> Self {
+        Self {
+            discovery,
+            mounted: None,
+            last_poll: None,
+        }
+    }
+
+    /// Returns the disks mounted since the last poll. Does nothing until the
+    /// poll interval has passed.
+    pub fn poll(&mut self) -> SanupResult<Vec<BackupDisk>> {
+        if self
+            .last_poll
+            .is_some_and(|last_poll| last_poll.elapsed() < POLL_INTERVAL)
+        {
+            return Ok(Vec::new());
+        }
+        self.last_poll = Some(Instant::now());
+
+        let disks = self.discovery.disks()?;
+        let ids: HashSet<Uuid> = disks.iter().map(BackupDisk::id).collect();
+
+        let appeared = match &self.mounted {
+            Some(mounted) => disks
+                .into_iter()
+                .filter(|disk| !mounted.contains(&disk.id()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        self.mounted = Some(ids);
+        Ok(appeared)
+    }
+}
@@ -1,8 +1,552 @@
-use crate::backup::{disk::BackupDisk, message::Message};
-use std::{sync::mpsc::Sender, thread::JoinHandle};
+use crate::{
+    app::compression_kind::CompressionKind,
+    backup::{
+        archive::ArchiveWriter,
+        checksum::checksum,
+        disk::BackupDisk,
+        index::{FileIndex, IndexEntry},
+        kind::BackupKind,
+        layout::{BackupLayout, normalize_sources},
+        message::{Message, WorkerEvent},
+        metadata::BackupMetadata,
+        preflight::{FreeSpacePolicy, SpaceVerdict, check_free_space},
+        scan::SizeScan,
+        status::BackupStatus,
+        verify,
+    },
+    config::Config,
+    error::{SanupError, SanupResult},
+};
+use log::{info, warn};
+use nix::sys::{
+    stat::{Mode, SFlag, mknod, utimes},
+    time::TimeVal,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::{MetadataExt, symlink},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender, TryIter},
+    thread::{self, JoinHandle},
+};
+use uuid::Uuid;
 
 pub struct BackupWorker {
     handler: JoinHandle<()>,
     tx: Sender<Message>,
+    rx: Receiver<WorkerEvent>,
     disk: BackupDisk,
 }
+
+impl BackupWorker {
+    /// Runs the backup in its own thread. With a `chain`, the parent first,
+    /// files that did not change since the parent are not copied again. With
+    /// `verify` the stored files are checked once copied.
+    pub fn spawn(
+        metadata: BackupMetadata,
+        chain: Vec<BackupMetadata>,
+        disk: BackupDisk,
+        policy: Option<FreeSpacePolicy>,
+        verify: bool,
+    ) -> SanupResult<Self> {
+        let (tx, worker_rx) = mpsc::channel();
+        let (worker_tx, rx) = mpsc::channel();
+
+        let chain: Vec<(Uuid, PathBuf)> = chain
+            .iter()
+            .map(|backup| {
+                let root = backup
+                    .located_on(&disk)
+                    .unwrap_or_else(|| backup.target_path().to_path_buf());
+                (backup.id(), root)
+            })
+            .collect();
+
+        let handler = thread::Builder::new()
+            .name(format!("backup-{}", metadata.id()))
+            .spawn(move || run(metadata, chain, policy, verify, worker_rx, worker_tx))?;
+
+        Ok(Self {
+            handler,
+            tx,
+            rx,
+            disk,
+        })
+    }
+
+    pub fn send(&self, message: Message) -> bool {
+        self.tx.send(message).is_ok()
+    }
+
+    pub fn events(&self) -> TryIter<'_, WorkerEvent> {
+        self.rx.try_iter()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handler.is_finished()
+    }
+
+    pub fn disk(&self) -> &BackupDisk {
+        &self.disk
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    Dir,
+    File,
+    Symlink,
+    Special,
+}
+
+struct Entry {
+    path: PathBuf,
+    kind: EntryKind,
+    size: u64,
+    index_entry: Option<IndexEntry>,
+}
+
+/// What the current backup is compared against: the parent id and index.
+type Parent = Option<(Uuid, FileIndex)>;
+
+struct Progress {
+    done_bytes: u64,
+    total_bytes: u64,
+}
+
+impl Progress {
+    fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            (self.done_bytes as f32 / self.total_bytes as f32).min(1.0)
+        }
+    }
+}
+
+fn run(
+    mut metadata: BackupMetadata,
+    chain: Vec<(Uuid, PathBuf)>,
+    policy: Option<FreeSpacePolicy>,
+    verify: bool,
+    rx: Receiver<Message>,
+    tx: Sender<WorkerEvent>,
+) {
+    metadata.start();
+    info!("Backup {} started", metadata.name());
+
+    // Compressed backups are written to one archive and never build on a
+    // parent, the files they hold could not be pointed at from another one.
+    let compressed = *metadata.compression() != CompressionKind::None;
+    let layout = BackupLayout::new(metadata.target_path());
+    let dir = if compressed {
+        layout.root().to_path_buf()
+    } else {
+        layout.data_dir()
+    };
+    if let Err(err) = fs::create_dir_all(dir) {
+        fail(metadata, &tx, err.to_string());
+        return;
+    }
+
+    let parent: Parent = chain
+        .first()
+        .filter(|_| !compressed)
+        .and_then(
+            |(id, root)| match FileIndex::load(BackupLayout::new(root).index_path()) {
+                Ok(index) => Some((*id, index)),
+                Err(err) => {
+                    warn!(
+                        "Failed to read index of parent backup {}, copying everything: {}",
+                        id, err
+                    );
+                    None
+                }
+            },
+        );
+    if parent.is_none() && metadata.parent_id().is_some() {
+        metadata.set_parent_id(None);
+        metadata.set_kind(BackupKind::Full);
+    }
+    let mut index = FileIndex::default();
+
+    let sources = normalize_sources(metadata.source_paths());
+    metadata.set_source_paths(sources.clone());
+
+    // Only files that changed since the parent take up space on the target,
+    // progress goes over everything as unchanged files are walked too.
+    let scan = SizeScan::new(sources.clone())
+        .with_selection(metadata.filter().clone(), metadata.rules().clone())
+        .run_against(parent.as_ref().map(|(_, index)| index));
+    let total_bytes = metadata.estimated_size_bytes().unwrap_or(scan.total.bytes);
+    metadata.set_estimated_size_bytes(Some(total_bytes));
+
+    if let Some(policy) = policy {
+        match check_free_space(layout.root(), scan.changed.bytes, policy) {
+            Ok(check) => match check.verdict {
+                SpaceVerdict::Enough => {}
+                SpaceVerdict::BelowReserve => {
+                    warn!(
+                        "Backup {} leaves less free space than reserved: {}",
+                        metadata.name(),
+                        check
+                    );
+                }
+                SpaceVerdict::Insufficient => {
+                    fail(
+                        metadata,
+                        &tx,
+                        format!("Not enough free space on target: {}", check),
+                    );
+                    return;
+                }
+            },
+            Err(err) => warn!("Failed to check free space: {}", err),
+        }
+    }
+
+    let mut archive = if compressed {
+        match ArchiveWriter::create(&layout, metadata.compression_level()) {
+            Ok(archive) => Some(archive),
+            Err(err) => {
+                fail(metadata, &tx, err.to_string());
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut progress = Progress {
+        done_bytes: 0,
+        total_bytes,
+    };
+
+    for source in &sources {
+        let mut state = SourceState {
+            layout: &layout,
+            parent: &parent,
+            archive: archive.as_mut(),
+            index: &mut index,
+            progress: &mut progress,
+        };
+        if !backup_source(source, &mut metadata, &mut state, &rx, &tx) {
+            if let Some(archive) = archive {
+                let _ = archive.finish();
+            }
+            metadata.finish();
+            metadata.set_status(BackupStatus::Cancelled);
+            let _ = index.save(layout.index_path());
+            let _ = metadata.save(layout.manifest_path());
+            info!("Backup {} cancelled", metadata.name());
+            let _ = tx.send(WorkerEvent::Cancelled(Box::new(metadata)));
+            return;
+        }
+    }
+
+    if let Some(archive) = archive
+        && let Err(err) = archive.finish()
+    {
+        fail(metadata, &tx, err.to_string());
+        return;
+    }
+
+    if let Some(algorithm) = metadata.hash_algorithm() {
+        match checksum(&layout, &index, algorithm) {
+            Ok(checksum) => metadata.set_archive_checksum(Some(checksum)),
+            Err(err) => warn!("Failed to hash backup {}: {}", metadata.name(), err),
+        }
+    }
+
+    metadata.finish();
+    metadata.set_status(BackupStatus::Completed);
+
+    match index.save(layout.index_path()).and_then(|()| {
+        if verify {
+            verify_backup(&mut metadata, layout.root(), &chain.into_iter().collect());
+        }
+        metadata.save(layout.manifest_path())
+    }) {
+        Ok(()) => {
+            let skipped = metadata.skipped();
+            info!(
+                "Backup {} completed: {} files, {} unchanged, {} failed, skipped by rule: {} too large, {} too old, {} not owned, {} special files",
+                metadata.name(),
+                metadata.file_count(),
+                metadata.unchanged_files(),
+                metadata.failed_files().len(),
+                skipped.too_large,
+                skipped.too_old,
+                skipped.not_owned,
+                skipped.special_file
+            );
+            let _ = tx.send(WorkerEvent::Completed(Box::new(metadata)));
+        }
+        Err(err) => fail(metadata, &tx, err.to_string()),
+    }
+}
+
+/// Checks that every file of the backup is stored with its size, recording
+/// the outcome in the metadata.
+fn verify_backup(metadata: &mut BackupMetadata, root: &Path, chain_roots: &HashMap<Uuid, PathBuf>) {
+    match verify::verify(root, chain_roots, false) {
+        Ok(report) => {
+            for (file, problem) in &report.problems {
+                warn!("{}: {}", file.display(), problem.to_string());
+            }
+            if !report.is_ok() {
+                warn!(
+                    "Verification of backup {} found {} problems",
+                    metadata.name(),
+                    report.problems.len()
+                );
+            }
+            metadata.set_verification(Some(report.verification()));
+        }
+        Err(err) => warn!("Failed to verify backup {}: {}", metadata.name(), err),
+    }
+}
+
+fn fail(mut metadata: BackupMetadata, tx: &Sender<WorkerEvent>, reason: String) {
+    metadata.finish();
+    metadata.set_status(BackupStatus::Failed {
+        reason: reason.clone(),
+    });
+    warn!("Backup {} failed: {}", metadata.name(), reason);
+    let _ = tx.send(WorkerEvent::Failed {
+        metadata: Box::new(metadata),
+        reason,
+    });
+}
+
+/// Handles pending control messages. Returns `false` when the backup has to
+/// stop, either because it was cancelled or because the task was dropped.
+fn wait_if_paused(rx: &Receiver<Message>, tx: &Sender<WorkerEvent>) -> bool {
+    let mut paused = false;
+
+    loop {
+        let message = if paused {
+            rx.recv().ok()
+        } else {
+            rx.try_recv().ok()
+        };
+
+        match message {
+            Some(Message::Pause) => {
+                if !paused {
+                    paused = true;
+                    let _ = tx.send(WorkerEvent::Paused);
+                }
+            }
+            Some(Message::Resume) => {
+                if paused {
+                    paused = false;
+                    let _ = tx.send(WorkerEvent::Resumed);
+                }
+            }
+            Some(Message::Cancel) => return false,
+            None => return !paused,
+        }
+    }
+}
+
+struct SourceState<'a> {
+    layout: &'a BackupLayout,
+    parent: &'a Parent,
+    archive: Option<&'a mut ArchiveWriter>,
+    index: &'a mut FileIndex,
+    progress: &'a mut Progress,
+}
+
+/// Copies one source entry by entry, skipping files that are unchanged since
+/// the parent. Returns `false` when the backup was cancelled on the way.
+fn backup_source(
+    source: &Path,
+    metadata: &mut BackupMetadata,
+    state: &mut SourceState,
+    rx: &Receiver<Message>,
+    tx: &Sender<WorkerEvent>,
+) -> bool {
+    let walk = match metadata.filter().walk(source) {
+        Ok(walk) => walk,
+        Err(err) => {
+            warn!("Failed to read {}: {}", source.display(), err);
+            add_failed_file(metadata, tx, source.to_string_lossy().to_string());
+            return true;
+        }
+    };
+
+    for result in walk {
+        if !wait_if_paused(rx, tx) {
+            return false;
+        }
+
+        let Some(entry) = to_entry(result, source, metadata, tx) else {
+            continue;
+        };
+
+        let _ = tx.send(WorkerEvent::Progress {
+            progress: state.progress.fraction(),
+            current_file: entry.path.to_string_lossy().to_string(),
+            done_bytes: state.progress.done_bytes,
+            total_bytes: state.progress.total_bytes,
+        });
+        state.progress.done_bytes += entry.size;
+
+        if let Some(index_entry) = &entry.index_entry
+            && let Some((parent_id, parent_index)) = state.parent
+            && let Some(previous) = parent_index.get(&entry.path)
+            && previous.is_unchanged(index_entry)
+        {
+            let stored_in = previous.stored_in.unwrap_or(*parent_id);
+            state.index.insert(
+                entry.path,
+                IndexEntry {
+                    stored_in: Some(stored_in),
+                    ..index_entry.clone()
+                },
+            );
+            metadata.add_unchanged_file();
+            continue;
+        }
+
+        let stored = match state.archive.as_deref_mut() {
+            Some(archive) => archive_entry(&entry, archive),
+            None => copy_entry(&entry, &state.layout.stored_path(&entry.path)),
+        };
+        match stored {
+            Ok(()) => {
+                if let Some(index_entry) = entry.index_entry {
+                    metadata.add_file(entry.size);
+                    state.index.insert(entry.path, index_entry);
+                }
+            }
+            Err(err) => {
+                warn!("Failed to back up {}: {}", entry.path.display(), err);
+                add_failed_file(metadata, tx, entry.path.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    true
+}
+
+/// Records a file that could not be backed up and reports it right away, so
+/// it shows while the backup is still running.
+fn add_failed_file(metadata: &mut BackupMetadata, tx: &Sender<WorkerEvent>, file: String) {
+    metadata.add_failed_file(file.clone());
+    let _ = tx.send(WorkerEvent::FileFailed(file));
+}
+
+/// Turns a walked entry into something to copy, applying the selection rules.
+fn to_entry(
+    result: Result<ignore::DirEntry, ignore::Error>,
+    source: &Path,
+    metadata: &mut BackupMetadata,
+    tx: &Sender<WorkerEvent>,
+) -> Option<Entry> {
+    let entry = match result {
+        Ok(entry) => entry,
+        Err(err) => {
+            warn!("Failed to read entry in {}: {}", source.display(), err);
+            add_failed_file(metadata, tx, err.to_string());
+            return None;
+        }
+    };
+
+    let file_type = entry.file_type()?;
+
+    let file_metadata = match entry.metadata() {
+        Ok(file_metadata) => file_metadata,
+        Err(err) => {
+            warn!("Failed to read {}: {}", entry.path().display(), err);
+            add_failed_file(metadata, tx, entry.path().to_string_lossy().to_string());
+            return None;
+        }
+    };
+
+    if let Some(reason) = metadata.rules().check(&file_metadata) {
+        metadata.add_skipped(reason);
+        return None;
+    }
+
+    let (kind, size) = if file_type.is_symlink() {
+        (EntryKind::Symlink, 0)
+    } else if file_type.is_dir() {
+        (EntryKind::Dir, 0)
+    } else if file_type.is_file() {
+        (EntryKind::File, file_metadata.len())
+    } else {
+        (EntryKind::Special, 0)
+    };
+
+    Some(Entry {
+        path: entry.into_path(),
+        kind,
+        size,
+        index_entry: (kind == EntryKind::File).then(|| IndexEntry::new(&file_metadata)),
+    })
+}
+
+fn copy_entry(entry: &Entry, target: &Path) -> SanupResult<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match entry.kind {
+        EntryKind::Dir => {
+            fs::create_dir_all(target)?;
+        }
+        EntryKind::File => {
+            fs::copy(&entry.path, target)?;
+            copy_mtime(&entry.path, target)?;
+        }
+        EntryKind::Symlink => {
+            if fs::symlink_metadata(target).is_ok() {
+                fs::remove_file(target)?;
+            }
+            symlink(fs::read_link(&entry.path)?, target)?;
+        }
+        EntryKind::Special => {
+            copy_special(&entry.path, target)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn archive_entry(entry: &Entry, archive: &mut ArchiveWriter) -> SanupResult<()> {
+    match entry.kind {
+        EntryKind::Dir => archive.add_dir(&entry.path),
+        EntryKind::File => archive.add_file(&entry.path),
+        EntryKind::Symlink => archive.add_symlink(&entry.path),
+        EntryKind::Special => Err(SanupError::Other(
+            "Special files cannot be stored in an archive".to_string(),
+        )),
+    }
+}
+
+/// Recreates FIFOs and device files, creating device files needs root.
+pub(crate) fn copy_special(source: &Path, target: &Path) -> SanupResult<()> {
+    let source_metadata = fs::symlink_metadata(source)?;
+    let kind = SFlag::from_bits_truncate(source_metadata.mode() & SFlag::S_IFMT.bits());
+    let perm = Mode::from_bits_truncate(source_metadata.mode() & 0o7777);
+
+    if fs::symlink_metadata(target).is_ok() {
+        fs::remove_file(target)?;
+    }
+    mknod(target, kind, perm, source_metadata.rdev()).map_err(std::io::Error::from)?;
+
+    Ok(())
+}
+
+pub(crate) fn copy_mtime(source: &Path, target: &Path) -> SanupResult<()> {
+    let source_metadata = fs::metadata(source)?;
+    let atime = TimeVal::new(source_metadata.atime(), source_metadata.atime_nsec() / 1000);
+    let mtime = TimeVal::new(source_metadata.mtime(), source_metadata.mtime_nsec() / 1000);
+
+    utimes(target, &atime, &mtime).map_err(std::io::Error::from)?;
+
+    Ok(())
+}
@@ -0,0 +1,900 @@
+use crate::{
+    app::{compression_kind::CompressionKind, engine::Engine, settings::Settings},
+    backup::{
+        catalog::Catalog,
+        discovery::DiskDiscovery,
+        disk::BackupDisk,
+        metadata::BackupMetadata,
+        profile::BackupProfile,
+        restore,
+        retention::{self, KeepReason},
+        status::BackupStatus,
+        task::BackupTask,
+        verify,
+    },
+    config::Config,
+    error::SanupError,
+    json::{BackupJson, Event, VerifyJson},
+    paths::Overrides,
+    systemd,
+    ui::format_bytes,
+};
+use chrono::{DateTime, Local, Utc};
+use clap::{Parser, Subcommand};
+use log::info;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+    time::Duration,
+};
+use toml::{Table, Value};
+use uuid::Uuid;
+
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+#[derive(Parser)]
+#[command(name = "sanup", version, about = "Backups from the terminal")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Settings file, defaults to $XDG_CONFIG_HOME/sanup/config.toml
+    #[arg(long, global = true, env = "SANUP_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Directory of the backup catalog, defaults to $XDG_DATA_HOME/sanup
+    #[arg(long, global = true, env = "SANUP_DATA_DIR", value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Directory of logs and other state, defaults to $XDG_STATE_HOME/sanup
+    #[arg(long, global = true, env = "SANUP_STATE_DIR", value_name = "DIR")]
+    pub state_dir: Option<PathBuf>,
+    /// Write logs to this directory, whatever the settings say
+    #[arg(long, global = true, env = "SANUP_LOG_DIR", value_name = "DIR")]
+    pub log_dir: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn overrides(&self) -> Overrides {
+        Overrides {
+            config_file: self.config.clone(),
+            data_dir: self.data_dir.clone(),
+            state_dir: self.state_dir.clone(),
+            log_dir: self.log_dir.clone(),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run schedules and watched disks in the background, without the TUI
+    Daemon {
+        /// Unix socket to serve status on, defaults to $XDG_RUNTIME_DIR/sanup.sock
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Run a backup profile and wait for it to finish
+    Backup {
+        /// Name of the profile
+        profile: String,
+        /// Print progress as newline-delimited JSON events
+        #[arg(long)]
+        json: bool,
+    },
+    /// Restore files from a backup
+    Restore {
+        /// Id of the backup, or a unique prefix of it
+        id: String,
+        /// Files or directories to restore, all sources when empty
+        paths: Vec<PathBuf>,
+        /// Restore under this directory instead of the original location
+        #[arg(long)]
+        to: Option<PathBuf>,
+        /// Print progress as newline-delimited JSON events
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the backups in the catalog
+    List {
+        /// Only list backups of this profile
+        #[arg(long)]
+        profile: Option<String>,
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show everything known about a backup
+    Show {
+        /// Id of the backup, or a unique prefix of it
+        id: String,
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check that every file of a backup is stored intact
+    Verify {
+        /// Id of the backup, or a unique prefix of it
+        id: String,
+        /// Also compare the data with source files that did not change since
+        #[arg(long)]
+        source: bool,
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove backups the retention rules of their profile no longer keep
+    Prune {
+        /// Only prune this profile
+        #[arg(long)]
+        profile: Option<String>,
+        /// Show what would be removed without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Read and change settings
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage systemd user units running profiles on their schedule
+    Systemd {
+        #[command(subcommand)]
+        command: SystemdCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SystemdCommand {
+    /// Write a service and timer for the profile
+    Install {
+        /// Name of the profile
+        profile: String,
+    },
+    /// Remove the service and timer of the profile
+    Uninstall {
+        /// Name of the profile
+        profile: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print a setting, or every setting without a key
+    Get {
+        /// Name of the setting, as in the config file
+        key: Option<String>,
+    },
+    /// Change a setting
+    Set {
+        /// Name of the setting, as in the config file
+        key: String,
+        value: String,
+    },
+}
+
+/// Exit codes of the subcommands. They are part of the interface scripts
+/// rely on, so existing ones never change meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Success = 0,
+    /// Anything not covered by a more specific code.
+    Error = 1,
+    /// Bad arguments, also used by the argument parser.
+    Usage = 2,
+    /// No profile, backup or setting with that name or id.
+    NotFound = 3,
+    /// The disk holding or receiving the backup is not mounted.
+    DiskUnavailable = 4,
+    /// The backup failed or was cancelled, or verification found problems.
+    Failed = 5,
+    /// The run finished but some files could not be backed up or restored.
+    Partial = 6,
+}
+
+impl From<Exit> for ExitCode {
+    fn from(value: Exit) -> Self {
+        ExitCode::from(value as u8)
+    }
+}
+
+/// Why a subcommand failed, and the code to exit with.
+#[derive(Debug)]
+pub struct CliError {
+    pub exit: Exit,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(exit: Exit, message: String) -> Self {
+        Self { exit, message }
+    }
+}
+
+impl From<SanupError> for CliError {
+    fn from(value: SanupError) -> Self {
+        Self::new(Exit::Error, value.to_string())
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(value: std::io::Error) -> Self {
+        SanupError::from(value).into()
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(value: serde_json::Error) -> Self {
+        SanupError::from(value).into()
+    }
+}
+
+impl From<toml::ser::Error> for CliError {
+    fn from(value: toml::ser::Error) -> Self {
+        SanupError::from(value).into()
+    }
+}
+
+pub type CliResult<T> = Result<T, CliError>;
+
+/// Runs a profile without the TUI and waits for it. With `json` the progress
+/// is printed as [`Event`]s, one per line.
+pub fn backup(profile: &str, json: bool) -> CliResult<Exit> {
+    let settings = Settings::load(Settings::default_path())?;
+    let profile = find_profile(&settings, profile)?;
+
+    let mut engine = Engine::new(Catalog::default_path());
+    engine.set_automation(false);
+    engine.run_profile(&settings, profile.id());
+
+    let mut events = BackupEvents::default();
+    loop {
+        engine.tick(&settings);
+        if json {
+            for event in events.update(engine.backups()) {
+                print_json(&event)?;
+            }
+        }
+
+        if engine
+            .backups()
+            .iter()
+            .all(|backup| backup.status().is_finished())
+        {
+            break;
+        }
+        thread::sleep(TICK_RATE);
+    }
+
+    if engine.backups().is_empty() {
+        return Err(if profile.target_disks().is_empty() {
+            CliError::new(
+                Exit::Error,
+                format!("Profile {} could not be started", profile.name()),
+            )
+        } else {
+            CliError::new(
+                Exit::DiskUnavailable,
+                format!("None of the disks of profile {} is mounted", profile.name()),
+            )
+        });
+    }
+
+    // Every backup reports how it ended before the first failure is
+    // returned, a partial run only counts when nothing failed.
+    let results: Vec<CliResult<Exit>> = engine
+        .backups()
+        .iter()
+        .map(|backup| backup_exit(backup.metadata(), backup.status(), json))
+        .collect();
+    let mut exit = Exit::Success;
+    for result in results {
+        if result? == Exit::Partial {
+            exit = Exit::Partial;
+        }
+    }
+    Ok(exit)
+}
+
+/// How a backup of `backup` ended, printing what went wrong.
+fn backup_exit(metadata: &BackupMetadata, status: &BackupStatus, json: bool) -> CliResult<Exit> {
+    match status {
+        BackupStatus::Completed => {
+            if !json {
+                println!(
+                    "Backed up {} files to {}",
+                    metadata.file_count(),
+                    metadata.target_path().display()
+                );
+            }
+            if metadata.failed_files().is_empty() {
+                Ok(Exit::Success)
+            } else {
+                if !json {
+                    for file in metadata.failed_files() {
+                        eprintln!("  failed: {}", file);
+                    }
+                }
+                eprintln!(
+                    "{} files could not be backed up",
+                    metadata.failed_files().len()
+                );
+                Ok(Exit::Partial)
+            }
+        }
+        BackupStatus::Failed { reason } => Err(CliError::new(
+            Exit::Failed,
+            format!("Backup of {} failed: {}", metadata.name(), reason),
+        )),
+        status => Err(CliError::new(
+            Exit::Failed,
+            format!("Backup of {} ended as {:?}", metadata.name(), status),
+        )),
+    }
+}
+
+/// Turns the state of the running backups into [`Event`]s: a start, every
+/// change of state and the finish of each of them.
+#[derive(Default)]
+pub(crate) struct BackupEvents {
+    last_status: HashMap<Uuid, BackupStatus>,
+}
+
+impl BackupEvents {
+    /// The events since the last call.
+    pub(crate) fn update(&mut self, backups: &[BackupTask]) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        for backup in backups {
+            let metadata = backup.metadata();
+            let status = backup.status();
+            let last_status = self.last_status.insert(metadata.id(), status.clone());
+            if last_status.as_ref().is_some_and(BackupStatus::is_finished) {
+                continue;
+            }
+
+            if last_status.is_none() {
+                events.push(Event::BackupStarted {
+                    backup: metadata.into(),
+                });
+            }
+            if status.is_finished() {
+                events.push(Event::BackupFinished {
+                    backup: metadata.into(),
+                });
+            } else if last_status.as_ref() != Some(status) {
+                events.push(Event::BackupStatus {
+                    id: metadata.id(),
+                    status: status.into(),
+                });
+            }
+        }
+
+        events
+    }
+}
+
+/// Restores `paths` of a backup, reading the data from wherever its disk is
+/// mounted now. Relative paths are taken from the working directory. With
+/// `json` every restored file is printed as an [`Event`].
+pub fn restore(id: &str, paths: &[PathBuf], to: Option<&Path>, json: bool) -> CliResult<Exit> {
+    let paths = &paths
+        .iter()
+        .map(|path| absolute_path(path))
+        .collect::<CliResult<Vec<_>>>()?;
+    let to = to.map(absolute_path).transpose()?;
+    let to = to.as_deref();
+
+    let catalog = Catalog::load(Catalog::default_path())?;
+    let metadata = find_backup(&catalog, id)?;
+    if !metadata.is_completed() {
+        return Err(CliError::new(
+            Exit::Failed,
+            format!("Backup {} did not complete", metadata.id()),
+        ));
+    }
+
+    let disks = DiskDiscovery::default().disks().unwrap_or_default();
+    let metadata = located(metadata, &disks)?;
+    let chain = catalog
+        .chain(&metadata)
+        .iter()
+        .map(|backup| located(backup, &disks))
+        .collect::<CliResult<Vec<_>>>()?;
+
+    let summary = if json {
+        let id = metadata.id();
+        print_json(&Event::RestoreStarted {
+            id,
+            paths: paths.to_vec(),
+            to: to.map(Path::to_path_buf),
+        })?;
+
+        let mut print_error = None;
+        let summary =
+            restore::restore_with_progress(&metadata, &chain, paths, to, &mut |path, summary| {
+                let event = Event::RestoredFile {
+                    id,
+                    path: path.to_path_buf(),
+                    file_count: summary.file_count,
+                    total_size_bytes: summary.total_size_bytes,
+                };
+                if let Err(err) = print_json(&event) {
+                    print_error.get_or_insert(err);
+                }
+            })?;
+        if let Some(err) = print_error {
+            return Err(err);
+        }
+
+        print_json(&Event::restore_finished(id, &summary))?;
+        summary
+    } else {
+        let summary = restore::restore(&metadata, &chain, paths, to)?;
+        println!(
+            "Restored {} files ({})",
+            summary.file_count,
+            format_bytes(summary.total_size_bytes)
+        );
+        for file in &summary.failed_files {
+            eprintln!("  failed: {}", file);
+        }
+        summary
+    };
+
+    if summary.failed_files.is_empty() {
+        Ok(Exit::Success)
+    } else {
+        eprintln!("{} files could not be restored", summary.failed_files.len());
+        Ok(Exit::Partial)
+    }
+}
+
+/// Lists the backups in the catalog, oldest first. With `json` they are
+/// printed as one array of [`BackupJson`].
+pub fn list(profile: Option<&str>, json: bool) -> CliResult<Exit> {
+    let profile_id = match profile {
+        Some(name) => Some(find_profile(&Settings::load(Settings::default_path())?, name)?.id()),
+        None => None,
+    };
+
+    let catalog = Catalog::load(Catalog::default_path())?;
+    let mut backups: Vec<&BackupMetadata> = catalog
+        .backups()
+        .iter()
+        .filter(|backup| profile_id.is_none_or(|id| backup.profile_id() == Some(id)))
+        .collect();
+    backups.sort_by_key(|backup| backup.created_at());
+
+    if json {
+        let backups: Vec<BackupJson> = backups.into_iter().map(BackupJson::from).collect();
+        print_json(&backups)?;
+        return Ok(Exit::Success);
+    }
+
+    for backup in backups {
+        println!(
+            "{}  {}  {:<12}  {:<9}  {:>10}  {:>7}  {}",
+            backup.id(),
+            backup
+                .created_at()
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M"),
+            backup.kind().to_string(),
+            status_name(backup.status()),
+            format_bytes(backup.total_size_bytes()),
+            backup.file_count(),
+            backup.name()
+        );
+    }
+
+    Ok(Exit::Success)
+}
+
+/// Prints everything the catalog knows about a backup.
+pub fn show(id: &str, json: bool) -> CliResult<Exit> {
+    let catalog = Catalog::load(Catalog::default_path())?;
+    let backup = find_backup(&catalog, id)?;
+
+    if json {
+        print_json(&BackupJson::from(backup))?;
+        return Ok(Exit::Success);
+    }
+
+    let settings = Settings::load(Settings::default_path())?;
+    let date = |date: DateTime<Utc>| {
+        date.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+
+    println!("Id:        {}", backup.id());
+    println!("Name:      {}", backup.name());
+    println!("Kind:      {}", backup.kind().to_string());
+    if *backup.compression() != CompressionKind::None {
+        println!(
+            "Compression: {} ({})",
+            backup.compression().to_string(),
+            backup.compression_level().to_string()
+        );
+    }
+    if let Some(profile_id) = backup.profile_id() {
+        println!(
+            "Profile:   {}",
+            settings
+                .profile(profile_id)
+                .map(|profile| profile.name().to_string())
+                .unwrap_or_else(|| profile_id.to_string())
+        );
+    }
+    if let Some(parent_id) = backup.parent_id() {
+        println!("Parent:    {}", parent_id);
+    }
+    if let Some(synthesized_from) = backup.synthesized_from() {
+        println!("Synthesized from: {}", synthesized_from);
+    }
+    println!("Status:    {}", status_name(backup.status()));
+    if let BackupStatus::Failed { reason } = backup.status() {
+        println!("Reason:    {}", reason);
+    }
+    println!("Created:   {}", date(backup.created_at()));
+    if let Some(duration) = backup.duration() {
+        println!("Duration:  {}s", duration.num_seconds());
+    }
+    println!(
+        "Files:     {} ({} unchanged, {} failed)",
+        backup.file_count(),
+        backup.unchanged_files(),
+        backup.failed_files().len()
+    );
+    println!("Size:      {}", format_bytes(backup.total_size_bytes()));
+    println!("Target:    {}", backup.target_path().display());
+    for source in backup.source_paths() {
+        println!("Source:    {}", source.display());
+    }
+    match backup.verification() {
+        Some(verification) if verification.is_ok() => println!(
+            "Verified:  {}, {} files ok",
+            date(verification.verified_at()),
+            verification.checked()
+        ),
+        Some(verification) => println!(
+            "Verified:  {}, {} of {} files with problems",
+            date(verification.verified_at()),
+            verification.problems(),
+            verification.checked()
+        ),
+        None => println!("Verified:  never"),
+    }
+    if let Some(note) = backup.note() {
+        println!("Note:      {}", note);
+    }
+    for file in backup.failed_files() {
+        println!("Failed:    {}", file);
+    }
+
+    Ok(Exit::Success)
+}
+
+/// Verifies a backup and records the outcome in the catalog. With `json`
+/// the outcome is printed as one [`VerifyJson`].
+pub fn verify(id: &str, against_source: bool, json: bool) -> CliResult<Exit> {
+    let catalog_path = Catalog::default_path();
+    let catalog = Catalog::load(&catalog_path)?;
+    let metadata = find_backup(&catalog, id)?;
+
+    let disks = DiskDiscovery::default().disks().unwrap_or_default();
+    let root = locate(metadata, &disks)?;
+    let mut chain_roots = HashMap::new();
+    for backup in catalog.chain(metadata) {
+        chain_roots.insert(backup.id(), locate(&backup, &disks)?);
+    }
+
+    let report = verify::verify(&root, &chain_roots, against_source)?;
+    if json {
+        print_json(&VerifyJson::new(metadata.id(), &report))?;
+    } else {
+        for (file, problem) in &report.problems {
+            println!("{}: {}", file.display(), problem.to_string());
+        }
+        println!(
+            "Checked {} files, {} problems",
+            report.checked,
+            report.problems.len()
+        );
+        if against_source && report.changed_sources > 0 {
+            println!(
+                "{} source files changed since the backup and were not compared",
+                report.changed_sources
+            );
+        }
+    }
+
+    let id = metadata.id();
+    let mut catalog = Catalog::load(&catalog_path)?;
+    if let Some(backup) = catalog.get(id) {
+        let mut backup = backup.clone();
+        backup.set_verification(Some(report.verification()));
+        catalog.record(backup);
+        catalog.save(&catalog_path)?;
+    }
+
+    Ok(if report.is_ok() {
+        Exit::Success
+    } else {
+        Exit::Failed
+    })
+}
+
+/// Prunes every profile with retention rules, or only `profile`.
+pub fn prune(profile: Option<&str>, dry_run: bool) -> CliResult<Exit> {
+    let settings = Settings::load(Settings::default_path())?;
+    if let Some(name) = profile {
+        find_profile(&settings, name)?;
+    }
+
+    let catalog_path = Catalog::default_path();
+    let mut catalog = Catalog::load(&catalog_path)?;
+    let disks = DiskDiscovery::default().disks().unwrap_or_default();
+    let now = Utc::now();
+    let mut failed = 0;
+
+    for profile in settings
+        .profiles()
+        .iter()
+        .filter(|candidate| profile.is_none_or(|name| candidate.name() == name))
+    {
+        let Some(rules) = profile.retention() else {
+            println!("{}: no retention rules, keeping everything", profile.name());
+            continue;
+        };
+
+        let plan = retention::plan(&catalog, profile.id(), rules, now);
+        println!(
+            "{}: keeping {}, removing {} ({})",
+            profile.name(),
+            plan.keep.len(),
+            plan.remove.len(),
+            format_bytes(plan.reclaimable_bytes())
+        );
+        for (backup, reasons) in &plan.keep {
+            let reasons: Vec<String> = reasons.iter().map(KeepReason::to_string).collect();
+            println!(
+                "  keep    {}  {}  ({})",
+                backup
+                    .created_at()
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M"),
+                backup.target_path().display(),
+                reasons.join(", ")
+            );
+        }
+        for backup in &plan.remove {
+            println!(
+                "  remove  {}  {}  {}",
+                backup
+                    .created_at()
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M"),
+                backup.target_path().display(),
+                format_bytes(backup.total_size_bytes())
+            );
+        }
+
+        if dry_run || plan.remove.is_empty() {
+            continue;
+        }
+
+        let summary = retention::prune(&plan, &mut catalog, &disks);
+        catalog.save(&catalog_path)?;
+        println!(
+            "Removed {} backups, reclaimed {}",
+            summary.removed.len(),
+            format_bytes(summary.reclaimed_bytes)
+        );
+        for backup in summary.unreachable {
+            println!(
+                "  {} is on a disk that is not mounted, left in place",
+                backup.target_path().display()
+            );
+        }
+        for (backup, reason) in &summary.failed {
+            println!(
+                "  {} could not be removed: {}",
+                backup.target_path().display(),
+                reason
+            );
+        }
+        failed += summary.failed.len();
+    }
+
+    if failed > 0 {
+        return Err(CliError::new(
+            Exit::Error,
+            format!("Failed to remove {} backups", failed),
+        ));
+    }
+
+    Ok(Exit::Success)
+}
+
+/// Prints one setting, or all of them that are plain values.
+pub fn config_get(key: Option<&str>) -> CliResult<Exit> {
+    let settings = settings_table()?;
+
+    match key {
+        Some(key) => {
+            let value = settings.get(key).ok_or_else(|| {
+                CliError::new(Exit::NotFound, format!("No setting named {}", key))
+            })?;
+            println!("{}", format_value(value));
+        }
+        None => {
+            for (key, value) in settings
+                .iter()
+                .filter(|(_, value)| !value.is_array() && !value.is_table())
+            {
+                println!("{} = {}", key, format_value(value));
+            }
+        }
+    }
+
+    Ok(Exit::Success)
+}
+
+/// Changes a setting. The value is parsed as the type the setting has, and
+/// the result has to be valid settings before anything is saved.
+pub fn config_set(key: &str, value: &str) -> CliResult<Exit> {
+    let mut settings = settings_table()?;
+    let invalid = |err: &dyn std::fmt::Display| {
+        let err = err.to_string();
+        CliError::new(
+            Exit::Usage,
+            format!(
+                "Invalid value {} for {}: {}",
+                value,
+                key,
+                err.lines().next().unwrap_or_default()
+            ),
+        )
+    };
+
+    let new_value = match settings.get(key) {
+        Some(Value::Boolean(_)) => Value::Boolean(value.parse().map_err(|err| invalid(&err))?),
+        Some(Value::Integer(_)) => Value::Integer(value.parse().map_err(|err| invalid(&err))?),
+        Some(Value::Float(_)) => Value::Float(value.parse().map_err(|err| invalid(&err))?),
+        Some(Value::Array(_) | Value::Table(_)) => {
+            return Err(CliError::new(
+                Exit::Usage,
+                format!("{} can not be set from the command line", key),
+            ));
+        }
+        Some(Value::String(_) | Value::Datetime(_)) | None => Value::String(value.to_string()),
+    };
+    settings.insert(key.to_string(), new_value);
+
+    let settings: Settings = Value::Table(settings)
+        .try_into()
+        .map_err(|err| invalid(&err))?;
+    if !toml::Value::try_from(&settings)?
+        .as_table()
+        .is_some_and(|table| table.contains_key(key))
+    {
+        return Err(CliError::new(
+            Exit::NotFound,
+            format!("No setting named {}", key),
+        ));
+    }
+
+    settings.save(Settings::default_path())?;
+    Ok(Exit::Success)
+}
+
+pub fn systemd(command: SystemdCommand) -> CliResult<Exit> {
+    match command {
+        SystemdCommand::Install { profile } => {
+            let settings = Settings::load(Settings::default_path())?;
+            let profile = find_profile(&settings, &profile)?;
+
+            for path in systemd::install(profile, &env::current_exe()?, systemd::units_dir())? {
+                info!("Wrote {}", path.display());
+                println!("Wrote {}", path.display());
+            }
+            println!(
+                "Enable it with: systemctl --user daemon-reload && systemctl --user enable --now {}.timer",
+                systemd::unit_name(profile.name())
+            );
+        }
+        SystemdCommand::Uninstall { profile } => {
+            let removed = systemd::uninstall(&profile, systemd::units_dir())?;
+            if removed.is_empty() {
+                println!("No units installed for {}", profile);
+            }
+            for path in removed {
+                info!("Removed {}", path.display());
+                println!("Removed {}", path.display());
+            }
+        }
+    }
+
+    Ok(Exit::Success)
+}
+
+fn find_profile<'a>(settings: &'a Settings, name: &str) -> CliResult<&'a BackupProfile> {
+    settings
+        .profile_by_name(name)
+        .ok_or_else(|| CliError::new(Exit::NotFound, format!("No backup profile named {}", name)))
+}
+
+/// `path` made absolute against the working directory. Paths with `..` are
+/// refused.
+pub(crate) fn absolute_path(path: &Path) -> CliResult<PathBuf> {
+    restore::resolve_path(path).map_err(|err| CliError::new(Exit::Usage, err.to_string()))
+}
+
+/// Looks a backup up by its id or a prefix only it has.
+pub(crate) fn find_backup<'a>(catalog: &'a Catalog, id: &str) -> CliResult<&'a BackupMetadata> {
+    let matches: Vec<&BackupMetadata> = catalog
+        .backups()
+        .iter()
+        .filter(|backup| backup.id().to_string().starts_with(id))
+        .collect();
+
+    match matches.as_slice() {
+        [backup] => Ok(backup),
+        [] => Err(CliError::new(
+            Exit::NotFound,
+            format!("No backup with id {}", id),
+        )),
+        _ => Err(CliError::new(
+            Exit::Usage,
+            format!("{} backups have an id starting with {}", matches.len(), id),
+        )),
+    }
+}
+
+fn locate(metadata: &BackupMetadata, disks: &[BackupDisk]) -> CliResult<PathBuf> {
+    metadata
+        .locate(disks)
+        .map_err(|err| CliError::new(Exit::DiskUnavailable, err.to_string()))
+}
+
+/// A copy of `metadata` pointing at where the backup is now.
+fn located(metadata: &BackupMetadata, disks: &[BackupDisk]) -> CliResult<BackupMetadata> {
+    let mut metadata = metadata.clone();
+    metadata.set_target_path(locate(&metadata, disks)?);
+    Ok(metadata)
+}
+
+/// The settings file as a TOML table, with the defaults for anything it
+/// leaves out.
+fn settings_table() -> CliResult<Table> {
+    let settings = Settings::load(Settings::default_path())?;
+    match toml::Value::try_from(&settings)? {
+        Value::Table(table) => Ok(table),
+        _ => Err(CliError::new(
+            Exit::Error,
+            "Settings are not a table".to_string(),
+        )),
+    }
+}
+
+fn status_name(status: &BackupStatus) -> String {
+    match status {
+        BackupStatus::Failed { .. } => "Failed".to_string(),
+        BackupStatus::Running { .. } => "Running".to_string(),
+        status => format!("{:?}", status),
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> CliResult<()> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
@@ -17,6 +17,9 @@ pub trait Config: Serialize + for<'a> Deserialize<'a> + Default {
 
     fn save<P: AsRef<Path>>(&self, path: P) -> SanupResult<()> {
         let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
         let toml_string = toml::to_string(self)?;
         fs::write(path, toml_string)?;
         Ok(())
@@ -0,0 +1,43 @@
+use crate::{
+    daemon::protocol::{Request, Response},
+    error::{SanupError, SanupResult},
+};
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+    time::Duration,
+};
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A connection to a running daemon.
+pub struct DaemonClient {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl DaemonClient {
+    pub fn connect<P: AsRef<Path>>(path: P) -> SanupResult<Self> {
+        let stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+
+        Ok(Self {
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+        })
+    }
+
+    pub fn request(&mut self, request: &Request) -> SanupResult<Response> {
+        writeln!(self.writer, "{}", serde_json::to_string(request)?)?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(SanupError::Other(
+                "The daemon closed the connection".to_string(),
+            ));
+        }
+
+        Ok(serde_json::from_str(&line)?)
+    }
+}
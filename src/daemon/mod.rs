@@ -0,0 +1,94 @@
+pub mod client;
+pub mod protocol;
+pub mod server;
+
+use crate::{
+    app::{engine::Engine, settings::Settings},
+    backup::catalog::Catalog,
+    config::Config,
+    daemon::{
+        protocol::{JobStatus, Request, Response},
+        server::DaemonServer,
+    },
+    error::SanupResult,
+    logger::SanupLogger,
+};
+use log::{info, warn};
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Runs schedules and watched disks without a terminal and answers status
+/// requests on `socket_path` until the process is killed. Watched disks that
+/// ask for confirmation are not run, as there is nobody to ask.
+pub fn run<P: AsRef<Path>>(socket_path: P) -> SanupResult<()> {
+    let settings_path = Settings::default_path();
+    let mut settings = Settings::load(&settings_path)?;
+    let mut settings_modified = modified(&settings_path);
+
+    let mut engine = Engine::new(Catalog::default_path());
+    engine.warn_overdue_disks(&settings);
+
+    let mut server = DaemonServer::bind(socket_path)?;
+    info!("Daemon started");
+
+    loop {
+        let modified_now = modified(&settings_path);
+        if modified_now != settings_modified {
+            settings_modified = modified_now;
+            match Settings::load(&settings_path) {
+                Ok(reloaded) => {
+                    if let Err(err) = SanupLogger::default().configure(&reloaded) {
+                        warn!("Failed to apply the log settings: {}", err);
+                    }
+                    info!("Reloaded settings from {}", settings_path.display());
+                    settings = reloaded;
+                }
+                Err(err) => warn!("Failed to reload settings: {}", err),
+            }
+        }
+
+        for disk in engine.tick(&settings).to_confirm {
+            info!(
+                "Watched disk {} was plugged in, its profile only runs after confirmation in the TUI",
+                disk.label()
+            );
+        }
+
+        server.serve(|request| handle(&engine, request));
+
+        thread::sleep(TICK_RATE);
+    }
+}
+
+fn handle(engine: &Engine, request: Request) -> Response {
+    let id = match request {
+        Request::Status => {
+            return Response::Status(engine.backups().iter().map(JobStatus::from).collect());
+        }
+        Request::Pause(id) | Request::Resume(id) | Request::Cancel(id) => id,
+    };
+    let Some(task) = engine.backup(id) else {
+        return Response::Error(format!("No job with id {}", id));
+    };
+
+    match request {
+        Request::Pause(_) => task.pause(),
+        Request::Resume(_) => task.resume(),
+        Request::Cancel(_) => task.cancel(),
+        Request::Status => {}
+    }
+
+    Response::Ok
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
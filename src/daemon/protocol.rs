@@ -0,0 +1,95 @@
+use crate::backup::{status::BackupStatus, task::BackupTask};
+use nix::unistd::getuid;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+use uuid::Uuid;
+
+/// A request sent to the daemon, one JSON object per line.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Request {
+    Status,
+    Pause(Uuid),
+    Resume(Uuid),
+    Cancel(Uuid),
+}
+
+/// The daemon's answer to a [`Request`], one JSON object per line.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Response {
+    Status(Vec<JobStatus>),
+    Ok,
+    Error(String),
+}
+
+/// A backup running in the daemon, as seen by an attached client.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobStatus {
+    pub id: Uuid,
+    pub name: String,
+    pub profile_id: Option<Uuid>,
+    pub target_path: PathBuf,
+    pub status: BackupStatus,
+    pub file_count: u64,
+    pub total_size_bytes: u64,
+    /// Bytes copied so far out of the size the sources were estimated at.
+    #[serde(default)]
+    pub done_bytes: u64,
+    #[serde(default)]
+    pub total_bytes: u64,
+    /// Time spent running, pauses excluded.
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    #[serde(default)]
+    pub failed_files: Vec<String>,
+}
+
+impl JobStatus {
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.elapsed_ms)
+    }
+
+    /// Bytes per second since the job started.
+    pub fn throughput(&self) -> Option<f64> {
+        let seconds = self.elapsed().as_secs_f64();
+        (seconds > 0.0 && self.done_bytes > 0).then(|| self.done_bytes as f64 / seconds)
+    }
+
+    /// Time left at the throughput so far, while the job runs.
+    pub fn eta(&self) -> Option<Duration> {
+        if !matches!(self.status, BackupStatus::Running { .. }) {
+            return None;
+        }
+
+        let remaining = self.total_bytes.saturating_sub(self.done_bytes) as f64;
+        self.throughput()
+            .map(|throughput| Duration::from_secs_f64(remaining / throughput))
+    }
+}
+
+impl From<&BackupTask> for JobStatus {
+    fn from(task: &BackupTask) -> Self {
+        let metadata = task.metadata();
+
+        Self {
+            id: task.id(),
+            name: metadata.name().to_string(),
+            profile_id: metadata.profile_id(),
+            target_path: metadata.target_path().to_path_buf(),
+            status: task.status().clone(),
+            file_count: metadata.file_count(),
+            total_size_bytes: metadata.total_size_bytes(),
+            done_bytes: task.progress().done_bytes,
+            total_bytes: task.progress().total_bytes,
+            elapsed_ms: task.progress().elapsed().as_millis() as u64,
+            failed_files: task.failed_files().to_vec(),
+        }
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/sanup.sock`, or a per-user socket in `/tmp` when there
+/// is no runtime directory.
+pub fn default_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .map(|dir| dir.join("sanup.sock"))
+        .unwrap_or_else(|| PathBuf::from(format!("/tmp/sanup-{}.sock", getuid())))
+}
@@ -0,0 +1,161 @@
+use crate::{
+    daemon::protocol::{Request, Response},
+    error::{SanupError, SanupResult},
+};
+use log::{debug, info, warn};
+use std::{
+    fs,
+    io::{ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// How long a client may take to accept a response before it is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Longest request line, a client sending more without a newline is dropped.
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Listens on the daemon's Unix socket. The socket file is removed again when
+/// the server is dropped.
+///
+/// Clients may stay connected as long as they like. Their streams are non
+/// blocking and only read from in [`DaemonServer::serve`], so neither a
+/// quiet nor a busy client holds up the daemon's tick loop.
+pub struct DaemonServer {
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<Client>,
+}
+
+/// A connected client and what it sent that is not a full line yet.
+struct Client {
+    stream: UnixStream,
+    pending: Vec<u8>,
+}
+
+impl DaemonServer {
+    /// Binds to `path`, replacing a socket left behind by a daemon that did
+    /// not shut down cleanly. Fails when another daemon still listens on it.
+    pub fn bind<P: AsRef<Path>>(path: P) -> SanupResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(SanupError::Other(format!(
+                    "A daemon is already listening on {}",
+                    path.display()
+                )));
+            }
+            fs::remove_file(&path)?;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        info!("Listening on {}", path.display());
+
+        Ok(Self {
+            listener,
+            path,
+            clients: Vec::new(),
+        })
+    }
+
+    /// Accepts new clients and answers the requests all clients sent since
+    /// the last call, without waiting for more.
+    pub fn serve<F: FnMut(Request) -> Response>(&mut self, mut handle: F) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => match Client::new(stream) {
+                    Ok(client) => self.clients.push(client),
+                    Err(err) => warn!("Failed to set up daemon client: {}", err),
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    warn!("Failed to accept daemon client: {}", err);
+                    break;
+                }
+            }
+        }
+
+        self.clients
+            .retain_mut(|client| match client.serve(&mut handle) {
+                Ok(connected) => connected,
+                Err(err) => {
+                    debug!("Dropping daemon client: {}", err);
+                    false
+                }
+            });
+    }
+}
+
+impl Drop for DaemonServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Client {
+    fn new(stream: UnixStream) -> SanupResult<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+        Ok(Self {
+            stream,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Reads what the client sent so far and answers every complete request.
+    /// Returns `false` once the client hung up.
+    fn serve<F: FnMut(Request) -> Response>(&mut self, handle: &mut F) -> SanupResult<bool> {
+        let mut connected = true;
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    connected = false;
+                    break;
+                }
+                Ok(read) => self.pending.extend_from_slice(&buf[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let response = match serde_json::from_slice(&line) {
+                Ok(request) => handle(request),
+                Err(err) => Response::Error(format!("Invalid request: {}", err)),
+            };
+            self.respond(&response)?;
+        }
+
+        if self.pending.len() > MAX_REQUEST_BYTES {
+            return Err(SanupError::Other(format!(
+                "Request longer than {} bytes",
+                MAX_REQUEST_BYTES
+            )));
+        }
+
+        Ok(connected)
+    }
+
+    /// Writes a response, waiting at most [`WRITE_TIMEOUT`] for the client
+    /// to take it.
+    fn respond(&mut self, response: &Response) -> SanupResult<()> {
+        let mut line = serde_json::to_vec(response)?;
+        line.push(b'\n');
+
+        self.stream.set_nonblocking(false)?;
+        let written = self.stream.write_all(&line);
+        self.stream.set_nonblocking(true)?;
+
+        Ok(written?)
+    }
+}
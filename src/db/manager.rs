@@ -1,6 +1,19 @@
-use mongodb::Client;
+use mongodb::{Client, Database};
 
 pub struct DatabaseManager {
     client: Client,
     db_name: String,
 }
+
+impl DatabaseManager {
+    pub fn new(client: Client, db_name: &str) -> Self {
+        Self {
+            client,
+            db_name: db_name.to_string(),
+        }
+    }
+
+    pub fn database(&self) -> Database {
+        self.client.database(&self.db_name)
+    }
+}
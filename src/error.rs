@@ -12,6 +12,9 @@ pub enum SanupError {
     DeserializeToml(toml::de::Error),
     MongoDB(mongodb::error::Error),
     SetLogger(log::SetLoggerError),
+    Ignore(ignore::Error),
+    Json(serde_json::Error),
+    Zip(zip::result::ZipError),
     Other(String),
 }
 
@@ -64,6 +67,27 @@ impl From<log::SetLoggerError> for SanupError {
     }
 }
 
+impl From<ignore::Error> for SanupError {
+    fn from(value: ignore::Error) -> Self {
+        error!("{}", value);
+        Self::Ignore(value)
+    }
+}
+
+impl From<serde_json::Error> for SanupError {
+    fn from(value: serde_json::Error) -> Self {
+        error!("{}", value);
+        Self::Json(value)
+    }
+}
+
+impl From<zip::result::ZipError> for SanupError {
+    fn from(value: zip::result::ZipError) -> Self {
+        error!("{}", value);
+        Self::Zip(value)
+    }
+}
+
 impl std::error::Error for SanupError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
@@ -74,6 +98,9 @@ impl std::error::Error for SanupError {
             SanupError::DeserializeToml(err) => Some(err),
             SanupError::MongoDB(err) => Some(err),
             SanupError::SetLogger(err) => Some(err),
+            SanupError::Ignore(err) => Some(err),
+            SanupError::Json(err) => Some(err),
+            SanupError::Zip(err) => Some(err),
             SanupError::Other(_) => None,
         }
     }
@@ -92,6 +119,9 @@ impl Display for SanupError {
                 SanupError::DeserializeToml(err) => err.to_string(),
                 SanupError::MongoDB(err) => err.to_string(),
                 SanupError::SetLogger(err) => err.to_string(),
+                SanupError::Ignore(err) => err.to_string(),
+                SanupError::Json(err) => err.to_string(),
+                SanupError::Zip(err) => err.to_string(),
                 SanupError::Other(err) => err.to_string(),
             }
         )
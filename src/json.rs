@@ -0,0 +1,254 @@
+//! What the command line prints with `--json`. These types are the schema
+//! scripts parse, so they are kept apart from the metadata saved on disk:
+//! fields are only ever added, never renamed or removed.
+
+use crate::{
+    app::compression_kind::CompressionKind,
+    backup::{
+        kind::BackupKind,
+        metadata::BackupMetadata,
+        restore::RestoreSummary,
+        status::BackupStatus,
+        verify::{Problem, Verification, VerifyReport},
+    },
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// A backup, as printed by `list`, `show` and in backup events.
+#[derive(Serialize)]
+pub struct BackupJson {
+    pub id: Uuid,
+    pub name: String,
+    /// `full`, `incremental`, `differential`, `mirror` or `compressed`.
+    pub kind: &'static str,
+    /// `zip` for compressed backups, whose files are in one zip archive,
+    /// `null` for the others.
+    pub compression: Option<&'static str>,
+    pub profile_id: Option<Uuid>,
+    /// Backup this one builds on, if any.
+    pub parent_id: Option<Uuid>,
+    /// Backup this synthetic full backup consolidates, if any.
+    pub synthesized_from: Option<Uuid>,
+    pub source_paths: Vec<PathBuf>,
+    pub target_path: PathBuf,
+    pub disk_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+    pub file_count: u64,
+    pub total_size_bytes: u64,
+    pub unchanged_files: u64,
+    pub failed_files: Vec<String>,
+    pub status: StatusJson,
+    pub verification: Option<VerificationJson>,
+    pub note: Option<String>,
+}
+
+impl From<&BackupMetadata> for BackupJson {
+    fn from(metadata: &BackupMetadata) -> Self {
+        Self {
+            id: metadata.id(),
+            name: metadata.name().to_string(),
+            kind: kind_name(metadata.kind()),
+            compression: compression_name(metadata.compression()),
+            profile_id: metadata.profile_id(),
+            parent_id: metadata.parent_id(),
+            synthesized_from: metadata.synthesized_from(),
+            source_paths: metadata.source_paths().to_vec(),
+            target_path: metadata.target_path().to_path_buf(),
+            disk_id: metadata.disk_id(),
+            created_at: metadata.created_at(),
+            started_at: metadata.started_at(),
+            finished_at: metadata.finished_at(),
+            duration_ms: metadata
+                .duration()
+                .map(|duration| duration.num_milliseconds()),
+            file_count: metadata.file_count(),
+            total_size_bytes: metadata.total_size_bytes(),
+            unchanged_files: metadata.unchanged_files(),
+            failed_files: metadata.failed_files().to_vec(),
+            status: metadata.status().into(),
+            verification: metadata.verification().map(VerificationJson::from),
+            note: metadata.note().map(str::to_string),
+        }
+    }
+}
+
+fn kind_name(kind: BackupKind) -> &'static str {
+    match kind {
+        BackupKind::Full => "full",
+        BackupKind::Incremental => "incremental",
+        BackupKind::Differential => "differential",
+        BackupKind::Mirror => "mirror",
+        BackupKind::Compressed => "compressed",
+    }
+}
+
+fn compression_name(compression: &CompressionKind) -> Option<&'static str> {
+    match compression {
+        CompressionKind::None => None,
+        CompressionKind::Zip => Some("zip"),
+    }
+}
+
+/// State of a backup, tagged by `state`.
+#[derive(Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum StatusJson {
+    Pending,
+    Running {
+        /// From 0.0 to 1.0.
+        progress: f32,
+        current_file: String,
+    },
+    Paused,
+    Completed,
+    Failed {
+        reason: String,
+    },
+    Cancelled,
+}
+
+impl From<&BackupStatus> for StatusJson {
+    fn from(status: &BackupStatus) -> Self {
+        match status {
+            BackupStatus::Pending => StatusJson::Pending,
+            BackupStatus::Running {
+                progress,
+                current_file,
+            } => StatusJson::Running {
+                progress: *progress,
+                current_file: current_file.clone(),
+            },
+            BackupStatus::Paused => StatusJson::Paused,
+            BackupStatus::Completed => StatusJson::Completed,
+            BackupStatus::Failed { reason } => StatusJson::Failed {
+                reason: reason.clone(),
+            },
+            BackupStatus::Cancelled => StatusJson::Cancelled,
+        }
+    }
+}
+
+/// Outcome of the last `verify` of a backup.
+#[derive(Serialize)]
+pub struct VerificationJson {
+    pub verified_at: DateTime<Utc>,
+    pub ok: bool,
+    pub checked: u64,
+    pub problems: u64,
+}
+
+impl From<&Verification> for VerificationJson {
+    fn from(verification: &Verification) -> Self {
+        Self {
+            verified_at: verification.verified_at(),
+            ok: verification.is_ok(),
+            checked: verification.checked(),
+            problems: verification.problems(),
+        }
+    }
+}
+
+/// What `verify` printed, as one object.
+#[derive(Serialize)]
+pub struct VerifyJson {
+    pub id: Uuid,
+    pub ok: bool,
+    pub checked: u64,
+    /// Source files not compared with `--source` because they changed.
+    pub changed_sources: u64,
+    pub problems: Vec<ProblemJson>,
+}
+
+impl VerifyJson {
+    pub fn new(id: Uuid, report: &VerifyReport) -> Self {
+        Self {
+            id,
+            ok: report.is_ok(),
+            checked: report.checked,
+            changed_sources: report.changed_sources,
+            problems: report
+                .problems
+                .iter()
+                .map(|(path, problem)| ProblemJson::new(path, problem))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProblemJson {
+    pub path: PathBuf,
+    /// `missing`, `not_a_file`, `size_mismatch`, `content_mismatch` or
+    /// `unreadable`.
+    pub problem: &'static str,
+    pub message: String,
+}
+
+impl ProblemJson {
+    fn new(path: &Path, problem: &Problem) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            problem: match problem {
+                Problem::Missing => "missing",
+                Problem::NotAFile => "not_a_file",
+                Problem::SizeMismatch { .. } => "size_mismatch",
+                Problem::ContentMismatch => "content_mismatch",
+                Problem::Unreadable(_) => "unreadable",
+            },
+            message: problem.to_string(),
+        }
+    }
+}
+
+/// A line of the newline-delimited progress of `backup` and `restore`,
+/// tagged by `event`.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    BackupStarted {
+        backup: BackupJson,
+    },
+    /// The state of a running backup changed.
+    BackupStatus {
+        id: Uuid,
+        status: StatusJson,
+    },
+    BackupFinished {
+        backup: BackupJson,
+    },
+    RestoreStarted {
+        id: Uuid,
+        paths: Vec<PathBuf>,
+        to: Option<PathBuf>,
+    },
+    /// A file was restored, with the totals so far.
+    RestoredFile {
+        id: Uuid,
+        path: PathBuf,
+        file_count: u64,
+        total_size_bytes: u64,
+    },
+    RestoreFinished {
+        id: Uuid,
+        file_count: u64,
+        total_size_bytes: u64,
+        failed_files: Vec<String>,
+    },
+}
+
+impl Event {
+    pub fn restore_finished(id: Uuid, summary: &RestoreSummary) -> Self {
+        Event::RestoreFinished {
+            id,
+            file_count: summary.file_count,
+            total_size_bytes: summary.total_size_bytes,
+            failed_files: summary.failed_files.clone(),
+        }
+    }
+}
@@ -1,8 +1,14 @@
 pub mod app;
 pub mod backup;
+pub mod cli;
 pub mod config;
+pub mod daemon;
 pub mod db;
 pub mod error;
+pub mod json;
 pub mod logger;
-pub mod test;
+pub mod paths;
+pub mod systemd;
+#[cfg(test)]
+mod test;
 pub mod ui;
@@ -1,41 +1,55 @@
-use crate::error::SanupResult;
-use chrono::{Local, Utc};
+use crate::{app::settings::Settings, error::SanupResult, paths};
+use chrono::{DateTime, Local};
 use fern::{
-    Dispatch,
+    Dispatch, Output,
     colors::{Color, ColoredLevelConfig},
 };
-use log::{LevelFilter, Log};
+use log::{Level, LevelFilter, Log};
 use std::{
-    path::Path,
-    sync::{Arc, Mutex},
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Write},
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+    time::{Duration, SystemTime},
 };
+use uuid::Uuid;
+
+/// Name of the current log file. Rotated files are numbered, `sanup.1.log`
+/// being the newest.
+const LOG_FILE: &str = "sanup";
+const SYSLOG_SOCKET: &str = "/dev/log";
+/// Records kept in memory for the log viewer.
+const BUFFER_CAPACITY: usize = 2000;
+
+static LOGGER: OnceLock<Arc<SanupLogger>> = OnceLock::new();
 
 pub struct SanupLogger {
     logger: Mutex<Box<dyn Log>>,
+    buffer: Arc<LogBuffer>,
 }
 
 #[allow(clippy::should_implement_trait)]
 impl SanupLogger {
+    /// The logger of the process, installed on first use with logging off.
     pub fn default() -> Arc<Self> {
-        let logger = Arc::new(SanupLogger {
-            logger: Mutex::new(Box::new(EmptyLogger)),
-        });
+        LOGGER
+            .get_or_init(|| {
+                let logger = Arc::new(SanupLogger {
+                    logger: Mutex::new(Box::new(EmptyLogger)),
+                    buffer: Arc::new(LogBuffer::new(BUFFER_CAPACITY)),
+                });
 
-        if log::set_boxed_logger(Box::new(logger.clone())).is_ok() {};
+                if log::set_boxed_logger(Box::new(logger.clone())).is_ok() {};
 
-        log::set_max_level(LevelFilter::Off);
+                log::set_max_level(LevelFilter::Off);
 
-        logger
-    }
-
-    pub fn is_logger_set() -> Option<Arc<Self>> {
-        let logger = Self::default();
-
-        if log::set_boxed_logger(Box::new(logger.clone())).is_ok() {
-            Some(logger)
-        } else {
-            None
-        }
+                logger
+            })
+            .clone()
     }
 
     fn change(
@@ -48,6 +62,11 @@ impl SanupLogger {
         Ok(())
     }
 
+    /// The latest records, whatever else is logged to.
+    pub fn buffer(&self) -> Arc<LogBuffer> {
+        self.buffer.clone()
+    }
+
     pub fn full_colors() -> ColoredLevelConfig {
         ColoredLevelConfig::new()
             .error(Color::Red)
@@ -57,26 +76,56 @@ impl SanupLogger {
             .trace(Color::Magenta)
     }
 
-    pub fn init<P: AsRef<Path>>(self: Arc<Self>, path: P) -> SanupResult<()> {
-        let mut file_path = path.as_ref().to_path_buf();
-        file_path.push(format!(
-            "log_{}.log",
-            Utc::now().date_naive().format("%Y-%m-%d")
-        ));
-
-        self.change(
+    /// Logs at the level of the settings to a rotated file in the log
+    /// directory and to syslog, as far as the settings ask for them, and at
+    /// least info records to the buffer of the log viewer. Can be called
+    /// again whenever the settings change. A missing syslog socket is only
+    /// warned about, so it never keeps sanup from starting.
+    pub fn configure(self: Arc<Self>, settings: &Settings) -> SanupResult<()> {
+        let level = LevelFilter::from(settings.log_level());
+        let mut outputs = vec![
             Dispatch::new()
-                .format(|out, msg, record| {
-                    out.finish(format_args!(
-                        "[{}] [{}] {}",
-                        Local::now().format("%Y-%m-%d %H:%M:%S"),
-                        SanupLogger::full_colors().color(record.level()),
-                        msg
-                    ));
-                })
-                .chain(fern::log_file(file_path)?)
-                .into_log(),
-        )?;
+                .level(level.max(LevelFilter::Info))
+                .chain(Box::new(self.buffer.clone()) as Box<dyn Log>),
+        ];
+        let mut syslog_error = None;
+
+        if let Some(log_dir) = paths::log_dir(settings) {
+            let file = RotatingFile::open(log_dir, RotationPolicy::from_settings(settings))?;
+            outputs.push(
+                Dispatch::new()
+                    .level(level)
+                    .format(|out, msg, record| {
+                        out.finish(format_args!(
+                            "[{}] [{}] {}",
+                            Local::now().format("%Y-%m-%d %H:%M:%S"),
+                            SanupLogger::full_colors().color(record.level()),
+                            msg
+                        ));
+                    })
+                    .chain(Output::writer(Box::new(file), "\n")),
+            );
+        }
+
+        if settings.log_to_syslog() {
+            match Syslog::connect() {
+                Ok(syslog) => outputs.push(
+                    Dispatch::new()
+                        .level(level)
+                        .chain(Box::new(syslog) as Box<dyn Log>),
+                ),
+                Err(err) => syslog_error = Some(err),
+            }
+        }
+
+        let dispatch = outputs
+            .into_iter()
+            .fold(Dispatch::new(), |dispatch, output| dispatch.chain(output));
+        self.change(dispatch.into_log())?;
+
+        if let Some(err) = syslog_error {
+            log::warn!("Cannot log to {}: {}", SYSLOG_SOCKET, err);
+        }
 
         Ok(())
     }
@@ -112,3 +161,255 @@ impl Log for EmptyLogger {
     fn log(&self, _record: &log::Record) {}
     fn flush(&self) {}
 }
+
+/// A record kept for the log viewer.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// Increases with every record, so viewers can ask for the new ones.
+    pub seq: u64,
+    pub time: DateTime<Local>,
+    pub level: Level,
+    /// The backup a worker thread logged this for, taken from the thread
+    /// name tasks are spawned with, like `backup-<id>` or `delete-<id>`.
+    pub task: Option<Uuid>,
+    pub message: String,
+}
+
+/// The latest records in memory, dropping the oldest past its capacity.
+pub struct LogBuffer {
+    capacity: usize,
+    entries: Mutex<VecDeque<LogEntry>>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Records logged after the one with `seq`, or all of them without it.
+    pub fn since(&self, seq: Option<u64>) -> Vec<LogEntry> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+
+        entries
+            .iter()
+            .filter(|entry| seq.is_none_or(|seq| entry.seq > seq))
+            .cloned()
+            .collect()
+    }
+
+    pub fn push(&self, level: Level, task: Option<Uuid>, message: String) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        let seq = entries.back().map_or(0, |entry| entry.seq + 1);
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(LogEntry {
+            seq,
+            time: Local::now(),
+            level,
+            task,
+            message,
+        });
+    }
+}
+
+impl Log for LogBuffer {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let task = thread::current()
+            .name()
+            .and_then(|name| name.split_once('-'))
+            .and_then(|(_, id)| Uuid::parse_str(id).ok());
+
+        self.push(record.level(), task, record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+/// When the log file is rotated and how many rotated files are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    pub max_size_bytes: u64,
+    /// Never rotated by age when `None`.
+    pub max_age: Option<Duration>,
+    pub max_files: usize,
+}
+
+impl RotationPolicy {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            max_size_bytes: settings.log_max_size_mb().max(1) * 1024 * 1024,
+            max_age: (settings.log_max_age_days() > 0)
+                .then(|| Duration::from_secs(settings.log_max_age_days() * 24 * 60 * 60)),
+            max_files: settings.log_max_files() as usize,
+        }
+    }
+}
+
+/// `sanup.log` in a directory, moved to `sanup.1.log` once it grows past
+/// the size or age of the policy, shifting older files up and dropping the
+/// ones past `max_files`. Rotation is checked on flush, which the logger does
+/// after every record, so records are never split across files.
+pub struct RotatingFile {
+    dir: PathBuf,
+    policy: RotationPolicy,
+    file: File,
+    size: u64,
+    created_at: SystemTime,
+}
+
+impl RotatingFile {
+    pub fn open<P: AsRef<Path>>(dir: P, policy: RotationPolicy) -> SanupResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let (file, size, created_at) = open_log(&dir)?;
+        let mut rotating_file = Self {
+            dir,
+            policy,
+            file,
+            size,
+            created_at,
+        };
+        if rotating_file.is_due() {
+            rotating_file.rotate()?;
+        }
+
+        Ok(rotating_file)
+    }
+
+    pub fn path(&self) -> PathBuf {
+        log_path(&self.dir, 0)
+    }
+
+    fn is_due(&self) -> bool {
+        let too_old = self.policy.max_age.is_some_and(|max_age| {
+            self.created_at
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed > max_age)
+        });
+
+        self.size > 0 && (self.size >= self.policy.max_size_bytes || too_old)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        for idx in (1..=self.policy.max_files).rev() {
+            let from = log_path(&self.dir, idx - 1);
+            if !from.exists() {
+                continue;
+            }
+            if idx == self.policy.max_files {
+                fs::remove_file(log_path(&self.dir, idx)).or_else(ignore_not_found)?;
+            }
+            fs::rename(from, log_path(&self.dir, idx))?;
+        }
+        if self.policy.max_files == 0 {
+            fs::remove_file(self.path()).or_else(ignore_not_found)?;
+        }
+
+        (self.file, self.size, self.created_at) = open_log(&self.dir)?;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.is_due() {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+}
+
+/// `sanup.log`, or `sanup.<idx>.log` for rotated files.
+fn log_path(dir: &Path, idx: usize) -> PathBuf {
+    if idx == 0 {
+        dir.join(format!("{}.log", LOG_FILE))
+    } else {
+        dir.join(format!("{}.{}.log", LOG_FILE, idx))
+    }
+}
+
+fn open_log(dir: &Path) -> io::Result<(File, u64, SystemTime)> {
+    let file = File::options()
+        .create(true)
+        .append(true)
+        .open(log_path(dir, 0))?;
+    let metadata = file.metadata()?;
+    let created_at = metadata.created().unwrap_or_else(|_| SystemTime::now());
+
+    Ok((file, metadata.len(), created_at))
+}
+
+fn ignore_not_found(err: io::Error) -> io::Result<()> {
+    if err.kind() == io::ErrorKind::NotFound {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+/// Sends records to the local syslog socket, which systemd-journald reads
+/// as well.
+struct Syslog {
+    socket: UnixDatagram,
+    pid: u32,
+}
+
+impl Syslog {
+    fn connect() -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(SYSLOG_SOCKET)?;
+
+        Ok(Self {
+            socket,
+            pid: process::id(),
+        })
+    }
+}
+
+impl Log for Syslog {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let severity = match record.level() {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug | Level::Trace => 7,
+        };
+        // Facility 1 is user-level messages.
+        let message = format!("<{}>sanup[{}]: {}", 8 + severity, self.pid, record.args());
+        let _ = self.socket.send(message.as_bytes());
+    }
+
+    fn flush(&self) {}
+}
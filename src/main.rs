@@ -1,13 +1,77 @@
+use clap::Parser;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{Terminal, prelude::CrosstermBackend};
-use sanup::{app::sanup::Sanup, error::SanupResult, logger::SanupLogger, ui::run_app};
-use std::io::stdout;
+use sanup::{
+    app::{sanup::Sanup, settings::Settings},
+    cli::{self, Cli, CliError, CliResult, Command, ConfigCommand, Exit},
+    config::Config,
+    daemon::{self, protocol::default_socket_path},
+    error::SanupResult,
+    logger::SanupLogger,
+    paths,
+    ui::run_app,
+};
+use std::{io::stdout, process::ExitCode};
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    paths::set_overrides(cli.overrides());
+
+    let result = init_logger()
+        .map_err(CliError::from)
+        .and_then(|_| match cli.command {
+            Some(command) => run_command(command),
+            None => run_tui().map(|_| Exit::Success).map_err(CliError::from),
+        });
+
+    match result {
+        Ok(exit) => exit.into(),
+        Err(err) => {
+            eprintln!("sanup: {}", err.message);
+            err.exit.into()
+        }
+    }
+}
 
-fn main() -> SanupResult<()> {
+fn run_command(command: Command) -> CliResult<Exit> {
+    match command {
+        Command::Daemon { socket } => {
+            daemon::run(socket.unwrap_or_else(default_socket_path))?;
+            Ok(Exit::Success)
+        }
+        Command::Backup { profile, json } => cli::backup(&profile, json),
+        Command::Restore {
+            id,
+            paths,
+            to,
+            json,
+        } => cli::restore(&id, &paths, to.as_deref(), json),
+        Command::List { profile, json } => cli::list(profile.as_deref(), json),
+        Command::Show { id, json } => cli::show(&id, json),
+        Command::Verify { id, source, json } => cli::verify(&id, source, json),
+        Command::Prune { profile, dry_run } => cli::prune(profile.as_deref(), dry_run),
+        Command::Config {
+            command: ConfigCommand::Get { key },
+        } => cli::config_get(key.as_deref()),
+        Command::Config {
+            command: ConfigCommand::Set { key, value },
+        } => cli::config_set(&key, &value),
+        Command::Systemd { command } => cli::systemd(command),
+    }
+}
+
+/// Logs to a file only when the settings or an override ask for it, so the
+/// working directory is left alone.
+fn init_logger() -> SanupResult<()> {
+    let settings = Settings::load(Settings::default_path()).unwrap_or_default();
+    SanupLogger::default().configure(&settings)
+}
+
+fn run_tui() -> SanupResult<()> {
     enable_raw_mode()?;
 
     let mut stdout = stdout();
@@ -16,9 +80,6 @@ fn main() -> SanupResult<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let logger = SanupLogger::default();
-    logger.init(".")?;
-
     let app = Sanup::default();
     let res = run_app(&mut terminal, app);
 
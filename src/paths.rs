@@ -0,0 +1,79 @@
+//! Where sanup keeps its files, following the XDG base directory spec:
+//! settings in `$XDG_CONFIG_HOME/sanup`, the catalog in `$XDG_DATA_HOME/sanup`
+//! and logs in `$XDG_STATE_HOME/sanup`. Each can be moved with a command line
+//! flag or environment variable, which the binary hands over once at start.
+
+use crate::app::settings::Settings;
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+const APP_DIR: &str = "sanup";
+const CONFIG_FILE: &str = "config.toml";
+const LOG_DIR: &str = "logs";
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+/// Locations given on the command line or in the environment. They take
+/// precedence over the XDG directories.
+#[derive(Default, Debug, Clone)]
+pub struct Overrides {
+    pub config_file: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub state_dir: Option<PathBuf>,
+    /// Logs go here even when the settings do not ask for a log file.
+    pub log_dir: Option<PathBuf>,
+}
+
+/// Sets the overrides for the rest of the process. Only the first call has
+/// an effect.
+pub fn set_overrides(overrides: Overrides) {
+    let _ = OVERRIDES.set(overrides);
+}
+
+fn overrides() -> Overrides {
+    OVERRIDES.get().cloned().unwrap_or_default()
+}
+
+fn app_dir(base: Option<PathBuf>) -> PathBuf {
+    base.unwrap_or_else(|| PathBuf::from(".")).join(APP_DIR)
+}
+
+/// `$XDG_CONFIG_HOME/sanup/config.toml`
+pub fn config_file() -> PathBuf {
+    overrides()
+        .config_file
+        .unwrap_or_else(|| app_dir(dirs::config_dir()).join(CONFIG_FILE))
+}
+
+/// `$XDG_DATA_HOME/sanup`, home of the catalog.
+pub fn data_dir() -> PathBuf {
+    overrides()
+        .data_dir
+        .unwrap_or_else(|| app_dir(dirs::data_dir()))
+}
+
+/// `$XDG_STATE_HOME/sanup`, for what is worth keeping across runs but not
+/// worth backing up, like logs.
+pub fn state_dir() -> PathBuf {
+    overrides()
+        .state_dir
+        .unwrap_or_else(|| app_dir(dirs::state_dir()))
+}
+
+/// Directory to write logs to, if any: the override, else the one of the
+/// settings when they ask for a log file, falling back to `logs` in the
+/// state directory.
+pub fn log_dir(settings: &Settings) -> Option<PathBuf> {
+    if let Some(log_dir) = overrides().log_dir {
+        return Some(log_dir);
+    }
+
+    settings.log_to_file().then(|| {
+        settings
+            .log_file_dir()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| state_dir().join(LOG_DIR))
+    })
+}
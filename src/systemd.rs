@@ -0,0 +1,151 @@
+use crate::{
+    backup::{frequency::Frequency, profile::BackupProfile, schedule::Schedule},
+    error::{SanupError, SanupResult},
+};
+use chrono::Timelike;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// `~/.config/systemd/user`, where systemd looks for user units.
+pub fn units_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("systemd")
+        .join("user")
+}
+
+/// `sanup-<profile name>`, with everything systemd does not allow in unit
+/// names replaced by `-`.
+pub fn unit_name(profile_name: &str) -> String {
+    let name: String = profile_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    format!("sanup-{}", name)
+}
+
+/// The `OnCalendar` expression of a schedule, `None` for manual profiles.
+pub fn on_calendar(schedule: &Schedule) -> Option<String> {
+    let at = schedule.at();
+
+    match schedule.frequency() {
+        Frequency::Manual => None,
+        Frequency::Hourly => Some(format!("*-*-* *:{:02}:00", at.minute())),
+        Frequency::Daily => Some(format!("*-*-* {:02}:{:02}:00", at.hour(), at.minute())),
+        Frequency::Weekly => Some(format!(
+            "{} *-*-* {:02}:{:02}:00",
+            schedule.weekday(),
+            at.hour(),
+            at.minute()
+        )),
+    }
+}
+
+/// A oneshot service running `sanup backup <profile>` with `exe`.
+pub fn service_unit(profile: &BackupProfile, exe: &Path) -> String {
+    let mut unit = format!(
+        "[Unit]\n\
+         Description=Sanup backup of {}\n",
+        profile.name().replace('%', "%%")
+    );
+    if profile.schedule().only_on_ac_power() {
+        unit.push_str("ConditionACPower=true\n");
+    }
+
+    unit.push_str(&format!(
+        "\n[Service]\n\
+         Type=oneshot\n\
+         ExecStart={} backup {}\n",
+        quote(&exe.to_string_lossy()),
+        quote(profile.name())
+    ));
+
+    unit
+}
+
+/// A timer starting the profile's service on its schedule. The time window
+/// is not carried over, the timer fires at the scheduled time.
+pub fn timer_unit(profile: &BackupProfile) -> SanupResult<String> {
+    let on_calendar = on_calendar(profile.schedule()).ok_or_else(|| {
+        SanupError::Other(format!(
+            "Profile {} has no schedule to build a timer from",
+            profile.name()
+        ))
+    })?;
+
+    Ok(format!(
+        "[Unit]\n\
+         Description=Scheduled sanup backup of {}\n\
+         \n\
+         [Timer]\n\
+         OnCalendar={}\n\
+         Persistent={}\n\
+         Unit={}.service\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n",
+        profile.name().replace('%', "%%"),
+        on_calendar,
+        profile.schedule().catch_up(),
+        unit_name(profile.name())
+    ))
+}
+
+/// Writes the service and timer of a profile into `dir` and returns their
+/// paths.
+pub fn install<P: AsRef<Path>>(
+    profile: &BackupProfile,
+    exe: &Path,
+    dir: P,
+) -> SanupResult<[PathBuf; 2]> {
+    let dir = dir.as_ref();
+    let timer = timer_unit(profile)?;
+    let service = service_unit(profile, exe);
+
+    fs::create_dir_all(dir)?;
+    let service_path = dir.join(format!("{}.service", unit_name(profile.name())));
+    let timer_path = dir.join(format!("{}.timer", unit_name(profile.name())));
+    fs::write(&service_path, service)?;
+    fs::write(&timer_path, timer)?;
+
+    Ok([service_path, timer_path])
+}
+
+/// Removes the service and timer of a profile from `dir` and returns the
+/// paths that existed. Works by name, so units of deleted profiles can still
+/// be removed.
+pub fn uninstall<P: AsRef<Path>>(profile_name: &str, dir: P) -> SanupResult<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let mut removed = Vec::new();
+
+    for extension in ["timer", "service"] {
+        let path = dir.join(format!("{}.{}", unit_name(profile_name), extension));
+        if path.exists() {
+            fs::remove_file(&path)?;
+            removed.push(path);
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Quotes a command line argument for `ExecStart`, escaping what systemd
+/// would otherwise expand.
+fn quote(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+
+    format!("\"{}\"", escaped)
+}
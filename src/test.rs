@@ -1 +1,1991 @@
+use crate::{
+    app::{
+        backup_wizard::{BackupWizard, WizardOutcome},
+        compression_kind::CompressionKind,
+        compression_level::CompressionLevel,
+        hash_algorithm::HashAlgorithm,
+        settings::Settings,
+    },
+    backup::{
+        catalog::Catalog,
+        delete::{self, DeletePlan, DeleteTask},
+        discovery::{DiskDiscovery, parse_mountinfo},
+        disk::{self, BackupDisk},
+        filter::BackupFilter,
+        frequency::Frequency,
+        index::{FileIndex, IndexEntry},
+        kind::BackupKind,
+        layout::{BackupLayout, normalize_sources, relative_to_root},
+        message::WorkerEvent,
+        metadata::BackupMetadata,
+        preflight::{
+            FreeSpacePolicy, SpaceCheck, SpaceVerdict, check_free_space, filesystem_space,
+        },
+        profile::BackupProfile,
+        restore,
+        retention::{self, KeepReason, Retention},
+        rotation,
+        rules::{SelectionRules, SkipReason, SkippedCounts},
+        scan::SizeScan,
+        schedule::{Schedule, TimeWindow, parse_time},
+        status::BackupStatus,
+        synthetic::{SyntheticPlan, SyntheticTask},
+        task::BackupTask,
+        verify,
+        worker::BackupWorker,
+    },
+    cli::{self, Cli, CliError, Command, Exit},
+    config::Config,
+    daemon::{
+        protocol::{Request, Response},
+        server::DaemonServer,
+    },
+    error::SanupError,
+    json::BackupJson,
+    logger::{LogBuffer, LogEntry, RotatingFile, RotationPolicy},
+    systemd,
+    ui::{
+        backupview::BackupView,
+        input::{
+            enumvariants::Choice,
+            field::Fields,
+            inputform::InputForm,
+            value::{Value, Values},
+        },
+    },
+};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use clap::Parser;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::{MetadataExt, symlink},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::SystemTime,
+};
+use uuid::Uuid;
 
+const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+25 22 0:22 / /proc rw,nosuid shared:12 - proc proc rw
+41 22 259:1 / /boot rw,relatime shared:29 - vfat /dev/nvme0n1p1 rw
+97 22 8:1 / /run/media/user/My\\040Disk rw,nosuid,nodev shared:51 - exfat /dev/sdb1 rw
+";
+
+#[test]
+fn mountinfo_is_parsed() {
+    let mounts = parse_mountinfo(MOUNTINFO);
+
+    assert_eq!(mounts.len(), 4);
+    assert_eq!(mounts[0].device_number, "259:2");
+    assert_eq!(mounts[0].mount_path, PathBuf::from("/"));
+    assert_eq!(mounts[0].fs_type, "ext4");
+    assert_eq!(mounts[0].source, "/dev/nvme0n1p2");
+    assert_eq!(mounts[1].fs_type, "proc");
+}
+
+#[test]
+fn mountinfo_escapes_are_decoded() {
+    let mounts = parse_mountinfo(MOUNTINFO);
+
+    assert_eq!(
+        mounts[3].mount_path,
+        PathBuf::from("/run/media/user/My Disk")
+    );
+}
+
+#[test]
+fn mountinfo_skips_malformed_lines() {
+    assert!(parse_mountinfo("garbage\n\n22 1 259:2\n").is_empty());
+}
+
+/// Lays out a `/sys/block` and `/dev/disk` tree with an internal NVMe disk,
+/// a USB stick with a mounted partition and an SD card that is not mounted.
+fn discovery_fixture(name: &str) -> DiskDiscovery {
+    let root = std::env::temp_dir().join(format!("sanup-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+
+    let sys_block = root.join("sys/block");
+    let devices = [
+        ("nvme0n1", None, "259:0", "0", "1000215216"),
+        ("nvme0n1", Some("nvme0n1p1"), "259:1", "", "1048576"),
+        ("nvme0n1", Some("nvme0n1p2"), "259:2", "", "999164591"),
+        ("sdb", None, "8:0", "1", "60063744"),
+        ("sdb", Some("sdb1"), "8:1", "", "60061696"),
+        ("mmcblk0", None, "179:0", "1", "31116288"),
+        ("mmcblk0", Some("mmcblk0p1"), "179:1", "", "31114240"),
+    ];
+    for (disk, partition, dev, removable, size) in devices {
+        let dir = match partition {
+            Some(partition) => {
+                let dir = sys_block.join(disk).join(partition);
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("partition"), "1\n").unwrap();
+                dir
+            }
+            None => {
+                let dir = sys_block.join(disk);
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("removable"), format!("{}\n", removable)).unwrap();
+                dir
+            }
+        };
+        fs::write(dir.join("dev"), format!("{}\n", dev)).unwrap();
+        fs::write(dir.join("size"), format!("{}\n", size)).unwrap();
+    }
+
+    let dev_disk = root.join("dev/disk");
+    let links = [
+        ("by-uuid", "0f3a-1c2b", "nvme0n1p1"),
+        (
+            "by-uuid",
+            "5e1c0b7a-8d44-4d0e-9a63-0c1f6b2e77aa",
+            "nvme0n1p2",
+        ),
+        ("by-uuid", "6A1B-2C3D", "sdb1"),
+        ("by-uuid", "1234-ABCD", "mmcblk0p1"),
+        ("by-label", "My\\x20Disk", "sdb1"),
+        ("by-label", "CAMERA", "mmcblk0p1"),
+    ];
+    for (kind, name, device) in links {
+        let dir = dev_disk.join(kind);
+        fs::create_dir_all(&dir).unwrap();
+        symlink(format!("../../{}", device), dir.join(name)).unwrap();
+    }
+
+    let mountinfo = root.join("mountinfo");
+    fs::write(&mountinfo, MOUNTINFO).unwrap();
+
+    DiskDiscovery::new(mountinfo, sys_block, dev_disk)
+}
+
+#[test]
+fn block_devices_are_read_from_sysfs() {
+    let discovery = discovery_fixture("block-devices");
+    let devices = discovery.block_devices().unwrap();
+
+    let names: Vec<&str> = devices.iter().map(|device| device.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "mmcblk0",
+            "mmcblk0p1",
+            "nvme0n1",
+            "nvme0n1p1",
+            "nvme0n1p2",
+            "sdb",
+            "sdb1"
+        ]
+    );
+
+    let sdb1 = devices.iter().find(|device| device.name == "sdb1").unwrap();
+    assert_eq!(sdb1.device_number, "8:1");
+    assert_eq!(sdb1.size_bytes, 60061696 * 512);
+    assert!(sdb1.removable);
+    assert_eq!(sdb1.uuid.as_deref(), Some("6A1B-2C3D"));
+    assert_eq!(sdb1.label.as_deref(), Some("My Disk"));
+
+    let nvme = devices
+        .iter()
+        .find(|device| device.name == "nvme0n1p2")
+        .unwrap();
+    assert!(!nvme.removable);
+    assert_eq!(nvme.label, None);
+}
+
+#[test]
+fn mounted_block_devices_become_disks() {
+    let discovery = discovery_fixture("disks");
+    let disks = discovery.disks().unwrap();
+
+    let mounts: Vec<PathBuf> = disks
+        .iter()
+        .map(|disk| disk.mount_path().to_path_buf())
+        .collect();
+    assert_eq!(
+        mounts,
+        [
+            PathBuf::from("/"),
+            PathBuf::from("/boot"),
+            PathBuf::from("/run/media/user/My Disk"),
+        ]
+    );
+
+    let usb = &disks[2];
+    assert_eq!(usb.label(), "My Disk");
+    assert_eq!(usb.device(), "/dev/sdb1");
+    assert_eq!(usb.fs_type(), "exfat");
+    assert_eq!(usb.fs_uuid(), Some("6A1B-2C3D"));
+    assert!(usb.removable());
+
+    assert_eq!(disks[1].label(), "boot");
+    assert!(!disks[1].removable());
+}
+
+#[test]
+fn unmounted_removable_devices_are_listed() {
+    let discovery = discovery_fixture("unmounted");
+    let devices = discovery.unmounted_removable().unwrap();
+
+    let names: Vec<&str> = devices.iter().map(|device| device.name.as_str()).collect();
+    assert_eq!(names, ["mmcblk0", "mmcblk0p1", "sdb"]);
+    assert_eq!(devices[1].label.as_deref(), Some("CAMERA"));
+}
+
+fn local(year: i32, month: u32, day: u32, time: &str) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .unwrap()
+        .and_time(parse_time(time).unwrap())
+}
+
+#[test]
+fn schedule_fields_fall_back_to_their_defaults() {
+    let schedule: Schedule = toml::from_str("frequency = \"Daily\"").unwrap();
+    assert_eq!(schedule.frequency(), Frequency::Daily);
+    assert_eq!(schedule.at(), parse_time("00:00").unwrap());
+    assert_eq!(schedule.weekday(), Weekday::Sun);
+    assert_eq!(schedule.window(), None);
+    assert!(!schedule.only_on_ac_power());
+    assert!(schedule.catch_up());
+
+    let schedule: Schedule = toml::from_str("").unwrap();
+    assert_eq!(schedule, Schedule::default());
+}
+
+#[test]
+fn previous_slots_cross_day_month_and_year_ends() {
+    let daily = Schedule::new(Frequency::Daily, parse_time("23:30").unwrap());
+    assert_eq!(
+        daily.previous_slot(local(2027, 3, 1, "00:10")),
+        Some(local(2027, 2, 28, "23:30"))
+    );
+    assert_eq!(
+        daily.previous_slot(local(2028, 3, 1, "00:10")),
+        Some(local(2028, 2, 29, "23:30"))
+    );
+    assert_eq!(
+        daily.previous_slot(local(2026, 10, 31, "23:30")),
+        Some(local(2026, 10, 31, "23:30"))
+    );
+
+    let hourly = Schedule::new(Frequency::Hourly, parse_time("07:15").unwrap());
+    assert_eq!(
+        hourly.previous_slot(local(2027, 1, 1, "00:05")),
+        Some(local(2026, 12, 31, "23:15"))
+    );
+    assert_eq!(
+        hourly.previous_slot(local(2027, 1, 1, "10:20")),
+        Some(local(2027, 1, 1, "10:15"))
+    );
+
+    let mut weekly = Schedule::new(Frequency::Weekly, parse_time("21:05").unwrap());
+    weekly.set_weekday(Weekday::Fri);
+    // Jan 1 2027 is a Friday, before its run.
+    assert_eq!(
+        weekly.previous_slot(local(2027, 1, 1, "10:00")),
+        Some(local(2026, 12, 25, "21:05"))
+    );
+    assert_eq!(
+        weekly.previous_slot(local(2027, 1, 3, "10:00")),
+        Some(local(2027, 1, 1, "21:05"))
+    );
+
+    assert_eq!(
+        Schedule::default().previous_slot(local(2027, 1, 1, "10:00")),
+        None
+    );
+}
+
+#[test]
+fn runs_are_due_once_per_slot() {
+    let mut schedule = Schedule::new(Frequency::Daily, parse_time("02:30").unwrap());
+    let now = local(2026, 10, 20, "09:00");
+    assert!(schedule.is_due(local(2026, 10, 19, "02:30"), now));
+    assert!(!schedule.is_due(local(2026, 10, 20, "02:30"), now));
+    assert!(!Schedule::default().is_due(local(2026, 1, 1, "00:00"), now));
+
+    // Without catching up only a run that is a few minutes late starts.
+    schedule.set_catch_up(false);
+    let last_run = local(2026, 10, 19, "02:30");
+    assert!(!schedule.is_due(last_run, now));
+    assert!(schedule.is_due(last_run, local(2026, 10, 20, "02:33")));
+
+    // Outside the window the run waits for it to open.
+    schedule.set_catch_up(true);
+    schedule.set_window(TimeWindow::parse("22:00-06:00"));
+    assert!(!schedule.is_due(last_run, now));
+    assert!(schedule.is_due(last_run, local(2026, 10, 20, "23:00")));
+}
+
+#[test]
+fn runs_follow_the_wall_clock_across_dst_changes() {
+    let schedule = Schedule::new(Frequency::Daily, parse_time("02:30").unwrap());
+
+    // Summer time starts: the clock jumps from 02:00 to 03:00, skipping the
+    // slot, and the run starts once the clock is past it.
+    let last_run = local(2027, 3, 27, "02:30");
+    assert!(!schedule.is_due(last_run, local(2027, 3, 28, "01:59")));
+    assert!(schedule.is_due(last_run, local(2027, 3, 28, "03:00")));
+
+    // Summer time ends: the clock goes back from 03:00 to 02:00, passing
+    // the slot twice, and only the first pass runs.
+    let last_run = local(2026, 10, 25, "02:30");
+    assert!(!schedule.is_due(last_run, local(2026, 10, 25, "02:10")));
+    assert!(!schedule.is_due(last_run, local(2026, 10, 25, "02:30")));
+    assert!(!schedule.is_due(last_run, local(2026, 10, 25, "02:45")));
+}
+
+fn scheduled_profile(name: &str, schedule: Schedule) -> BackupProfile {
+    let mut profile = BackupProfile::new(
+        name,
+        BackupKind::Incremental,
+        vec![PathBuf::from("/home/user/Documents")],
+        PathBuf::from("/mnt/backup"),
+    );
+    profile.set_schedule(schedule);
+    profile
+}
+
+#[test]
+fn systemd_unit_names_are_sanitized() {
+    assert_eq!(systemd::unit_name("Home Documents"), "sanup-home-documents");
+    assert_eq!(systemd::unit_name("photos_2024"), "sanup-photos_2024");
+}
+
+#[test]
+fn systemd_service_snapshot() {
+    let mut schedule = Schedule::new(Frequency::Daily, parse_time("02:30").unwrap());
+    schedule.set_only_on_ac_power(true);
+    let profile = scheduled_profile("Home Documents", schedule);
+
+    assert_eq!(
+        systemd::service_unit(&profile, Path::new("/usr/bin/sanup")),
+        "\
+[Unit]
+Description=Sanup backup of Home Documents
+ConditionACPower=true
+
+[Service]
+Type=oneshot
+ExecStart=\"/usr/bin/sanup\" backup \"Home Documents\"
+"
+    );
+}
+
+#[test]
+fn systemd_service_escapes_arguments() {
+    let profile = scheduled_profile("50% \"off\" $HOME", Schedule::default());
+
+    assert_eq!(
+        systemd::service_unit(&profile, Path::new("/opt/my tools/sanup")),
+        "\
+[Unit]
+Description=Sanup backup of 50%% \"off\" $HOME
+
+[Service]
+Type=oneshot
+ExecStart=\"/opt/my tools/sanup\" backup \"50%% \\\"off\\\" $$HOME\"
+"
+    );
+}
+
+#[test]
+fn systemd_daily_timer_snapshot() {
+    let profile = scheduled_profile(
+        "Home Documents",
+        Schedule::new(Frequency::Daily, parse_time("02:30").unwrap()),
+    );
+
+    assert_eq!(
+        systemd::timer_unit(&profile).unwrap(),
+        "\
+[Unit]
+Description=Scheduled sanup backup of Home Documents
+
+[Timer]
+OnCalendar=*-*-* 02:30:00
+Persistent=true
+Unit=sanup-home-documents.service
+
+[Install]
+WantedBy=timers.target
+"
+    );
+}
+
+#[test]
+fn systemd_weekly_timer_snapshot() {
+    let mut schedule = Schedule::new(Frequency::Weekly, parse_time("21:05").unwrap());
+    schedule.set_weekday(Weekday::Fri);
+    schedule.set_catch_up(false);
+    let profile = scheduled_profile("photos", schedule);
+
+    assert_eq!(
+        systemd::timer_unit(&profile).unwrap(),
+        "\
+[Unit]
+Description=Scheduled sanup backup of photos
+
+[Timer]
+OnCalendar=Fri *-*-* 21:05:00
+Persistent=false
+Unit=sanup-photos.service
+
+[Install]
+WantedBy=timers.target
+"
+    );
+}
+
+#[test]
+fn systemd_hourly_timer_uses_the_minute() {
+    let schedule = Schedule::new(Frequency::Hourly, parse_time("07:15").unwrap());
+
+    assert_eq!(
+        systemd::on_calendar(&schedule).as_deref(),
+        Some("*-*-* *:15:00")
+    );
+}
+
+#[test]
+fn systemd_manual_profiles_have_no_timer() {
+    let profile = scheduled_profile("manual", Schedule::default());
+
+    assert!(systemd::timer_unit(&profile).is_err());
+}
+
+#[test]
+fn systemd_units_are_installed_and_uninstalled() {
+    let dir = std::env::temp_dir().join(format!("sanup-systemd-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let profile = scheduled_profile(
+        "Home Documents",
+        Schedule::new(Frequency::Daily, parse_time("02:30").unwrap()),
+    );
+
+    let [service, timer] = systemd::install(&profile, Path::new("/usr/bin/sanup"), &dir).unwrap();
+    assert_eq!(service, dir.join("sanup-home-documents.service"));
+    assert_eq!(timer, dir.join("sanup-home-documents.timer"));
+    assert!(service.exists() && timer.exists());
+
+    let removed = systemd::uninstall(profile.name(), &dir).unwrap();
+    assert_eq!(removed, [timer.clone(), service.clone()]);
+    assert!(!service.exists() && !timer.exists());
+    assert!(systemd::uninstall(profile.name(), &dir).unwrap().is_empty());
+}
+
+fn edited_settings() -> Settings {
+    let mut table = toml::Table::try_from(Settings::default()).unwrap();
+    table.insert("default_backup_dir".into(), "/mnt/backups".into());
+    table.insert("compression_enable".into(), true.into());
+    table.insert("compression_kind".into(), "Zip".into());
+    table.insert("compression_level".into(), "Best".into());
+    table.insert("verify_after_backup".into(), false.into());
+    table.insert("min_free_space_gb".into(), 3.into());
+    table.insert("theme".into(), "Nord".into());
+    table.insert("log_level".into(), "Debug".into());
+    table.insert("log_file_dir".into(), "/var/log/sanup".into());
+    table.try_into().unwrap()
+}
+
+#[test]
+fn settings_round_trip_through_fields() {
+    let edited = edited_settings();
+    let values = Fields::from(edited.clone()).values();
+
+    let mut settings = Settings::default();
+    settings.update_from(&values).unwrap();
+
+    assert_eq!(
+        toml::to_string(&settings).unwrap(),
+        toml::to_string(&edited).unwrap()
+    );
+}
+
+#[test]
+fn invalid_settings_values_change_nothing() {
+    let mut values: Vec<Value> = Fields::from(edited_settings())
+        .values()
+        .into_iter()
+        .collect();
+    for value in &mut values {
+        if value.title() == "min_free_space_gb" {
+            *value = Value::Integer("min_free_space_gb".into(), -1);
+        }
+    }
+
+    let mut settings = Settings::default();
+    assert!(settings.update_from(&Values::new(values)).is_err());
+    assert_eq!(
+        toml::to_string(&settings).unwrap(),
+        toml::to_string(&Settings::default()).unwrap()
+    );
+}
+
+#[test]
+fn new_profile_names_are_unique_and_path_safe() {
+    let mut settings = Settings::default();
+    settings.add_profile(scheduled_profile("Home Documents", Schedule::default()));
+
+    assert!(settings.check_new_profile_name("Photos").is_ok());
+    assert!(settings.check_new_profile_name("  ").is_err());
+    assert!(settings.check_new_profile_name("home/documents").is_err());
+    assert!(settings.check_new_profile_name("Home Documents").is_err());
+    assert!(settings.check_new_profile_name("home documents ").is_err());
+}
+
+#[test]
+fn log_files_rotate_by_size() {
+    let dir = std::env::temp_dir().join(format!("sanup-logs-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let policy = RotationPolicy {
+        max_size_bytes: 64,
+        max_age: None,
+        max_files: 2,
+    };
+
+    let mut file = RotatingFile::open(&dir, policy).unwrap();
+    for idx in 0..10 {
+        writeln!(file, "record {} of the rotation test", idx).unwrap();
+        file.flush().unwrap();
+    }
+
+    let first_line = |name: &str| {
+        fs::read_to_string(dir.join(name))
+            .unwrap()
+            .lines()
+            .next()
+            .map(str::to_string)
+    };
+    assert_eq!(
+        first_line("sanup.1.log").unwrap(),
+        "record 6 of the rotation test"
+    );
+    assert_eq!(
+        first_line("sanup.2.log").unwrap(),
+        "record 3 of the rotation test"
+    );
+    assert_eq!(
+        first_line("sanup.log").unwrap(),
+        "record 9 of the rotation test"
+    );
+    assert!(!dir.join("sanup.3.log").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn log_buffer_keeps_the_latest_records() {
+    let buffer = LogBuffer::new(3);
+    for idx in 0..5 {
+        buffer.push(log::Level::Info, None, format!("record {}", idx));
+    }
+
+    let messages = |entries: Vec<LogEntry>| {
+        entries
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        messages(buffer.since(None)),
+        ["record 2", "record 3", "record 4"]
+    );
+    assert_eq!(messages(buffer.since(Some(3))), ["record 4"]);
+}
+
+#[test]
+fn backup_view_survives_a_shorter_catalog() {
+    let mut catalog = Catalog::default();
+    for name in ["first", "second", "third"] {
+        catalog.record(BackupMetadata::new(
+            name,
+            BackupKind::Full,
+            Vec::new(),
+            "/backups",
+        ));
+    }
+    let newest = catalog.backups()[2].id();
+
+    let mut view = BackupView::new(&catalog);
+    for _ in 0..catalog.backups().len() {
+        if view.selected().map(|backup| backup.id()) == Some(newest) {
+            break;
+        }
+        view.on_key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+    }
+    assert_eq!(view.selected().map(|backup| backup.id()), Some(newest));
+
+    catalog.remove(newest);
+    view.refresh(&catalog);
+    let selected = view.selected().map(|backup| backup.id()).unwrap();
+    assert!(catalog.get(selected).is_some());
+
+    catalog.remove(selected);
+    catalog.remove(catalog.backups()[0].id());
+    view.refresh(&catalog);
+    assert!(view.selected().is_none());
+}
+
+/// The values of a wizard step with some of them replaced.
+fn wizard_values(form: &InputForm, mut replaced: Vec<Value>) -> Values {
+    form.values()
+        .into_iter()
+        .map(|value| {
+            match replaced
+                .iter()
+                .position(|replacement| replacement.title() == value.title())
+            {
+                Some(idx) => replaced.remove(idx),
+                None => value,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn wizard_starts_on_the_chosen_disk_without_toggling_confirm() {
+    let dir = std::env::temp_dir().join(format!("sanup-wizard-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("source")).unwrap();
+    let disks = [
+        BackupDisk::new("Backup", dir.join("a")),
+        BackupDisk::new("Backup", dir.join("b")),
+    ];
+
+    let mut wizard = BackupWizard::new(&Settings::default(), &disks, &[dir.join("source")]);
+    let next = |wizard: &mut BackupWizard, replaced: Vec<Value>| match wizard
+        .submit(&wizard_values(&wizard.form(), replaced))
+    {
+        WizardOutcome::Step(form) => form,
+        _ => panic!("the wizard left early"),
+    };
+
+    next(
+        &mut wizard,
+        vec![Value::String("name".into(), "Docs".into())],
+    );
+    let choice = format!("Backup ({})", &disks[1].id().to_string()[..8]);
+    next(
+        &mut wizard,
+        vec![
+            Value::Enum(
+                "disk".into(),
+                Box::new(Choice::new(vec![choice.clone()], &choice)),
+            ),
+            Value::String("target".into(), "sanup".into()),
+        ],
+    );
+    let summary = next(&mut wizard, Vec::new());
+    assert_eq!(summary.values().len(), 1);
+
+    match wizard.submit(&summary.values()) {
+        WizardOutcome::Start { metadata, disk } => {
+            assert_eq!(disk.map(|disk| disk.id()), Some(disks[1].id()));
+            assert!(metadata.target_path().starts_with(dir.join("b/sanup")));
+        }
+        _ => panic!("the summary was not confirmed"),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Wednesday, so the current week started two days earlier.
+fn prune_now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 21, 12, 0, 0).unwrap()
+}
+
+/// A backup of `profile_id` created `days_ago` days before `prune_now`.
+fn backup_of(profile_id: Uuid, days_ago: i64, parent: Option<&BackupMetadata>) -> BackupMetadata {
+    let kind = if parent.is_some() {
+        BackupKind::Incremental
+    } else {
+        BackupKind::Full
+    };
+    let mut backup = BackupMetadata::new("Docs", kind, Vec::new(), "/backups");
+    backup.set_profile_id(Some(profile_id));
+    backup.set_parent_id(parent.map(|parent| parent.id()));
+    backup.set_status(BackupStatus::Completed);
+
+    let mut table = toml::Table::try_from(&backup).unwrap();
+    let created_at = prune_now() - Duration::days(days_ago) - Duration::hours(2);
+    table.insert("created_at".into(), created_at.to_rfc3339().into());
+    table.try_into().unwrap()
+}
+
+fn kept_days(plan: &retention::PrunePlan) -> HashMap<i64, Vec<KeepReason>> {
+    plan.keep
+        .iter()
+        .map(|(backup, reasons)| {
+            let days_ago = (prune_now() - backup.created_at()).num_days();
+            (days_ago, reasons.clone())
+        })
+        .collect()
+}
+
+#[test]
+fn prune_keeps_the_newest_backup_of_each_period() {
+    let profile_id = Uuid::new_v4();
+    let mut catalog = Catalog::default();
+    for days_ago in 0..70 {
+        catalog.record(backup_of(profile_id, days_ago, None));
+    }
+
+    let retention = Retention::new(1, 3, 2, 2, Some(0));
+    let plan = retention::plan(&catalog, profile_id, &retention, prune_now());
+    let kept = kept_days(&plan);
+
+    // Oct 21, 20 and 19 are the last days, Oct 18 ends the previous week
+    // and Sep 30 the previous month.
+    let mut days: Vec<i64> = kept.keys().copied().collect();
+    days.sort();
+    assert_eq!(days, [0, 1, 2, 3, 21]);
+    assert_eq!(
+        kept[&0],
+        [
+            KeepReason::Last,
+            KeepReason::Daily,
+            KeepReason::Weekly,
+            KeepReason::Monthly
+        ]
+    );
+    assert_eq!(kept[&2], [KeepReason::Daily]);
+    assert_eq!(kept[&3], [KeepReason::Weekly]);
+    assert_eq!(kept[&21], [KeepReason::Monthly]);
+    assert_eq!(plan.remove.len(), 65);
+}
+
+#[test]
+fn prune_keeps_the_last_backups() {
+    let profile_id = Uuid::new_v4();
+    let mut catalog = Catalog::default();
+    for days_ago in 0..10 {
+        catalog.record(backup_of(profile_id, days_ago, None));
+    }
+    catalog.record(backup_of(Uuid::new_v4(), 20, None));
+
+    let plan = retention::plan(
+        &catalog,
+        profile_id,
+        &Retention::new(4, 0, 0, 0, Some(0)),
+        prune_now(),
+    );
+    let mut days: Vec<i64> = kept_days(&plan).keys().copied().collect();
+    days.sort();
+    assert_eq!(days, [0, 1, 2, 3]);
+    assert_eq!(plan.remove.len(), 6);
+
+    // The newest backup survives even when nothing is to be kept.
+    let plan = retention::plan(
+        &catalog,
+        profile_id,
+        &Retention::new(0, 0, 0, 0, Some(0)),
+        prune_now(),
+    );
+    assert_eq!(kept_days(&plan).keys().copied().collect::<Vec<_>>(), [0]);
+}
+
+#[test]
+fn prune_keeps_what_kept_backups_build_on() {
+    let profile_id = Uuid::new_v4();
+    let mut catalog = Catalog::default();
+    let mut parent = backup_of(profile_id, 10, None);
+    catalog.record(parent.clone());
+    for days_ago in (0..10).rev() {
+        let backup = backup_of(profile_id, days_ago, Some(&parent));
+        catalog.record(backup.clone());
+        parent = backup;
+    }
+    let mut failed = backup_of(profile_id, 0, Some(&parent));
+    failed.set_status(BackupStatus::Failed {
+        reason: "disk full".to_string(),
+    });
+    catalog.record(failed.clone());
+
+    let plan = retention::plan(
+        &catalog,
+        profile_id,
+        &Retention::new(1, 0, 0, 0, Some(0)),
+        prune_now(),
+    );
+    let kept = kept_days(&plan);
+
+    assert_eq!(kept.len(), 11);
+    assert_eq!(kept[&0], [KeepReason::Last]);
+    assert!((1..=10).all(|days_ago| kept[&days_ago] == [KeepReason::Dependency]));
+    assert_eq!(
+        plan.remove
+            .iter()
+            .map(|backup| backup.id())
+            .collect::<Vec<_>>(),
+        [failed.id()]
+    );
+}
+
+#[test]
+fn prune_goes_on_after_a_failed_removal() {
+    let dir = std::env::temp_dir().join(format!("sanup-prune-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("old")).unwrap();
+    fs::create_dir_all(dir.join("older")).unwrap();
+    // Not a directory, so removing it as a backup fails.
+    fs::write(dir.join("broken"), "not a backup").unwrap();
+
+    let profile_id = Uuid::new_v4();
+    let mut catalog = Catalog::default();
+    for (days_ago, name) in [(0, "new"), (1, "old"), (2, "broken"), (3, "older")] {
+        let mut backup = backup_of(profile_id, days_ago, None);
+        backup.set_target_path(dir.join(name));
+        catalog.record(backup);
+    }
+
+    let plan = retention::plan(
+        &catalog,
+        profile_id,
+        &Retention::new(1, 0, 0, 0, Some(0)),
+        prune_now(),
+    );
+    let summary = retention::prune(&plan, &mut catalog, &[]);
+
+    assert_eq!(summary.removed.len(), 2);
+    assert_eq!(summary.failed.len(), 1);
+    assert!(!dir.join("old").exists() && !dir.join("older").exists());
+    let left: Vec<PathBuf> = catalog
+        .backups()
+        .iter()
+        .map(|backup| backup.target_path().to_path_buf())
+        .collect();
+    assert_eq!(left, [dir.join("new"), dir.join("broken")]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Writes a backup to `root`: its manifest, its index and the data of the
+/// files stored in it. Files are `(path, content, stored_in)`.
+fn stored_backup(
+    root: &Path,
+    name: &str,
+    parent: Option<&BackupMetadata>,
+    files: &[(&str, &str, Option<Uuid>)],
+) -> BackupMetadata {
+    let kind = if parent.is_some() {
+        BackupKind::Incremental
+    } else {
+        BackupKind::Full
+    };
+    let mut metadata = BackupMetadata::new(name, kind, vec![PathBuf::from("/src")], root);
+    metadata.set_parent_id(parent.map(|parent| parent.id()));
+    metadata.set_status(BackupStatus::Completed);
+
+    let layout = BackupLayout::new(root);
+    let mut index = FileIndex::default();
+    for (path, content, stored_in) in files {
+        if stored_in.is_none() {
+            let stored = layout.stored_path(path);
+            fs::create_dir_all(stored.parent().unwrap()).unwrap();
+            fs::write(stored, content).unwrap();
+        }
+        index.insert(
+            PathBuf::from(path),
+            IndexEntry {
+                size: content.len() as u64,
+                mtime: 0,
+                mtime_nsec: 0,
+                stored_in: *stored_in,
+            },
+        );
+    }
+    index.save(layout.index_path()).unwrap();
+    metadata.save(layout.manifest_path()).unwrap();
+
+    metadata
+}
+
+fn fixture_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sanup-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn deleting_a_parent_merges_it_into_its_chain() {
+    let dir = fixture_dir("delete-merge");
+    let full = stored_backup(
+        &dir.join("full"),
+        "full",
+        None,
+        &[("/src/a", "A", None), ("/src/b", "B", None)],
+    );
+    let first = stored_backup(
+        &dir.join("first"),
+        "first",
+        Some(&full),
+        &[("/src/a", "A", Some(full.id())), ("/src/b", "B2", None)],
+    );
+    let second = stored_backup(
+        &dir.join("second"),
+        "second",
+        Some(&first),
+        &[
+            ("/src/a", "A", Some(full.id())),
+            ("/src/b", "B2", Some(first.id())),
+        ],
+    );
+    let mut failed = stored_backup(&dir.join("failed"), "failed", Some(&full), &[]);
+    failed.set_status(BackupStatus::Cancelled);
+
+    let mut catalog = Catalog::default();
+    for backup in [&full, &first, &second, &failed] {
+        catalog.record(backup.clone());
+    }
+
+    let dependents: Vec<Uuid> = delete::dependents(&catalog, full.id())
+        .iter()
+        .map(|backup| backup.id())
+        .collect();
+    assert_eq!(dependents, [first.id(), second.id()]);
+    assert!(DeletePlan::new(&catalog, full.id(), &[], false).is_err());
+
+    let plan = DeletePlan::new(&catalog, full.id(), &[], true).unwrap();
+    let report = DeleteTask::spawn(plan).unwrap().join().unwrap();
+    assert_eq!(report.merged.len(), 1);
+    assert!(!dir.join("full").exists());
+
+    let layout = BackupLayout::new(dir.join("first"));
+    let merged = BackupMetadata::load(layout.manifest_path()).unwrap();
+    assert_eq!(merged.kind(), BackupKind::Full);
+    assert_eq!(merged.parent_id(), None);
+    let index = FileIndex::load(layout.index_path()).unwrap();
+    assert!(index.referenced_backups().is_empty());
+    assert_eq!(
+        fs::read_to_string(layout.stored_path("/src/a")).unwrap(),
+        "A"
+    );
+
+    let index = FileIndex::load(BackupLayout::new(dir.join("second")).index_path()).unwrap();
+    assert_eq!(index.get("/src/a").unwrap().stored_in, Some(first.id()));
+    assert_eq!(index.get("/src/b").unwrap().stored_in, Some(first.id()));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_failed_merge_leaves_every_dependent_as_it_was() {
+    let dir = fixture_dir("delete-rollback");
+    let full = stored_backup(&dir.join("full"), "full", None, &[("/src/a", "A", None)]);
+    let first = stored_backup(
+        &dir.join("first"),
+        "first",
+        Some(&full),
+        &[("/src/a", "A", Some(full.id()))],
+    );
+    let second = stored_backup(
+        &dir.join("second"),
+        "second",
+        Some(&full),
+        &[("/src/a", "A", Some(full.id()))],
+    );
+    fs::write(
+        BackupLayout::new(dir.join("second")).index_path(),
+        "not an index",
+    )
+    .unwrap();
+
+    let mut catalog = Catalog::default();
+    for backup in [&full, &first, &second] {
+        catalog.record(backup.clone());
+    }
+
+    let first_layout = BackupLayout::new(dir.join("first"));
+    let manifest = fs::read_to_string(first_layout.manifest_path()).unwrap();
+    let index = fs::read_to_string(first_layout.index_path()).unwrap();
+
+    let plan = DeletePlan::new(&catalog, full.id(), &[], true).unwrap();
+    assert!(DeleteTask::spawn(plan).unwrap().join().is_err());
+
+    assert_eq!(
+        fs::read_to_string(first_layout.manifest_path()).unwrap(),
+        manifest
+    );
+    assert_eq!(
+        fs::read_to_string(first_layout.index_path()).unwrap(),
+        index
+    );
+    assert!(!first_layout.stored_path("/src/a").exists());
+    assert!(fs::read_dir(dir.join("first")).unwrap().all(|entry| {
+        !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".merging")
+    }));
+    assert!(dir.join("full").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Every file under `dir` by its path relative to it, with its content.
+fn read_tree(dir: &Path) -> BTreeMap<PathBuf, String> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        for entry in fs::read_dir(&path).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let content = fs::read_to_string(&path).unwrap();
+                files.insert(path.strip_prefix(dir).unwrap().to_path_buf(), content);
+            }
+        }
+    }
+    files
+}
+
+#[test]
+fn a_synthetic_full_backup_restores_like_its_chain() {
+    let dir = fixture_dir("synthetic");
+    let full = stored_backup(
+        &dir.join("full"),
+        "full",
+        None,
+        &[("/src/a", "A", None), ("/src/b", "B", None)],
+    );
+    let first = stored_backup(
+        &dir.join("first"),
+        "first",
+        Some(&full),
+        &[
+            ("/src/a", "A", Some(full.id())),
+            ("/src/b", "B2", None),
+            ("/src/c", "C", None),
+        ],
+    );
+    let second = stored_backup(
+        &dir.join("second"),
+        "second",
+        Some(&first),
+        &[
+            ("/src/a", "A", Some(full.id())),
+            ("/src/b", "B2", Some(first.id())),
+            ("/src/c", "C2", None),
+            ("/src/sub/d", "D", None),
+        ],
+    );
+    let mut catalog = Catalog::default();
+    for backup in [&full, &first, &second] {
+        catalog.record(backup.clone());
+    }
+
+    let plan = SyntheticPlan::new(&catalog, second.id(), &[]).unwrap();
+    let synthetic = SyntheticTask::spawn(plan).unwrap().join().unwrap();
+    assert_eq!(synthetic.kind(), BackupKind::Full);
+    assert_eq!(synthetic.parent_id(), None);
+    assert_eq!(synthetic.synthesized_from(), Some(second.id()));
+    assert_eq!(synthetic.created_at(), second.created_at());
+    assert_eq!(synthetic.file_count(), 4);
+
+    let index = FileIndex::load(BackupLayout::new(synthetic.target_path()).index_path()).unwrap();
+    assert_eq!(index.iter().count(), 4);
+    assert!(index.iter().all(|(_, entry)| entry.stored_in.is_none()));
+
+    let chain = catalog.chain(&second);
+    restore::restore(&second, &chain, &[], Some(&dir.join("from-chain"))).unwrap();
+    restore::restore(&synthetic, &[], &[], Some(&dir.join("from-synthetic"))).unwrap();
+    let restored = read_tree(&dir.join("from-synthetic"));
+    assert_eq!(restored, read_tree(&dir.join("from-chain")));
+    assert_eq!(restored.len(), 4);
+    assert_eq!(restored[Path::new("src/b")], "B2");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_synthetic_full_backup_supersedes_only_its_tip() {
+    let profile_id = Uuid::new_v4();
+    let full = backup_of(profile_id, 2, None);
+    let tip = backup_of(profile_id, 1, Some(&full));
+    let mut synthetic = backup_of(profile_id, 1, None);
+    synthetic.set_synthesized_from(Some(tip.id()));
+    let newer = backup_of(profile_id, 0, None);
+
+    let mut catalog = Catalog::default();
+    for backup in [&full, &tip, &newer, &synthetic] {
+        catalog.record(backup.clone());
+    }
+
+    // The synthetic backup holds the tip's day, not the one it was built on.
+    let retention = Retention::new(1, 7, 0, 0, Some(0));
+    let plan = retention::plan(&catalog, profile_id, &retention, prune_now());
+    let kept = kept_days(&plan);
+    assert_eq!(kept[&0], [KeepReason::Last, KeepReason::Daily]);
+    assert_eq!(kept[&1], [KeepReason::Daily]);
+    assert_eq!(kept[&2], [KeepReason::Daily]);
+    assert!(
+        plan.keep
+            .iter()
+            .any(|(backup, _)| backup.id() == synthetic.id())
+    );
+    assert_eq!(
+        plan.remove
+            .iter()
+            .map(|backup| backup.id())
+            .collect::<Vec<_>>(),
+        [tip.id()]
+    );
+
+    // A backup still building on the tip keeps it around.
+    let child = backup_of(profile_id, 0, Some(&tip));
+    catalog.record(child);
+    let plan = retention::plan(
+        &catalog,
+        profile_id,
+        &Retention::new(2, 7, 0, 0, Some(0)),
+        prune_now(),
+    );
+    assert!(plan.remove.is_empty());
+    assert!(
+        plan.keep.iter().any(
+            |(backup, reasons)| backup.id() == tip.id() && reasons == &[KeepReason::Dependency]
+        )
+    );
+}
+
+/// Runs a backup in a worker to its end and returns what it completed with.
+fn run_backup(metadata: BackupMetadata, verify: bool) -> BackupMetadata {
+    let disk = BackupDisk::new("test", metadata.target_path());
+    let worker = BackupWorker::spawn(metadata, Vec::new(), disk, None, verify).unwrap();
+    while !worker.is_finished() {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    worker
+        .events()
+        .find_map(|event| match event {
+            WorkerEvent::Completed(metadata) => Some(*metadata),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn backups_are_verified_once_copied_when_asked_to() {
+    let dir = fixture_dir("verify-after");
+    fs::create_dir_all(dir.join("source/sub")).unwrap();
+    fs::write(dir.join("source/a"), "A").unwrap();
+    fs::write(dir.join("source/sub/b"), "BB").unwrap();
+
+    let run = |name: &str, verify: bool| {
+        let metadata = BackupMetadata::new(
+            name,
+            BackupKind::Full,
+            vec![dir.join("source")],
+            dir.join(name),
+        );
+        run_backup(metadata, verify)
+    };
+
+    let verified = run("verified", true);
+    let verification = verified.verification().unwrap();
+    assert_eq!(verification.checked(), 2);
+    assert!(verification.is_ok());
+    let manifest = BackupMetadata::load(BackupLayout::new(dir.join("verified")).manifest_path());
+    assert!(manifest.unwrap().verification().is_some());
+
+    assert!(run("unverified", false).verification().is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compressed_backups_are_archives_that_verify_and_restore() {
+    let dir = fixture_dir("compressed");
+    let source = dir.join("source");
+    fs::create_dir_all(source.join("sub")).unwrap();
+    fs::write(source.join("a"), "A".repeat(4096)).unwrap();
+    fs::write(source.join("sub/b"), "BB").unwrap();
+    symlink("../a", source.join("sub/link")).unwrap();
+
+    let run = |name: &str, kind: BackupKind, compression: CompressionKind| {
+        let mut metadata = BackupMetadata::new(name, kind, vec![source.clone()], dir.join(name));
+        metadata.set_compression(compression, CompressionLevel::Best);
+        metadata.set_hash_algorithm(Some(HashAlgorithm::Sha256));
+        run_backup(metadata, true)
+    };
+
+    let compressed = run("compressed", BackupKind::Compressed, CompressionKind::Zip);
+    let layout = BackupLayout::new(compressed.target_path());
+    assert!(layout.archive_path().is_file());
+    assert!(!layout.data_dir().exists());
+    assert!(fs::metadata(layout.archive_path()).unwrap().len() < 4096);
+    assert_eq!(compressed.file_count(), 2);
+    assert!(compressed.verification().unwrap().is_ok());
+    assert_eq!(BackupJson::from(&compressed).compression, Some("zip"));
+
+    // The checksum covers the content, not how it is stored.
+    let copied = run("copied", BackupKind::Full, CompressionKind::None);
+    assert_eq!(compressed.archive_checksum(), copied.archive_checksum());
+
+    let report = verify::verify(layout.root(), &HashMap::new(), true).unwrap();
+    assert_eq!(report.checked, 2);
+    assert!(report.is_ok());
+
+    let to = dir.join("restored");
+    let summary = restore::restore(&compressed, &[], &[], Some(&to)).unwrap();
+    assert_eq!(summary.file_count, 2);
+    assert!(summary.failed_files.is_empty());
+    let restored = to.join(relative_to_root(&source));
+    assert_eq!(read_tree(&restored), read_tree(&source));
+    assert_eq!(
+        fs::read_link(restored.join("sub/link")).unwrap(),
+        PathBuf::from("../a")
+    );
+    assert_eq!(
+        fs::metadata(restored.join("a")).unwrap().mtime(),
+        fs::metadata(source.join("a")).unwrap().mtime()
+    );
+
+    // Same size and time, but other content.
+    let modified = fs::metadata(source.join("sub/b"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    fs::write(source.join("sub/b"), "CC").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(source.join("sub/b"))
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    let report = verify::verify(layout.root(), &HashMap::new(), true).unwrap();
+    assert_eq!(report.problems.len(), 1);
+    assert_eq!(report.problems[0].0, source.join("sub/b"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sources_are_stored_by_their_absolute_path() {
+    let layout = BackupLayout::new("/mnt/backup/docs");
+    assert_eq!(
+        layout.manifest_path(),
+        PathBuf::from("/mnt/backup/docs/backup.toml")
+    );
+    assert_eq!(
+        layout.index_path(),
+        PathBuf::from("/mnt/backup/docs/index.toml")
+    );
+
+    let stored = layout.stored_path("/home/user/.config/app.toml");
+    assert_eq!(
+        stored,
+        PathBuf::from("/mnt/backup/docs/data/home/user/.config/app.toml")
+    );
+    assert_eq!(
+        layout.original_path(&stored),
+        Some(PathBuf::from("/home/user/.config/app.toml"))
+    );
+    assert_eq!(layout.original_path("/elsewhere/file"), None);
+    assert_eq!(
+        relative_to_root("/etc/../etc/./hosts"),
+        PathBuf::from("etc/etc/hosts")
+    );
+
+    let dir = fixture_dir("layout");
+    fs::create_dir_all(dir.join("docs/projects")).unwrap();
+    fs::create_dir_all(dir.join("etc")).unwrap();
+    let sources = normalize_sources(&[
+        dir.join("etc"),
+        dir.join("docs/projects"),
+        dir.join("docs"),
+        dir.join("etc"),
+    ]);
+    assert_eq!(sources, [dir.join("docs"), dir.join("etc")]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn restores_give_back_what_was_backed_up() {
+    let dir = fixture_dir("restore");
+    fs::create_dir_all(dir.join("docs/sub")).unwrap();
+    fs::create_dir_all(dir.join("etc")).unwrap();
+    fs::write(dir.join("docs/a"), "A").unwrap();
+    fs::write(dir.join("docs/sub/b"), "B").unwrap();
+    symlink("a", dir.join("docs/link")).unwrap();
+    fs::write(dir.join("etc/c"), "C").unwrap();
+
+    let metadata = BackupMetadata::new(
+        "docs",
+        BackupKind::Full,
+        vec![dir.join("docs"), dir.join("etc")],
+        dir.join("backup"),
+    );
+    let metadata = run_backup(metadata, false);
+    assert_eq!(metadata.file_count(), 3);
+
+    let all = dir.join("all");
+    let summary = restore::restore(&metadata, &[], &[], Some(&all)).unwrap();
+    assert!(summary.failed_files.is_empty());
+    let restored = all.join(relative_to_root(&dir));
+    assert_eq!(
+        read_tree(&restored.join("docs")),
+        read_tree(&dir.join("docs"))
+    );
+    assert_eq!(
+        read_tree(&restored.join("etc")),
+        read_tree(&dir.join("etc"))
+    );
+    assert_eq!(
+        fs::read_link(restored.join("docs/link")).unwrap(),
+        PathBuf::from("a")
+    );
+
+    let one = dir.join("one");
+    restore::restore(&metadata, &[], &[dir.join("docs/./sub")], Some(&one)).unwrap();
+    let restored = one.join(relative_to_root(&dir));
+    assert_eq!(
+        read_tree(&restored),
+        BTreeMap::from([(PathBuf::from("docs/sub/b"), "B".to_string())])
+    );
+
+    // Nothing outside the sources, and no way out of them with `..`.
+    for path in [
+        dir.clone(),
+        dir.join("docs/../etc"),
+        dir.join("docs/sub/../../.."),
+    ] {
+        assert!(restore::restore(&metadata, &[], &[path], Some(&one)).is_err());
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_arguments_parse_into_commands() {
+    let cli = Cli::try_parse_from([
+        "sanup",
+        "--config",
+        "/tmp/sanup.toml",
+        "restore",
+        "1f2e",
+        "docs",
+        "/etc",
+        "--to",
+        "out",
+        "--json",
+    ])
+    .unwrap();
+    assert_eq!(cli.config, Some(PathBuf::from("/tmp/sanup.toml")));
+    match cli.command {
+        Some(Command::Restore {
+            id,
+            paths,
+            to,
+            json,
+        }) => {
+            assert_eq!(id, "1f2e");
+            assert_eq!(paths, [PathBuf::from("docs"), PathBuf::from("/etc")]);
+            assert_eq!(to, Some(PathBuf::from("out")));
+            assert!(json);
+        }
+        _ => panic!("not parsed as a restore"),
+    }
+
+    match Cli::try_parse_from(["sanup", "prune", "--dry-run"])
+        .unwrap()
+        .command
+    {
+        Some(Command::Prune { profile, dry_run }) => {
+            assert_eq!(profile, None);
+            assert!(dry_run);
+        }
+        _ => panic!("not parsed as a prune"),
+    }
+    assert!(Cli::try_parse_from(["sanup"]).unwrap().command.is_none());
+
+    for args in [
+        vec!["sanup", "restore"],
+        vec!["sanup", "backup", "home", "--bogus"],
+        vec!["sanup", "unknown"],
+    ] {
+        let err = Cli::try_parse_from(args).err().unwrap();
+        assert_eq!(err.exit_code(), Exit::Usage as i32);
+    }
+}
+
+#[test]
+fn cli_failures_map_to_their_exit_codes() {
+    let codes = [
+        (Exit::Success, 0),
+        (Exit::Error, 1),
+        (Exit::Usage, 2),
+        (Exit::NotFound, 3),
+        (Exit::DiskUnavailable, 4),
+        (Exit::Failed, 5),
+        (Exit::Partial, 6),
+    ];
+    for (exit, code) in codes {
+        assert_eq!(ExitCode::from(exit), ExitCode::from(code));
+    }
+
+    let err = CliError::from(SanupError::Other("broken".to_string()));
+    assert_eq!((err.exit, err.message.as_str()), (Exit::Error, "broken"));
+
+    let mut catalog = Catalog::default();
+    for name in ["first", "second"] {
+        catalog.record(BackupMetadata::new(
+            name,
+            BackupKind::Full,
+            Vec::new(),
+            "/backups",
+        ));
+    }
+    let id = catalog.backups()[0].id().to_string();
+    assert_eq!(
+        cli::find_backup(&catalog, &id[..8]).unwrap().name(),
+        "first"
+    );
+    assert_eq!(
+        cli::find_backup(&catalog, "zz").err().unwrap().exit,
+        Exit::NotFound
+    );
+    assert_eq!(
+        cli::find_backup(&catalog, "").err().unwrap().exit,
+        Exit::Usage
+    );
+}
+
+#[test]
+fn cli_paths_are_taken_from_the_working_directory() {
+    let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+    assert_eq!(
+        cli::absolute_path(Path::new("docs/report.txt")).unwrap(),
+        cwd.join("docs/report.txt")
+    );
+    assert_eq!(
+        cli::absolute_path(Path::new("./docs")).unwrap(),
+        cwd.join("docs")
+    );
+    assert_eq!(
+        cli::absolute_path(Path::new("/etc/hosts")).unwrap(),
+        PathBuf::from("/etc/hosts")
+    );
+    assert_eq!(
+        cli::absolute_path(Path::new("../docs")).unwrap_err().exit,
+        Exit::Usage
+    );
+}
+
+#[test]
+fn json_events_cover_every_backup_of_a_run() {
+    let dir = fixture_dir("json-events");
+    let mut tasks = Vec::new();
+    for name in ["docs", "photos"] {
+        fs::create_dir_all(dir.join(name)).unwrap();
+        fs::write(dir.join(name).join("file"), name).unwrap();
+
+        let mut task = BackupTask::new(BackupMetadata::new(
+            name,
+            BackupKind::Full,
+            vec![dir.join(name)],
+            dir.join("backups").join(name),
+        ));
+        task.start(BackupDisk::new("test", dir.join("backups")), None, false)
+            .unwrap();
+        tasks.push(task);
+    }
+
+    let mut events = cli::BackupEvents::default();
+    let mut lines = Vec::new();
+    loop {
+        for task in &mut tasks {
+            task.update();
+        }
+        let finished = tasks.iter().all(|task| task.status().is_finished());
+        for event in events.update(&tasks) {
+            lines.push(serde_json::to_value(&event).unwrap());
+        }
+        if finished {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert!(events.update(&tasks).is_empty());
+
+    for task in &tasks {
+        let id = task.metadata().id().to_string();
+        let of_task: Vec<&serde_json::Value> = lines
+            .iter()
+            .filter(|line| line["id"] == id.as_str() || line["backup"]["id"] == id.as_str())
+            .collect();
+        let names: Vec<&str> = of_task
+            .iter()
+            .map(|line| line["event"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(names.first(), Some(&"backup_started"));
+        assert_eq!(names.last(), Some(&"backup_finished"));
+        assert_eq!(
+            names
+                .iter()
+                .filter(|name| **name == "backup_finished")
+                .count(),
+            1
+        );
+
+        let started = &of_task[0]["backup"];
+        assert_eq!(started["name"], task.metadata().name());
+        assert_eq!(started["kind"], "full");
+        assert!(started["compression"].is_null());
+        assert!(started["source_paths"].is_array());
+        for line in &of_task[1..of_task.len() - 1] {
+            assert_eq!(line["event"], "backup_status");
+            assert!(line["status"]["state"].is_string());
+        }
+        let finished = &of_task[of_task.len() - 1]["backup"];
+        assert_eq!(finished["status"]["state"], "completed");
+        assert_eq!(finished["file_count"], 1);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+fn filter_fixture(name: &str) -> PathBuf {
+    let dir = fixture_dir(name);
+    for (path, content) in [
+        ("a.txt", "a"),
+        ("big.iso", "iso image"),
+        ("keep.iso", "kept iso"),
+        ("build/out.o", "object"),
+        ("build/nested/deep.o", "deep object"),
+        ("notes/todo.md", "todo"),
+        ("notes/scratch.tmp", "scratch"),
+        ("cache/blob", "blob"),
+        (".gitignore", "*.tmp\n"),
+        (".sanupignore", "cache/\n"),
+    ] {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn walked_files(filter: &BackupFilter, source: &Path) -> Vec<String> {
+    filter
+        .walk(source)
+        .unwrap()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| {
+            entry
+                .path()
+                .strip_prefix(source)
+                .unwrap()
+                .display()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn filters_exclude_and_reinclude_gitignore_patterns() {
+    let dir = filter_fixture("filter-patterns");
+    let filter = BackupFilter::new(
+        vec!["*.iso".to_string(), "build/".to_string()],
+        vec!["keep.iso".to_string()],
+    );
+
+    let matcher = filter.matcher(&dir).unwrap();
+    assert!(matcher.matched(dir.join("big.iso"), false).is_ignore());
+    assert!(matcher.matched(dir.join("keep.iso"), false).is_whitelist());
+    assert!(matcher.matched(dir.join("build"), true).is_ignore());
+    assert!(matcher.matched(dir.join("a.txt"), false).is_none());
+
+    assert_eq!(
+        walked_files(&filter, &dir),
+        vec![
+            ".gitignore",
+            ".sanupignore",
+            "a.txt",
+            "keep.iso",
+            "notes/scratch.tmp",
+            "notes/todo.md",
+        ]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn ignore_files_apply_only_when_enabled() {
+    let dir = filter_fixture("filter-ignore-files");
+    let mut filter = BackupFilter::default();
+
+    let files = walked_files(&filter, &dir);
+    assert!(files.contains(&"notes/scratch.tmp".to_string()));
+    assert!(!files.contains(&"cache/blob".to_string()));
+
+    filter.set_use_gitignore(true);
+    filter.set_use_sanupignore(false);
+    let files = walked_files(&filter, &dir);
+    assert!(!files.contains(&"notes/scratch.tmp".to_string()));
+    assert!(files.contains(&"cache/blob".to_string()));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn filter_preview_sums_kept_files_and_top_excluded_paths() {
+    let dir = filter_fixture("filter-preview");
+    let mut filter = BackupFilter::new(vec!["build/".to_string(), "*.iso".to_string()], vec![]);
+    filter.set_use_gitignore(true);
+
+    let preview = filter.preview(std::slice::from_ref(&dir)).unwrap();
+
+    // .gitignore, .sanupignore, a.txt and notes/todo.md
+    assert_eq!(preview.included_files, 4);
+    assert_eq!(preview.included_bytes, 6 + 7 + 1 + 4);
+    assert_eq!(preview.excluded_files, 6);
+    assert_eq!(
+        preview.excluded_bytes,
+        ("iso image".len() + "kept iso".len() + "object".len() + "deep object".len()) as u64
+            + ("scratch".len() + "blob".len()) as u64
+    );
+
+    let matches = preview
+        .matches
+        .iter()
+        .map(|(path, size)| {
+            (
+                path.strip_prefix(&dir).unwrap().display().to_string(),
+                (size.files, size.bytes),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        matches,
+        vec![
+            ("big.iso".to_string(), (1, 9)),
+            ("build".to_string(), (2, 17)),
+            ("cache".to_string(), (1, 4)),
+            ("keep.iso".to_string(), (1, 8)),
+            ("notes/scratch.tmp".to_string(), (1, 7)),
+        ]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn selection_rules_skip_by_size_age_and_type() {
+    let dir = fixture_dir("selection-rules");
+    fs::create_dir_all(&dir).unwrap();
+
+    let large = dir.join("large.bin");
+    fs::File::create(&large)
+        .unwrap()
+        .set_len(2 * 1024 * 1024 + 1)
+        .unwrap();
+    let old = dir.join("old.txt");
+    let file = fs::File::create(&old).unwrap();
+    file.set_modified(SystemTime::now() - std::time::Duration::from_secs(10 * 24 * 60 * 60))
+        .unwrap();
+    let small = dir.join("small.txt");
+    fs::write(&small, "small").unwrap();
+    let socket = dir.join("socket");
+    let _listener = UnixListener::bind(&socket).unwrap();
+
+    let check =
+        |rules: &SelectionRules, path: &Path| rules.check(&fs::symlink_metadata(path).unwrap());
+
+    let mut rules = SelectionRules::default();
+    rules.set_skip_special_files(false);
+    for path in [&large, &old, &small, &socket, &dir] {
+        assert_eq!(check(&rules, path), None);
+    }
+
+    rules.set_max_file_size_mb(Some(2));
+    rules.set_max_age_days(Some(7));
+    rules.set_only_owned_by_me(true);
+    assert_eq!(check(&rules, &large), Some(SkipReason::TooLarge));
+    assert_eq!(check(&rules, &old), Some(SkipReason::TooOld));
+    assert_eq!(check(&rules, &small), None);
+    assert_eq!(check(&rules, &socket), None);
+    assert_eq!(check(&rules, &dir), None);
+
+    rules.set_max_file_size_mb(Some(3));
+    rules.set_max_age_days(Some(11));
+    rules.set_skip_special_files(true);
+    assert_eq!(check(&rules, &large), None);
+    assert_eq!(check(&rules, &old), None);
+    assert_eq!(check(&rules, &socket), Some(SkipReason::SpecialFile));
+
+    let mut counts = SkippedCounts::default();
+    for reason in [
+        SkipReason::TooLarge,
+        SkipReason::TooOld,
+        SkipReason::SpecialFile,
+        SkipReason::SpecialFile,
+    ] {
+        counts.add(reason);
+    }
+    assert_eq!(counts.special_file, 2);
+    assert_eq!(counts.total(), 4);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn disk_ids_come_from_the_marker_then_the_fs_uuid_then_the_mount_path() {
+    let dir = fixture_dir("disk-ids");
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut disk = BackupDisk::new("usb", &dir);
+    let by_mount = Uuid::new_v5(
+        &Uuid::NAMESPACE_OID,
+        format!("mount:{}", dir.to_string_lossy()).as_bytes(),
+    );
+    assert!(disk.marker().is_none());
+    assert_eq!(disk.id(), by_mount);
+
+    disk.set_fs_uuid(Some("1234-ABCD".to_string()));
+    let by_fs_uuid = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"fs-uuid:1234-ABCD");
+    assert_eq!(disk.id(), by_fs_uuid);
+
+    fs::write(
+        dir.join(disk::MARKER_FILE),
+        "id = \"2021efcb-c2ba-4cf4-bc59-78dd250bbbe8\"\n\
+         label = \"usb\"\n\
+         created_at = \"2026-10-01T12:00:00Z\"\n",
+    )
+    .unwrap();
+    let mut disk = BackupDisk::new("usb", &dir);
+    disk.set_fs_uuid(Some("1234-ABCD".to_string()));
+    assert_eq!(
+        disk.id(),
+        Uuid::parse_str("2021efcb-c2ba-4cf4-bc59-78dd250bbbe8").unwrap()
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn disk_markers_keep_the_id_across_mount_points() {
+    let dir = fixture_dir("disk-marker");
+    let first = dir.join("first");
+    fs::create_dir_all(&first).unwrap();
+
+    let mut disk = BackupDisk::new("usb", &first);
+    disk.set_fs_uuid(Some("1234-ABCD".to_string()));
+    let id = disk.id();
+    disk.write_marker().unwrap();
+    assert_eq!(disk.marker().unwrap().id(), id);
+    assert_eq!(disk.marker().unwrap().label(), "usb");
+
+    let created_at = disk.marker().unwrap().created_at();
+    disk.write_marker().unwrap();
+    assert_eq!(disk.marker().unwrap().created_at(), created_at);
+
+    // The same disk mounted elsewhere, read without its filesystem UUID.
+    let second = dir.join("second");
+    fs::rename(&first, &second).unwrap();
+    let moved = BackupDisk::new("usb", &second);
+    assert_eq!(moved.id(), id);
+    assert_eq!(moved.marker().unwrap().created_at(), created_at);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+fn rotation_disk(name: &str) -> BackupDisk {
+    let mut disk = BackupDisk::new(name, format!("/run/media/user/{}", name));
+    disk.set_fs_uuid(Some(format!("uuid-{}", name)));
+    disk
+}
+
+#[test]
+fn rotation_picks_the_mounted_disk_that_waited_longest() {
+    let disks = ["blue", "green", "red", "other"].map(rotation_disk);
+    let mut profile = scheduled_profile("Docs", Schedule::default());
+    for disk in &disks[..3] {
+        profile.add_target_disk(disk, "sanup");
+    }
+
+    let mut catalog = Catalog::default();
+    for (disk, days_ago) in [(&disks[0], 1), (&disks[1], 9), (&disks[1], 5)] {
+        let mut backup = backup_of(profile.id(), days_ago, None);
+        backup.set_disk(disk);
+        catalog.record(backup);
+    }
+    let mut failed = backup_of(profile.id(), 2, None);
+    failed.set_disk(&disks[2]);
+    failed.set_status(BackupStatus::Failed {
+        reason: "disk full".to_string(),
+    });
+    catalog.record(failed);
+
+    let picked = |mounted: &[BackupDisk]| {
+        rotation::pick_disk(&profile, mounted, &catalog).map(|disk| disk.label().to_string())
+    };
+
+    // Red never got a completed backup.
+    assert_eq!(picked(&disks).as_deref(), Some("red"));
+    // Unmounted disks are left out, green waited longer than blue.
+    assert_eq!(picked(&disks[..2]).as_deref(), Some("green"));
+    assert_eq!(picked(&disks[..1]).as_deref(), Some("blue"));
+    // A mounted disk outside the rotation is never picked.
+    assert_eq!(picked(&disks[3..]), None);
+    assert_eq!(picked(&[]), None);
+}
+
+#[test]
+fn space_checks_compare_the_scan_estimate_with_free_space() {
+    let dir = filter_fixture("space-estimate");
+    let filter = BackupFilter::new(vec!["build/".to_string()], vec![]);
+    let mut rules = SelectionRules::default();
+    rules.set_max_file_size_mb(Some(1));
+    fs::File::create(dir.join("huge.bin"))
+        .unwrap()
+        .set_len(1024 * 1024 + 1)
+        .unwrap();
+
+    // Everything but build/, cache/ (.sanupignore) and huge.bin (rules).
+    let estimate = SizeScan::new(vec![dir.clone()])
+        .with_selection(filter, rules)
+        .run()
+        .total;
+    assert_eq!(estimate.files, 7);
+    assert_eq!(estimate.bytes, 6 + 7 + 1 + 9 + 8 + 4 + 7);
+
+    let policy = FreeSpacePolicy { reserve_bytes: 10 };
+    let verdict = |free_bytes| SpaceCheck::new(estimate.bytes, free_bytes, policy).verdict;
+    assert_eq!(verdict(estimate.bytes + 10), SpaceVerdict::Enough);
+    assert_eq!(verdict(estimate.bytes + 9), SpaceVerdict::BelowReserve);
+    assert_eq!(verdict(estimate.bytes), SpaceVerdict::BelowReserve);
+    assert_eq!(verdict(estimate.bytes - 1), SpaceVerdict::Insufficient);
+
+    let check = check_free_space(&dir, estimate.bytes, policy).unwrap();
+    assert_eq!(check.free_bytes, filesystem_space(&dir).unwrap().free_bytes);
+    assert_eq!(check.required_bytes, estimate.bytes);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn backups_that_do_not_fit_fail_before_copying() {
+    let dir = fixture_dir("space-insufficient");
+    let source = dir.join("source");
+    fs::create_dir_all(&source).unwrap();
+    fs::write(source.join("a"), "A").unwrap();
+    // Sparse, it takes no space but is larger than what is free.
+    let huge = source.join("huge");
+    fs::File::create(&huge)
+        .unwrap()
+        .set_len(filesystem_space(&dir).unwrap().free_bytes + 1)
+        .unwrap();
+
+    let run = |name: &str, chain: Vec<BackupMetadata>| {
+        let kind = if chain.is_empty() {
+            BackupKind::Full
+        } else {
+            BackupKind::Incremental
+        };
+        let mut metadata = BackupMetadata::new(name, kind, vec![source.clone()], dir.join(name));
+        metadata.set_parent_id(chain.first().map(|parent| parent.id()));
+        let disk = BackupDisk::new("test", &dir);
+        let policy = FreeSpacePolicy { reserve_bytes: 0 };
+        let worker = BackupWorker::spawn(metadata, chain, disk, Some(policy), false).unwrap();
+        while !worker.is_finished() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        worker.events().last().unwrap()
+    };
+
+    match run("full", Vec::new()) {
+        WorkerEvent::Failed { metadata, reason } => {
+            assert!(reason.starts_with("Not enough free space on target"));
+            assert!(matches!(metadata.status(), BackupStatus::Failed { .. }));
+            assert!(
+                !BackupLayout::new(dir.join("full"))
+                    .stored_path(&huge)
+                    .exists()
+            );
+        }
+        _ => panic!("expected the full backup to fail"),
+    }
+
+    // A parent that already holds the huge file leaves only `a` to store.
+    let parent = BackupMetadata::new(
+        "parent",
+        BackupKind::Full,
+        vec![source.clone()],
+        dir.join("parent"),
+    );
+    let mut index = FileIndex::default();
+    index.insert(huge.clone(), IndexEntry::new(&fs::metadata(&huge).unwrap()));
+    index
+        .save(BackupLayout::new(dir.join("parent")).index_path())
+        .unwrap();
+
+    let scan = SizeScan::new(vec![source.clone()]).run_against(Some(&index));
+    assert_eq!(scan.changed.files, 1);
+    assert_eq!(scan.changed.bytes, 1);
+    assert_eq!(scan.total.files, 2);
+
+    match run("incremental", vec![parent]) {
+        WorkerEvent::Completed(metadata) => {
+            assert_eq!(metadata.file_count(), 1);
+            assert_eq!(metadata.unchanged_files(), 1);
+        }
+        _ => panic!("expected the incremental backup to complete"),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn daemon_clients_stay_connected_across_idle_gaps() {
+    let dir = fixture_dir("daemon-socket");
+    let mut server = DaemonServer::bind(dir.join("sanup.sock")).unwrap();
+    let handle = |request| match request {
+        Request::Status => Response::Status(Vec::new()),
+        _ => Response::Ok,
+    };
+
+    let stream = UnixStream::connect(dir.join("sanup.sock")).unwrap();
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_secs(2)))
+        .unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut status = || {
+        writeln!(
+            writer,
+            "{}",
+            serde_json::to_string(&Request::Status).unwrap()
+        )
+        .unwrap();
+
+        // A connected client never keeps the tick loop waiting.
+        let served_at = std::time::Instant::now();
+        server.serve(handle);
+        server.serve(handle);
+        assert!(served_at.elapsed() < std::time::Duration::from_millis(500));
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line
+    };
+
+    assert_eq!(status(), "{\"Status\":[]}\n");
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    assert_eq!(status(), "{\"Status\":[]}\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
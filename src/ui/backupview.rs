@@ -0,0 +1,565 @@
+use crate::{
+    app::{settings::Settings, status_filter::StatusFilter},
+    backup::{catalog::Catalog, disk::BackupDisk, metadata::BackupMetadata, status::BackupStatus},
+    ui::{format_bytes, format_duration},
+};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Paragraph, Row, StatefulWidget, Table, TableState, Widget, Wrap},
+};
+use std::{cmp::Ordering, collections::HashMap};
+use uuid::Uuid;
+
+/// Column the history is sorted by.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum SortColumn {
+    #[default]
+    Created,
+    Name,
+    Kind,
+    Source,
+    Disk,
+    Size,
+    Files,
+    Duration,
+    Status,
+    Verified,
+}
+
+impl SortColumn {
+    const ALL: [SortColumn; 10] = [
+        SortColumn::Created,
+        SortColumn::Name,
+        SortColumn::Kind,
+        SortColumn::Source,
+        SortColumn::Disk,
+        SortColumn::Size,
+        SortColumn::Files,
+        SortColumn::Duration,
+        SortColumn::Status,
+        SortColumn::Verified,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            SortColumn::Created => "Created",
+            SortColumn::Name => "Name",
+            SortColumn::Kind => "Kind",
+            SortColumn::Source => "Source",
+            SortColumn::Disk => "Disk",
+            SortColumn::Size => "Size",
+            SortColumn::Files => "Files",
+            SortColumn::Duration => "Duration",
+            SortColumn::Status => "Status",
+            SortColumn::Verified => "Verified",
+        }
+    }
+
+    fn next(&self) -> Self {
+        let idx = Self::ALL
+            .iter()
+            .position(|column| column == self)
+            .unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// What the history is narrowed down to. Profile and disk match any part of
+/// the name, ignoring case.
+#[derive(Default, Clone)]
+pub struct HistoryFilter {
+    pub profile: String,
+    pub disk: String,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub status: StatusFilter,
+}
+
+impl HistoryFilter {
+    fn describe(&self) -> Vec<String> {
+        let mut parts = Vec::new();
+        if !self.profile.is_empty() {
+            parts.push(format!("profile ~ {}", self.profile));
+        }
+        if !self.disk.is_empty() {
+            parts.push(format!("disk ~ {}", self.disk));
+        }
+        if let Some(from) = self.from {
+            parts.push(format!("from {}", from));
+        }
+        if let Some(to) = self.to {
+            parts.push(format!("to {}", to));
+        }
+        if self.status != StatusFilter::Any {
+            parts.push(self.status.to_string());
+        }
+        parts
+    }
+}
+
+/// Backups recorded in the catalog, filtered and sorted, with the details of
+/// the selected one.
+#[derive(Default)]
+pub struct BackupView {
+    backups: Vec<BackupMetadata>,
+    /// Indices into `backups` of the rows shown, in order.
+    rows: Vec<usize>,
+    profile_names: HashMap<Uuid, String>,
+    disk_labels: HashMap<Uuid, String>,
+    filter: HistoryFilter,
+    sort: SortColumn,
+    /// Newest, largest etc. first unless set.
+    ascending: bool,
+    selected_idx: usize,
+    details_scroll: u16,
+    /// Outcome of the last operation on a backup, shown below the table.
+    notice: Option<String>,
+}
+
+impl BackupView {
+    pub fn new(catalog: &Catalog) -> Self {
+        let mut view = Self::default();
+        view.refresh(catalog);
+        view
+    }
+
+    pub fn selected(&self) -> Option<&BackupMetadata> {
+        self.rows
+            .get(self.selected_idx)
+            .and_then(|&idx| self.backups.get(idx))
+    }
+
+    pub fn is_at_top(&self) -> bool {
+        self.selected_idx == 0
+    }
+
+    pub fn set_notice(&mut self, notice: String) {
+        self.notice = Some(notice);
+    }
+
+    pub fn filter(&self) -> &HistoryFilter {
+        &self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: HistoryFilter) {
+        self.filter = filter;
+        self.selected_idx = 0;
+        self.update_rows(None);
+    }
+
+    /// Names profiles and disks after the settings and the disks mounted now.
+    pub fn set_labels(&mut self, settings: &Settings, disks: &[BackupDisk]) {
+        self.profile_names = settings
+            .profiles()
+            .iter()
+            .map(|profile| (profile.id(), profile.name().to_string()))
+            .collect();
+
+        self.disk_labels = settings
+            .profiles()
+            .iter()
+            .flat_map(|profile| profile.target_disks())
+            .map(|disk| (disk.disk_id, disk.label.clone()))
+            .chain(
+                settings
+                    .watched_disks()
+                    .iter()
+                    .filter_map(|disk| Some((disk.disk_id()?, disk.label()?.to_string()))),
+            )
+            .chain(
+                disks
+                    .iter()
+                    .map(|disk| (disk.id(), disk.label().to_string())),
+            )
+            .collect();
+
+        self.update_rows(self.selected().map(|backup| backup.id()));
+    }
+
+    /// Takes the backups of the catalog, keeping the selected one selected
+    /// while it is still shown.
+    pub fn refresh(&mut self, catalog: &Catalog) {
+        let selected = self.selected().map(|backup| backup.id());
+        self.backups = catalog.backups().to_vec();
+        self.update_rows(selected);
+    }
+
+    /// `rows` index into `backups`, so the selection is passed by id and
+    /// found again after they change.
+    fn update_rows(&mut self, selected: Option<Uuid>) {
+        let mut rows: Vec<usize> = (0..self.backups.len())
+            .filter(|&idx| self.is_shown(&self.backups[idx]))
+            .collect();
+        rows.sort_by(|&a, &b| {
+            let ordering = self.compare(&self.backups[a], &self.backups[b]);
+            if self.ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+        self.rows = rows;
+
+        self.selected_idx = selected
+            .and_then(|id| {
+                self.rows
+                    .iter()
+                    .position(|&idx| self.backups[idx].id() == id)
+            })
+            .unwrap_or(self.selected_idx)
+            .min(self.rows.len().saturating_sub(1));
+    }
+
+    fn is_shown(&self, backup: &BackupMetadata) -> bool {
+        let filter = &self.filter;
+        let contains = |text: &str, part: &str| text.to_lowercase().contains(&part.to_lowercase());
+        let created = backup.created_at().with_timezone(&Local).date_naive();
+
+        (filter.profile.is_empty() || contains(&self.profile_name(backup), &filter.profile))
+            && (filter.disk.is_empty() || contains(&self.disk_label(backup), &filter.disk))
+            && filter.from.is_none_or(|from| created >= from)
+            && filter.to.is_none_or(|to| created <= to)
+            && filter.status.matches(backup.status())
+    }
+
+    fn compare(&self, a: &BackupMetadata, b: &BackupMetadata) -> Ordering {
+        let ordering = match self.sort {
+            SortColumn::Created => Ordering::Equal,
+            SortColumn::Name => a.name().cmp(b.name()),
+            SortColumn::Kind => a.kind().to_string().cmp(&b.kind().to_string()),
+            SortColumn::Source => source(a).cmp(&source(b)),
+            SortColumn::Disk => self.disk_label(a).cmp(&self.disk_label(b)),
+            SortColumn::Size => a.total_size_bytes().cmp(&b.total_size_bytes()),
+            SortColumn::Files => a.file_count().cmp(&b.file_count()),
+            SortColumn::Duration => a.duration().cmp(&b.duration()),
+            SortColumn::Status => status(a.status()).cmp(&status(b.status())),
+            SortColumn::Verified => verified(a).cmp(&verified(b)),
+        };
+
+        ordering.then_with(|| a.created_at().cmp(&b.created_at()))
+    }
+
+    fn profile_name(&self, backup: &BackupMetadata) -> String {
+        backup
+            .profile_id()
+            .and_then(|id| self.profile_names.get(&id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The label of the disk the backup is on, or the directory it is in
+    /// when the disk is not known by name.
+    fn disk_label(&self, backup: &BackupMetadata) -> String {
+        backup
+            .disk_id()
+            .and_then(|id| self.disk_labels.get(&id))
+            .cloned()
+            .unwrap_or_else(|| {
+                backup
+                    .target_path()
+                    .parent()
+                    .unwrap_or(backup.target_path())
+                    .display()
+                    .to_string()
+            })
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') if self.selected_idx + 1 < self.rows.len() => {
+                self.selected_idx += 1;
+                self.details_scroll = 0;
+            }
+            KeyCode::Char('k') => {
+                self.selected_idx = self.selected_idx.saturating_sub(1);
+                self.details_scroll = 0;
+            }
+            KeyCode::Char('J') => self.details_scroll = self.details_scroll.saturating_add(1),
+            KeyCode::Char('K') => self.details_scroll = self.details_scroll.saturating_sub(1),
+            KeyCode::Char('o') => {
+                self.sort = self.sort.next();
+                self.update_rows(self.selected().map(|backup| backup.id()));
+            }
+            KeyCode::Char('O') => {
+                self.ascending = !self.ascending;
+                self.update_rows(self.selected().map(|backup| backup.id()));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn source(backup: &BackupMetadata) -> String {
+    match backup.source_paths() {
+        [] => String::new(),
+        [source] => source.display().to_string(),
+        [source, rest @ ..] => format!("{} +{}", source.display(), rest.len()),
+    }
+}
+
+fn status(status: &BackupStatus) -> String {
+    match status {
+        BackupStatus::Failed { .. } => "Failed".to_string(),
+        BackupStatus::Running { .. } => "Running".to_string(),
+        status => format!("{:?}", status),
+    }
+}
+
+fn verified(backup: &BackupMetadata) -> String {
+    match backup.verification() {
+        None => "never".to_string(),
+        Some(verification) if verification.is_ok() => "ok".to_string(),
+        Some(verification) => format!("{} problems", verification.problems()),
+    }
+}
+
+fn date(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+impl Widget for &BackupView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut title = format!(
+            "Backups ({}/{}), by {} {}",
+            self.rows.len(),
+            self.backups.len(),
+            self.sort.title(),
+            if self.ascending { "▲" } else { "▼" }
+        );
+        let filters = self.filter.describe();
+        if !filters.is_empty() {
+            title.push_str(&format!(", {}", filters.join(", ")));
+        }
+
+        let block = Block::bordered().title(title).title_bottom(
+            "c: new, o: sort, O: reverse, f: filter, F: clear, J/K: scroll details, L: logs",
+        );
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let notice_height = if self.notice.is_some() { 1 } else { 0 };
+        let [table_area, details_area, notice_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Percentage(45),
+            Constraint::Length(notice_height),
+        ])
+        .areas(inner_area);
+
+        let header = Row::new(SortColumn::ALL.map(|column| {
+            if column == self.sort {
+                format!(
+                    "{}{}",
+                    column.title(),
+                    if self.ascending { "▲" } else { "▼" }
+                )
+            } else {
+                column.title().to_string()
+            }
+        }))
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = self
+            .rows
+            .iter()
+            .map(|&idx| {
+                let backup = &self.backups[idx];
+                Row::new([
+                    backup
+                        .created_at()
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string(),
+                    backup.name().to_string(),
+                    backup.kind().to_string(),
+                    source(backup),
+                    self.disk_label(backup),
+                    format_bytes(backup.total_size_bytes()),
+                    backup.file_count().to_string(),
+                    backup
+                        .duration()
+                        .and_then(|duration| duration.to_std().ok())
+                        .map(format_duration)
+                        .unwrap_or_default(),
+                    status(backup.status()),
+                    verified(backup),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(16),
+                Constraint::Percentage(14),
+                Constraint::Length(12),
+                Constraint::Percentage(20),
+                Constraint::Percentage(12),
+                Constraint::Length(10),
+                Constraint::Length(7),
+                Constraint::Length(9),
+                Constraint::Length(10),
+                Constraint::Length(11),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::default().fg(Color::Black).bg(Color::Green));
+
+        let mut state = TableState::default().with_selected(Some(self.selected_idx));
+        StatefulWidget::render(table, table_area, buf, &mut state);
+
+        if let Some(backup) = self.selected() {
+            Paragraph::new(self.details(backup))
+                .block(Block::bordered().title(format!("{} ({})", backup.name(), backup.id())))
+                .wrap(Wrap { trim: false })
+                .scroll((self.details_scroll, 0))
+                .render(details_area, buf);
+        }
+
+        if let Some(notice) = &self.notice {
+            Paragraph::new(notice.as_str())
+                .style(Style::default().fg(Color::Yellow))
+                .render(notice_area, buf);
+        }
+    }
+}
+
+impl BackupView {
+    /// Every field of the backup's metadata, one per line.
+    fn details(&self, backup: &BackupMetadata) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        let mut line = |label: &str, value: String| {
+            lines.push(Line::from(format!(
+                "{:<17}{}",
+                format!("{}:", label),
+                value
+            )));
+        };
+        let id = |id: Option<Uuid>| id.map(|id| id.to_string()).unwrap_or_default();
+
+        line("Kind", backup.kind().to_string());
+        line(
+            "Profile",
+            format!("{} {}", self.profile_name(backup), id(backup.profile_id())),
+        );
+        line("Parent", id(backup.parent_id()));
+        line("Synthesized from", id(backup.synthesized_from()));
+        line("Status", status(backup.status()));
+        if let BackupStatus::Failed { reason } = backup.status() {
+            line("Reason", reason.clone());
+        }
+        line("Created", date(backup.created_at()));
+        line("Started", backup.started_at().map(date).unwrap_or_default());
+        line(
+            "Finished",
+            backup.finished_at().map(date).unwrap_or_default(),
+        );
+        line(
+            "Duration",
+            backup
+                .duration()
+                .and_then(|duration| duration.to_std().ok())
+                .map(format_duration)
+                .unwrap_or_default(),
+        );
+        for source in backup.source_paths() {
+            line("Source", source.display().to_string());
+        }
+        line("Target", backup.target_path().display().to_string());
+        line(
+            "Disk",
+            format!(
+                "{} {}",
+                self.disk_label(backup),
+                backup
+                    .disk_path()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default()
+            ),
+        );
+        line("Exclude", backup.filter().exclude().join(", "));
+        line("Include", backup.filter().include().join(", "));
+        line(
+            "Ignore files",
+            format!(
+                "gitignore: {}, sanupignore: {}",
+                backup.filter().use_gitignore(),
+                backup.filter().use_sanupignore()
+            ),
+        );
+        let rules = backup.rules();
+        line(
+            "Rules",
+            format!(
+                "max size: {}, max age: {}, only mine: {}, skip special: {}",
+                rules
+                    .max_file_size_mb()
+                    .map(|size| format!("{} MB", size))
+                    .unwrap_or_else(|| "-".to_string()),
+                rules
+                    .max_age_days()
+                    .map(|days| format!("{} days", days))
+                    .unwrap_or_else(|| "-".to_string()),
+                rules.only_owned_by_me(),
+                rules.skip_special_files()
+            ),
+        );
+        line(
+            "Estimated size",
+            backup
+                .estimated_size_bytes()
+                .map(format_bytes)
+                .unwrap_or_default(),
+        );
+        line(
+            "Files",
+            format!(
+                "{} ({}), {} unchanged",
+                backup.file_count(),
+                format_bytes(backup.total_size_bytes()),
+                backup.unchanged_files()
+            ),
+        );
+        let skipped = backup.skipped();
+        line(
+            "Skipped",
+            format!(
+                "{} too large, {} too old, {} not owned, {} special",
+                skipped.too_large, skipped.too_old, skipped.not_owned, skipped.special_file
+            ),
+        );
+        line("Compression", backup.compression().to_string());
+        line(
+            "Checksum",
+            backup.archive_checksum().unwrap_or_default().to_string(),
+        );
+        line(
+            "Verified",
+            match backup.verification() {
+                Some(verification) => format!(
+                    "{}, {} files checked, {} problems",
+                    date(verification.verified_at()),
+                    verification.checked(),
+                    verification.problems()
+                ),
+                None => "never".to_string(),
+            },
+        );
+        line("Note", backup.note().unwrap_or_default().to_string());
+        line("Failed files", backup.failed_files().len().to_string());
+        for file in backup.failed_files() {
+            lines.push(Line::styled(
+                format!("  {}", file),
+                Style::default().fg(Color::Red),
+            ));
+        }
+
+        lines
+    }
+}
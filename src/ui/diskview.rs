@@ -0,0 +1,181 @@
+use crate::{
+    backup::{
+        discovery::{BlockDevice, DiskDiscovery},
+        disk::BackupDisk,
+    },
+    ui::format_bytes,
+};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Paragraph, Row, StatefulWidget, Table, TableState, Widget},
+};
+
+/// Mounted disks that can be backed up to, plus removable devices that are
+/// plugged in but not mounted. `r` rescans.
+pub struct DiskView {
+    discovery: DiskDiscovery,
+    disks: Vec<BackupDisk>,
+    unmounted: Vec<BlockDevice>,
+    selected_idx: usize,
+    error: Option<String>,
+}
+
+impl DiskView {
+    pub fn new(discovery: DiskDiscovery) -> Self {
+        let mut view = Self {
+            discovery,
+            disks: Vec::new(),
+            unmounted: Vec::new(),
+            selected_idx: 0,
+            error: None,
+        };
+        view.refresh();
+        view
+    }
+
+    pub fn disks(&self) -> &[BackupDisk] {
+        &self.disks
+    }
+
+    pub fn selected(&self) -> Option<&BackupDisk> {
+        self.disks.get(self.selected_idx)
+    }
+
+    pub fn is_at_top(&self) -> bool {
+        self.selected_idx == 0
+    }
+
+    pub fn refresh(&mut self) {
+        let result = self.discovery.disks().and_then(|disks| {
+            let unmounted = self.discovery.unmounted_removable()?;
+            Ok((disks, unmounted))
+        });
+
+        match result {
+            Ok((disks, unmounted)) => {
+                self.disks = disks;
+                self.unmounted = unmounted;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+
+        self.selected_idx = self.selected_idx.min(self.disks.len().saturating_sub(1));
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') if self.selected_idx + 1 < self.disks.len() => {
+                self.selected_idx += 1
+            }
+            KeyCode::Char('k') => self.selected_idx = self.selected_idx.saturating_sub(1),
+            KeyCode::Char('r') => self.refresh(),
+            _ => {}
+        }
+    }
+}
+
+impl Widget for &DiskView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered().title(format!("Disks ({})", self.disks.len()));
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        if let Some(error) = &self.error {
+            Paragraph::new(error.as_str())
+                .style(Style::default().fg(Color::Red))
+                .render(inner_area, buf);
+            return;
+        }
+
+        let unmounted_height = if self.unmounted.is_empty() {
+            0
+        } else {
+            self.unmounted.len() as u16 + 2
+        };
+        let [disks_area, unmounted_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(unmounted_height)])
+                .areas(inner_area);
+
+        let header = Row::new([
+            "Label",
+            "Device",
+            "UUID",
+            "FS",
+            "Mount",
+            "Size",
+            "Free",
+            "Removable",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = self
+            .disks
+            .iter()
+            .map(|disk| {
+                Row::new([
+                    disk.label().to_string(),
+                    disk.device().to_string(),
+                    disk.fs_uuid().unwrap_or("-").to_string(),
+                    disk.fs_type().to_string(),
+                    disk.mount_path().to_string_lossy().to_string(),
+                    format_bytes(disk.total_capacity_bytes()),
+                    format_bytes(disk.free_space_bytes()),
+                    if disk.removable() { "yes" } else { "no" }.to_string(),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(14),
+                Constraint::Percentage(14),
+                Constraint::Percentage(20),
+                Constraint::Percentage(6),
+                Constraint::Percentage(20),
+                Constraint::Percentage(9),
+                Constraint::Percentage(9),
+                Constraint::Percentage(8),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::default().fg(Color::Black).bg(Color::Green));
+
+        let mut state = TableState::default().with_selected(Some(self.selected_idx));
+        StatefulWidget::render(table, disks_area, buf, &mut state);
+
+        if self.unmounted.is_empty() {
+            return;
+        }
+
+        let rows: Vec<Row> = self
+            .unmounted
+            .iter()
+            .map(|device| {
+                Row::new([
+                    device.label.clone().unwrap_or_else(|| "-".to_string()),
+                    format!("/dev/{}", device.name),
+                    device.uuid.clone().unwrap_or_else(|| "-".to_string()),
+                    format_bytes(device.size_bytes),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+                Constraint::Percentage(40),
+                Constraint::Percentage(20),
+            ],
+        )
+        .block(Block::default().title("Removable, not mounted"));
+
+        Widget::render(table, unmounted_area, buf);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{
+    backup::scan::{DirStats, ScanHandle, SizeScan},
+    ui::format_bytes,
+};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, StatefulWidget, Widget},
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone)]
+pub struct BrowserEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+}
+
+/// Miller column file browser: parent directory, current directory and a
+/// preview of the selected directory, navigated with `h/j/k/l`.
+pub struct FileBrowser {
+    cwd: PathBuf,
+    entries: Vec<BrowserEntry>,
+    parent_entries: Vec<BrowserEntry>,
+    preview_entries: Vec<BrowserEntry>,
+    selected_idx: usize,
+    marks: BTreeSet<PathBuf>,
+    show_hidden: bool,
+    sort_by_size: bool,
+    sizes: HashMap<PathBuf, DirStats>,
+    scan: Option<ScanHandle>,
+    error: Option<String>,
+}
+
+impl FileBrowser {
+    pub fn new<P: AsRef<Path>>(cwd: P, show_hidden: bool) -> Self {
+        let mut browser = Self {
+            cwd: cwd.as_ref().to_path_buf(),
+            entries: Vec::new(),
+            parent_entries: Vec::new(),
+            preview_entries: Vec::new(),
+            selected_idx: 0,
+            marks: BTreeSet::new(),
+            show_hidden,
+            sort_by_size: false,
+            sizes: HashMap::new(),
+            scan: None,
+            error: None,
+        };
+        browser.reload();
+        browser
+    }
+
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    pub fn selected(&self) -> Option<&BrowserEntry> {
+        self.entries.get(self.selected_idx)
+    }
+
+    pub fn is_at_top(&self) -> bool {
+        self.selected_idx == 0
+    }
+
+    pub fn marks(&self) -> Vec<PathBuf> {
+        self.marks.iter().cloned().collect()
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marks.clear();
+    }
+
+    pub fn show_hidden(&self) -> bool {
+        self.show_hidden
+    }
+
+    pub fn toggle_hidden(&mut self) {
+        self.show_hidden = !self.show_hidden;
+        let selected = self.selected().map(|entry| entry.path.clone());
+        self.reload();
+        if let Some(selected) = selected {
+            self.select_path(&selected);
+        }
+    }
+
+    /// Sources to scan or back up: the marked paths, or the current
+    /// directory when nothing is marked.
+    pub fn selection(&self) -> Vec<PathBuf> {
+        if self.marks.is_empty() {
+            vec![self.cwd.clone()]
+        } else {
+            self.marks()
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scan.as_ref().is_some_and(|scan| !scan.is_done())
+    }
+
+    pub fn start_scan(&mut self) {
+        self.scan = Some(SizeScan::new(self.selection()).spawn());
+    }
+
+    /// Size of a scanned directory, or of all scanned directories when
+    /// `paths` covers several of them.
+    pub fn scanned_size(&self, paths: &[PathBuf]) -> Option<u64> {
+        paths
+            .iter()
+            .map(|path| {
+                self.sizes.get(path).map(|stats| stats.bytes).or_else(|| {
+                    fs::metadata(path)
+                        .ok()
+                        .filter(|metadata| metadata.is_file())
+                        .map(|metadata| metadata.len())
+                })
+            })
+            .sum()
+    }
+
+    pub fn update(&mut self) {
+        if let Some(scan) = &mut self.scan {
+            scan.update();
+            if let Some(result) = scan.result() {
+                self.sizes.extend(
+                    result
+                        .dirs
+                        .iter()
+                        .map(|(path, stats)| (path.clone(), *stats)),
+                );
+                self.scan = None;
+                self.sort_entries();
+            }
+        }
+    }
+
+    fn size(&self, entry: &BrowserEntry) -> Option<u64> {
+        if entry.is_dir {
+            self.sizes.get(&entry.path).map(|stats| stats.bytes)
+        } else {
+            entry.size
+        }
+    }
+
+    fn sort_entries(&mut self) {
+        let selected = self.selected().map(|entry| entry.path.clone());
+        let mut entries = std::mem::take(&mut self.entries);
+        if self.sort_by_size {
+            entries.sort_by_key(|entry| std::cmp::Reverse(self.size(entry).unwrap_or(0)));
+        } else {
+            entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        }
+        self.entries = entries;
+        if let Some(selected) = selected {
+            self.select_path(&selected);
+        }
+    }
+
+    pub fn reload(&mut self) {
+        match self.read_dir(&self.cwd) {
+            Ok(entries) => {
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(err) => {
+                self.entries = Vec::new();
+                self.error = Some(err.to_string());
+            }
+        }
+
+        self.parent_entries = self
+            .cwd
+            .parent()
+            .and_then(|parent| self.read_dir(parent).ok())
+            .unwrap_or_default();
+
+        self.selected_idx = self.selected_idx.min(self.entries.len().saturating_sub(1));
+        if self.sort_by_size {
+            self.sort_entries();
+        }
+        self.reload_preview();
+    }
+
+    fn reload_preview(&mut self) {
+        self.preview_entries = match self.selected() {
+            Some(entry) if entry.is_dir => self.read_dir(&entry.path).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+    }
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<BrowserEntry>> {
+        let mut entries: Vec<BrowserEntry> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let metadata = fs::metadata(entry.path()).ok();
+                let is_dir = metadata.as_ref().is_some_and(|metadata| metadata.is_dir());
+                BrowserEntry {
+                    path: entry.path(),
+                    name,
+                    is_dir,
+                    size: metadata.filter(|_| !is_dir).map(|metadata| metadata.len()),
+                }
+            })
+            .filter(|entry| self.show_hidden || !entry.name.starts_with('.'))
+            .collect();
+
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(entries)
+    }
+
+    fn select_path(&mut self, path: &Path) {
+        if let Some(idx) = self.entries.iter().position(|entry| entry.path == path) {
+            self.selected_idx = idx;
+            self.reload_preview();
+        }
+    }
+
+    fn enter(&mut self) {
+        if let Some(entry) = self.selected()
+            && entry.is_dir
+        {
+            self.cwd = entry.path.clone();
+            self.selected_idx = 0;
+            self.reload();
+        }
+    }
+
+    fn leave(&mut self) {
+        if let Some(parent) = self.cwd.parent().map(Path::to_path_buf) {
+            let previous = std::mem::replace(&mut self.cwd, parent);
+            self.selected_idx = 0;
+            self.reload();
+            self.select_path(&previous);
+        }
+    }
+
+    fn move_selection(&mut self, idx: usize) {
+        self.selected_idx = idx.min(self.entries.len().saturating_sub(1));
+        self.reload_preview();
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(path) = self.selected().map(|entry| entry.path.clone())
+            && !self.marks.remove(&path)
+        {
+            self.marks.insert(path);
+        }
+        self.move_selection(self.selected_idx + 1);
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.move_selection(self.selected_idx + 1),
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.move_selection(self.selected_idx.saturating_sub(1))
+            }
+            KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => self.enter(),
+            KeyCode::Char('h') | KeyCode::Left | KeyCode::Backspace => self.leave(),
+            KeyCode::Char('g') => self.move_selection(0),
+            KeyCode::Char('G') => self.move_selection(self.entries.len()),
+            KeyCode::Char(' ') | KeyCode::Char('m') => self.toggle_mark(),
+            KeyCode::Char('s') => self.start_scan(),
+            KeyCode::Char('S') => {
+                self.sort_by_size = !self.sort_by_size;
+                self.sort_entries();
+            }
+            _ => {}
+        }
+    }
+
+    fn list<'a>(&self, entries: &'a [BrowserEntry], width: u16) -> List<'a> {
+        let items: Vec<ListItem> = entries
+            .iter()
+            .map(|entry| {
+                let mark = if self.marks.contains(&entry.path) {
+                    "* "
+                } else {
+                    "  "
+                };
+                let name = if entry.is_dir {
+                    format!("{}/", entry.name)
+                } else {
+                    entry.name.clone()
+                };
+                let size = match self.sizes.get(&entry.path) {
+                    Some(stats) if entry.is_dir => {
+                        format!("{} files {:>9}", stats.files, format_bytes(stats.bytes))
+                    }
+                    _ => entry.size.map(format_bytes).unwrap_or_default(),
+                };
+                let padding = (width as usize)
+                    .saturating_sub(mark.len() + name.chars().count() + size.len() + 2);
+
+                let mut style = Style::default();
+                if entry.is_dir {
+                    style = style.fg(Color::Blue);
+                }
+                if self.marks.contains(&entry.path) {
+                    style = style.fg(Color::Yellow);
+                }
+
+                ListItem::new(Line::from(vec![
+                    Span::raw(mark),
+                    Span::styled(name, style),
+                    Span::raw(" ".repeat(padding)),
+                    Span::raw(size),
+                ]))
+            })
+            .collect();
+
+        List::new(items).highlight_style(
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+        )
+    }
+}
+
+impl Widget for &FileBrowser {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut title = format!("{} [{} marked]", self.cwd.display(), self.marks.len());
+        if self.is_scanning() {
+            title.push_str(" [scanning...]");
+        } else if let Some(size) = self.scanned_size(&self.selection()) {
+            title.push_str(&format!(" [{}]", format_bytes(size)));
+        }
+        let block = Block::bordered().title(title);
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let [parent_area, current_area, preview_area] = Layout::horizontal([
+            Constraint::Percentage(20),
+            Constraint::Percentage(45),
+            Constraint::Percentage(35),
+        ])
+        .areas(inner_area);
+
+        let parent_selected = self
+            .parent_entries
+            .iter()
+            .position(|entry| entry.path == self.cwd);
+        StatefulWidget::render(
+            self.list(&self.parent_entries, parent_area.width.saturating_sub(1))
+                .block(Block::default().borders(Borders::RIGHT)),
+            parent_area,
+            buf,
+            &mut ListState::default().with_selected(parent_selected),
+        );
+
+        if let Some(error) = &self.error {
+            Line::styled(error.as_str(), Style::default().fg(Color::Red)).render(current_area, buf);
+        } else {
+            StatefulWidget::render(
+                self.list(&self.entries, current_area.width.saturating_sub(1))
+                    .block(Block::default().borders(Borders::RIGHT)),
+                current_area,
+                buf,
+                &mut ListState::default().with_selected(Some(self.selected_idx)),
+            );
+        }
+
+        Widget::render(
+            self.list(&self.preview_entries, preview_area.width),
+            preview_area,
+            buf,
+        );
+    }
+}
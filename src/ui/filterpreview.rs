@@ -0,0 +1,131 @@
+use crate::{
+    backup::filter::{BackupFilter, FilterPreview},
+    error::SanupResult,
+    ui::format_bytes,
+};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, Paragraph, Widget},
+};
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+enum FilterPreviewState {
+    Running(Receiver<SanupResult<FilterPreview>>),
+    Ready(FilterPreview),
+    Failed(String),
+}
+
+pub struct FilterPreviewView {
+    sources: Vec<PathBuf>,
+    state: FilterPreviewState,
+    scroll: usize,
+}
+
+impl FilterPreviewView {
+    /// Computes the preview in a background thread, walking big source trees
+    /// would otherwise block the UI.
+    pub fn spawn(filter: BackupFilter, sources: Vec<PathBuf>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let thread_sources = sources.clone();
+
+        thread::spawn(move || {
+            let _ = tx.send(filter.preview(&thread_sources));
+        });
+
+        Self {
+            sources,
+            state: FilterPreviewState::Running(rx),
+            scroll: 0,
+        }
+    }
+
+    pub fn update(&mut self) {
+        if let FilterPreviewState::Running(rx) = &self.state
+            && let Ok(result) = rx.try_recv()
+        {
+            self.state = match result {
+                Ok(preview) => FilterPreviewState::Ready(preview),
+                Err(err) => FilterPreviewState::Failed(err.to_string()),
+            };
+        }
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            _ => {}
+        }
+    }
+}
+
+impl Widget for &FilterPreviewView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let sources = self
+            .sources
+            .iter()
+            .map(|source| source.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let block = Block::bordered().title(format!("Filter preview: {}", sources));
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let preview = match &self.state {
+            FilterPreviewState::Running(_) => {
+                Paragraph::new("Scanning...").render(inner_area, buf);
+                return;
+            }
+            FilterPreviewState::Failed(reason) => {
+                Paragraph::new(reason.as_str())
+                    .style(Style::default().fg(Color::Red))
+                    .render(inner_area, buf);
+                return;
+            }
+            FilterPreviewState::Ready(preview) => preview,
+        };
+
+        let [summary_area, matches_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(inner_area);
+
+        Paragraph::new(vec![
+            Line::from(format!(
+                "Included: {} files, {}",
+                preview.included_files,
+                format_bytes(preview.included_bytes)
+            )),
+            Line::from(format!(
+                "Excluded: {} files, {}",
+                preview.excluded_files,
+                format_bytes(preview.excluded_bytes)
+            )),
+        ])
+        .render(summary_area, buf);
+
+        let items: Vec<ListItem> = preview
+            .matches
+            .iter()
+            .skip(self.scroll)
+            .map(|(path, size)| {
+                ListItem::new(format!(
+                    "{:>10}  {:>7} files  {}",
+                    format_bytes(size.bytes),
+                    size.files,
+                    path.display()
+                ))
+            })
+            .collect();
+
+        List::new(items)
+            .block(Block::default().title("Excluded paths"))
+            .render(matches_area, buf);
+    }
+}
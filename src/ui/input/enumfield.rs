@@ -41,10 +41,6 @@ impl EnumField {
     pub fn value(&self) -> &dyn EnumVariants {
         &*self.value
     }
-
-    pub fn to_string(&self) -> String {
-        self.value.to_string()
-    }
 }
 
 impl InputType for EnumField {
@@ -110,7 +106,7 @@ impl Widget for EnumField {
             let height = self.variants.len() as u16 + 2;
             let popup_area = Rect {
                 x: area.x,
-                y: area.y - height,
+                y: area.y.saturating_sub(height),
                 width: self.value.longest().len() as u16 + 4,
                 height,
             };
@@ -167,6 +163,7 @@ where
     }
 }
 
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for EnumField {
     fn to_string(&self) -> String {
         self.value.to_string()
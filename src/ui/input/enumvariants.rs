@@ -1,5 +1,6 @@
 use std::fmt::Debug;
 
+#[allow(clippy::wrong_self_convention)]
 pub trait EnumVariants: ToString + Send + Sync {
     fn default(&self) -> Box<dyn EnumVariants>;
     fn longest(&self) -> String;
@@ -14,6 +15,7 @@ impl Clone for Box<dyn EnumVariants> {
     }
 }
 
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for Box<dyn EnumVariants> {
     fn to_string(&self) -> String {
         (**self).to_string()
@@ -45,7 +47,7 @@ impl EnumVariants for EmptyEnum {
     }
 
     fn from_string(&self, _s: String) -> Box<dyn EnumVariants> {
-        Self::default(&self)
+        Self::default(self)
     }
 
     fn clone_box(&self) -> Box<dyn EnumVariants> {
@@ -53,8 +55,63 @@ impl EnumVariants for EmptyEnum {
     }
 }
 
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for EmptyEnum {
     fn to_string(&self) -> String {
         String::new()
     }
 }
+
+/// One of options only known at runtime, like the disks mounted now.
+#[derive(Clone)]
+pub struct Choice {
+    options: Vec<String>,
+    selected: usize,
+}
+
+impl Choice {
+    pub fn new(options: Vec<String>, selected: &str) -> Self {
+        let selected = options
+            .iter()
+            .position(|option| option == selected)
+            .unwrap_or(0);
+
+        Self { options, selected }
+    }
+}
+
+impl EnumVariants for Choice {
+    fn default(&self) -> Box<dyn EnumVariants> {
+        Box::new(Choice {
+            options: self.options.clone(),
+            selected: 0,
+        })
+    }
+
+    fn longest(&self) -> String {
+        self.options
+            .iter()
+            .max_by_key(|option| option.len())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn variants(&self) -> Vec<String> {
+        self.options.clone()
+    }
+
+    fn from_string(&self, s: String) -> Box<dyn EnumVariants> {
+        Box::new(Choice::new(self.options.clone(), &s))
+    }
+
+    fn clone_box(&self) -> Box<dyn EnumVariants> {
+        Box::new(self.clone())
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
+impl ToString for Choice {
+    fn to_string(&self) -> String {
+        self.options.get(self.selected).cloned().unwrap_or_default()
+    }
+}
@@ -71,9 +71,9 @@ impl<'a> IntoIterator for &'a mut Fields {
     }
 }
 
-impl Into<Fields> for Vec<Field> {
-    fn into(self) -> Fields {
-        Fields(self)
+impl From<Vec<Field>> for Fields {
+    fn from(value: Vec<Field>) -> Self {
+        Fields(value)
     }
 }
 
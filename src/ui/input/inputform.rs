@@ -3,14 +3,15 @@ use crate::ui::{
     input::{
         enumfield::EnumFieldState,
         field::{Field, Fields},
-        value::{Value, Values},
+        value::Values,
     },
 };
 use ratatui::{
     buffer::Buffer,
     crossterm::event::{KeyCode, KeyEvent},
     layout::{Constraint, Layout, Margin, Rect},
-    widgets::{Block, Borders, Widget},
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
 };
 use std::rc::Rc;
 
@@ -27,6 +28,10 @@ pub struct InputForm {
     title: &'static str,
     focus_idx: usize,
     fields: Fields,
+    /// Read-only lines shown above the fields.
+    lines: Vec<String>,
+    /// Why the last submit was not accepted, shown below the fields.
+    error: Option<String>,
     field_areas: Rc<[Rect]>,
     inner_area: Rect,
 }
@@ -38,6 +43,8 @@ impl InputForm {
             title: label,
             focus_idx: 0,
             fields,
+            lines: Vec::new(),
+            error: None,
             field_areas: Rc::new([]),
             inner_area: Rect::ZERO,
         }
@@ -59,6 +66,22 @@ impl InputForm {
         self.fields.values()
     }
 
+    pub fn with_lines(mut self, lines: Vec<String>) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    pub fn with_error(mut self, error: String) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Goes back to editing after a submit that was not accepted.
+    pub fn reject(&mut self, error: String) {
+        self.state = InputFormState::Active;
+        self.error = Some(error);
+    }
+
     fn next_focus(&mut self) {
         self.focus_idx += 1;
         self.focus_idx %= self.fields.len();
@@ -107,10 +130,8 @@ impl InputForm {
                 }
             },
             EnumFieldState::Active => {
-                if let Some(field) = self.fields.get_mut(self.focus_idx) {
-                    if let Field::Enum(f) = field {
-                        f.on_key(key);
-                    }
+                if let Some(Field::Enum(f)) = self.fields.get_mut(self.focus_idx) {
+                    f.on_key(key);
                 }
             }
         }
@@ -141,18 +162,52 @@ impl InputForm {
 
 impl Widget for &mut InputForm {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let height = self.fields.len() as u16 + 3;
+        let error_height = if self.error.is_some() { 1 } else { 0 };
+        let height = self.lines.len() as u16 + self.fields.len() as u16 + error_height + 3;
         let popup_area = centered_rect(area, 50, height);
 
+        Clear.render(popup_area, buf);
+
         let block = Block::default().title(self.title).borders(Borders::ALL);
         block.render(popup_area, buf);
 
         self.inner_area = popup_area.inner(Margin::new(1, 1));
+        let lines_area = Rect {
+            height: (self.lines.len() as u16).min(self.inner_area.height),
+            ..self.inner_area
+        };
+        let fields_area = Rect {
+            y: lines_area.bottom(),
+            height: self.inner_area.height - lines_area.height,
+            ..self.inner_area
+        };
+        for (i, line) in self.lines.iter().enumerate() {
+            Paragraph::new(line.as_str()).render(
+                Rect {
+                    y: lines_area.y + i as u16,
+                    height: 1,
+                    ..lines_area
+                },
+                buf,
+            );
+        }
+
         let constraints: Vec<Constraint> = (0..self.fields.len())
             .map(|_| Constraint::Length(1))
             .collect();
         let layout = Layout::vertical(constraints);
-        self.field_areas = layout.split(self.inner_area);
+        self.field_areas = layout.split(fields_area);
+
+        if let Some(error) = &self.error {
+            let error_area = Rect {
+                y: self.inner_area.bottom().saturating_sub(1),
+                height: 1,
+                ..self.inner_area
+            };
+            Paragraph::new(error.as_str())
+                .style(Style::default().fg(Color::Red))
+                .render(error_area, buf);
+        }
 
         for (i, field) in self.fields.iter().enumerate() {
             let area = self.field_areas[i];
@@ -174,6 +229,8 @@ impl Default for InputForm {
             title: "",
             focus_idx: 0,
             fields: Fields::default(),
+            lines: Vec::new(),
+            error: None,
             field_areas: Rc::new([]),
             inner_area: Rect::ZERO,
         }
@@ -1,14 +1,15 @@
 use crate::ui::input::{
+    enumfield::EnumFieldState,
     field::{Field, Fields},
     value::Values,
 };
 use ratatui::{
     buffer::Buffer,
     crossterm::event::{KeyCode, KeyEvent},
-    layout::Rect,
+    layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     text::Line,
-    widgets::{Block, Borders, List, ListItem, Widget},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Widget},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,7 +17,10 @@ enum InputListState {
     None,
     Active,
     Changed,
+    /// Leaving with changes, asking whether to save or reset them.
     SaveOrReset,
+    Submitted,
+    Reset,
 }
 
 pub struct InputList {
@@ -25,6 +29,8 @@ pub struct InputList {
     fields: Fields,
     focus_idx: usize,
     show_border: bool,
+    /// Outcome of the last save, shown below the fields.
+    notice: Option<String>,
 }
 
 impl InputList {
@@ -35,6 +41,7 @@ impl InputList {
             fields,
             focus_idx: 0,
             show_border,
+            notice: None,
         }
     }
 
@@ -50,13 +57,47 @@ impl InputList {
         self.state == InputListState::SaveOrReset
     }
 
+    pub fn is_submitted(&self) -> bool {
+        self.state == InputListState::Submitted
+    }
+
+    pub fn is_reset(&self) -> bool {
+        self.state == InputListState::Reset
+    }
+
+    /// Whether the list was left without changes to save.
+    pub fn is_left(&self) -> bool {
+        self.state == InputListState::None
+    }
+
+    /// Starts editing, or goes back to editing after a failed save.
+    pub fn activate(&mut self) {
+        self.state = match self.state {
+            InputListState::Submitted | InputListState::SaveOrReset => InputListState::Changed,
+            InputListState::Changed => InputListState::Changed,
+            _ => InputListState::Active,
+        };
+        self.set_focus(self.focus_idx);
+    }
+
+    pub fn set_notice(&mut self, notice: String) {
+        self.notice = Some(notice);
+    }
+
     pub fn values(&self) -> Values {
         self.fields.values()
     }
 
     fn next_focus(&mut self) {
-        self.focus_idx += 1;
-        self.focus_idx %= self.fields.len();
+        self.set_focus((self.focus_idx + 1) % self.fields.len());
+    }
+
+    fn prev_focus(&mut self) {
+        self.set_focus((self.focus_idx + self.fields.len() - 1) % self.fields.len());
+    }
+
+    fn set_focus(&mut self, focus_idx: usize) {
+        self.focus_idx = focus_idx;
 
         for (i, field) in self.fields.iter_mut().enumerate() {
             match field {
@@ -69,23 +110,21 @@ impl InputList {
         }
     }
 
-    fn create_list_items(&self) -> Vec<ListItem> {
+    fn is_enum_active(&self) -> bool {
+        matches!(
+            self.fields.get(self.focus_idx),
+            Some(Field::Enum(f)) if f.field().state() == EnumFieldState::Active
+        )
+    }
+
+    fn create_list_items(&self) -> Vec<ListItem<'_>> {
         self.fields
             .iter()
             .enumerate()
             .map(|(idx, field)| {
-                let (label, value) = match field {
-                    Field::Bool(f) => (f.title(), f.value().to_string()),
-                    Field::Integer(f) => (f.title(), f.value().to_string()),
-                    Field::Float(f) => (f.title(), f.value().to_string()),
-                    Field::String(f) => (f.title(), f.value().clone()),
-                    Field::Enum(f) => (f.title(), f.field().to_string()),
-                };
-
-                let line = Line::from(format!("{}: {}", label, value));
-                let mut item = ListItem::new(line);
-
-                if idx == self.focus_idx {
+                let mut item = ListItem::new(Line::from(field_text(field)));
+
+                if idx == self.focus_idx && self.state != InputListState::None {
                     item = item.style(
                         Style::default()
                             .fg(Color::Green)
@@ -98,66 +137,140 @@ impl InputList {
             .collect()
     }
 
+    /// Tab and the arrow keys move between fields, the other keys edit the
+    /// focused one. Enter or Esc with changes asks to save or reset them,
+    /// Esc without changes leaves the list.
     pub fn on_key(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Esc | KeyCode::Enter => if let InputListState::Changed = self.state {},
-            KeyCode::Tab => {
-                self.next_focus();
+        if self.state == InputListState::SaveOrReset {
+            match key.code {
+                KeyCode::Char('s') => self.state = InputListState::Submitted,
+                KeyCode::Char('r') => self.state = InputListState::Reset,
+                KeyCode::Esc => self.state = InputListState::Changed,
+                _ => {}
             }
-            _ => {
-                if let Some(field) = self.fields.get_mut(self.focus_idx) {
-                    match field {
-                        Field::Bool(f) => f.on_key(key),
-                        Field::Integer(f) => f.on_key(key),
-                        Field::Float(f) => f.on_key(key),
-                        Field::String(f) => f.on_key(key),
-                        Field::Enum(f) => f.on_key(key),
-                    }
-                }
+            return;
+        }
+
+        if self.is_enum_active() {
+            self.edit_field(key);
+            return;
+        }
+
+        match (key.code, self.state) {
+            (KeyCode::Esc | KeyCode::Enter, InputListState::Changed) => {
+                self.state = InputListState::SaveOrReset;
             }
+            (KeyCode::Esc, _) => self.state = InputListState::None,
+            (KeyCode::Enter, _) => {}
+            (KeyCode::Tab | KeyCode::Down, _) => self.next_focus(),
+            (KeyCode::BackTab | KeyCode::Up, _) => self.prev_focus(),
+            _ => self.edit_field(key),
+        }
+    }
+
+    fn edit_field(&mut self, key: KeyEvent) {
+        let Some(field) = self.fields.get_mut(self.focus_idx) else {
+            return;
+        };
+
+        let before = field_text(field);
+        match field {
+            Field::Bool(f) => f.on_key(key),
+            Field::Integer(f) => f.on_key(key),
+            Field::Float(f) => f.on_key(key),
+            Field::String(f) => f.on_key(key),
+            Field::Enum(f) => f.on_key(key),
+        }
+        if field_text(field) != before {
+            self.state = InputListState::Changed;
+            self.notice = None;
+        }
+    }
+
+    fn title(&self) -> Option<String> {
+        let changed = matches!(
+            self.state,
+            InputListState::Changed | InputListState::SaveOrReset
+        );
+        match (&self.title, changed) {
+            (Some(title), true) => Some(format!("{} (modified)", title)),
+            (Some(title), false) => Some(title.clone()),
+            (None, true) => Some("(modified)".to_string()),
+            (None, false) => None,
+        }
+    }
+
+    /// The prompt or notice below the fields, if any.
+    fn status_line(&self) -> Option<Line<'_>> {
+        if self.state == InputListState::SaveOrReset {
+            Some(Line::styled(
+                "Unsaved changes. s: save, r: reset, esc: keep editing",
+                Style::default().fg(Color::Yellow),
+            ))
+        } else {
+            self.notice
+                .as_deref()
+                .map(|notice| Line::styled(notice, Style::default().fg(Color::Yellow)))
         }
     }
 }
 
+fn field_text(field: &Field) -> String {
+    let (label, value) = match field {
+        Field::Bool(f) => (f.title(), f.value().to_string()),
+        Field::Integer(f) => (f.title(), f.value().to_string()),
+        Field::Float(f) => (f.title(), f.value().to_string()),
+        Field::String(f) => (f.title(), f.value().clone()),
+        Field::Enum(f) => (f.title(), f.field().to_string()),
+    };
+
+    format!("{}: {}", label, value)
+}
+
 impl Widget for InputList {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let items = self.create_list_items();
-        let mut list = List::new(items);
-
-        if self.show_border {
-            let block = if let Some(title) = &self.title {
-                Block::bordered().title(title.clone())
-            } else {
-                Block::bordered()
-            };
-            list = list.block(block);
-        } else if let Some(title) = &self.title {
-            let block = Block::default().title(title.clone());
-            list = list.block(block);
-        }
-
-        list.render(area, buf);
+        (&self).render(area, buf);
     }
 }
 
 impl Widget for &InputList {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let items = self.create_list_items();
-        let mut list = List::new(items);
-
-        if self.show_border {
-            let block = if let Some(ref title) = self.title {
-                Block::default().title(title.as_str()).borders(Borders::ALL)
-            } else {
-                Block::default().borders(Borders::ALL)
+        let mut block = if self.show_border {
+            Block::default().borders(Borders::ALL)
+        } else {
+            Block::default()
+        };
+        if let Some(title) = self.title() {
+            block = block.title(title);
+        }
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let status_line = self.status_line();
+        let status_height = if status_line.is_some() { 1 } else { 0 };
+        let [list_area, status_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(status_height)])
+                .areas(inner_area);
+
+        List::new(self.create_list_items()).render(list_area, buf);
+
+        // The variants of an enum being edited pop up over the list.
+        if let Some(Field::Enum(f)) = self.fields.get(self.focus_idx)
+            && self.is_enum_active()
+        {
+            let label_width = f.title().len() as u16 + 2;
+            let field_area = Rect {
+                x: list_area.x + label_width,
+                y: list_area.y + self.focus_idx as u16,
+                width: list_area.width.saturating_sub(label_width),
+                height: 1,
             };
-            list = list.block(block);
-        } else if let Some(ref title) = self.title {
-            let block = Block::default().title(title.as_str());
-            list = list.block(block);
+            f.field().clone().render(field_area, buf);
         }
 
-        list.render(area, buf);
+        if let Some(status_line) = status_line {
+            Paragraph::new(status_line).render(status_area, buf);
+        }
     }
 }
 
@@ -169,6 +282,7 @@ impl Default for InputList {
             fields: Fields::default(),
             focus_idx: 0,
             show_border: false,
+            notice: None,
         }
     }
 }
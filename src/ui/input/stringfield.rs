@@ -53,13 +53,13 @@ impl From<PathBuf> for StringField {
 
 impl From<Option<PathBuf>> for StringField {
     fn from(value: Option<PathBuf>) -> Self {
-        if let Some(value) = value {
-            if let Some(str) = value.to_str() {
-                return StringField {
-                    focus: false,
-                    value: str.to_string(),
-                };
-            }
+        if let Some(value) = value
+            && let Some(str) = value.to_str()
+        {
+            return StringField {
+                focus: false,
+                value: str.to_string(),
+            };
         }
         StringField {
             focus: false,
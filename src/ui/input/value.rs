@@ -15,6 +15,18 @@ pub enum Value {
     Enum(String, Box<dyn EnumVariants>),
 }
 
+impl Value {
+    pub fn title(&self) -> &str {
+        match self {
+            Value::Bool(title, _)
+            | Value::Integer(title, _)
+            | Value::Float(title, _)
+            | Value::String(title, _)
+            | Value::Enum(title, _) => title,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Values(Vec<Value>);
 
@@ -22,6 +34,45 @@ impl Values {
     pub fn new(values: Vec<Value>) -> Self {
         Self(values)
     }
+
+    pub fn get(&self, title: &str) -> Option<&Value> {
+        self.iter().find(|value| value.title() == title)
+    }
+
+    pub fn get_bool(&self, title: &str) -> Option<bool> {
+        match self.get(title) {
+            Some(Value::Bool(_, b)) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn get_integer(&self, title: &str) -> Option<i64> {
+        match self.get(title) {
+            Some(Value::Integer(_, i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn get_float(&self, title: &str) -> Option<f64> {
+        match self.get(title) {
+            Some(Value::Float(_, f)) => Some(*f),
+            _ => None,
+        }
+    }
+
+    pub fn get_string(&self, title: &str) -> Option<String> {
+        match self.get(title) {
+            Some(Value::String(_, s)) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_enum(&self, title: &str) -> Option<String> {
+        match self.get(title) {
+            Some(Value::Enum(_, e)) => Some(e.to_string()),
+            _ => None,
+        }
+    }
 }
 
 impl Serialize for Values {
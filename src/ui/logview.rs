@@ -0,0 +1,206 @@
+use crate::logger::{LogBuffer, LogEntry};
+use log::{Level, LevelFilter};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// The records of the log buffer, narrowed down to a level, a search text
+/// and the backup a task logged them for. Following keeps the newest record
+/// selected as records come in.
+pub struct LogView {
+    buffer: Arc<LogBuffer>,
+    entries: Vec<LogEntry>,
+    rows: Vec<usize>,
+    level: LevelFilter,
+    search: String,
+    /// Id and name of the backup the records are scoped to.
+    task: Option<(Uuid, String)>,
+    follow: bool,
+    selected_idx: usize,
+}
+
+impl LogView {
+    pub fn new(buffer: Arc<LogBuffer>) -> Self {
+        let mut view = Self {
+            buffer,
+            entries: Vec::new(),
+            rows: Vec::new(),
+            level: LevelFilter::Info,
+            search: String::new(),
+            task: None,
+            follow: true,
+            selected_idx: 0,
+        };
+        view.refresh();
+        view
+    }
+
+    /// Takes the records logged since the last refresh.
+    pub fn refresh(&mut self) {
+        let new_entries = self
+            .buffer
+            .since(self.entries.last().map(|entry| entry.seq));
+        if new_entries.is_empty() {
+            return;
+        }
+
+        self.entries.extend(new_entries);
+        let dropped = self.entries.len().saturating_sub(self.buffer.capacity());
+        self.entries.drain(..dropped);
+        self.update_rows();
+    }
+
+    pub fn is_at_top(&self) -> bool {
+        self.selected_idx == 0
+    }
+
+    pub fn search(&self) -> &str {
+        &self.search
+    }
+
+    pub fn set_search(&mut self, search: String) {
+        self.search = search.trim().to_string();
+        self.update_rows();
+    }
+
+    /// Shows only what the tasks of a backup logged.
+    pub fn scope_to(&mut self, id: Uuid, name: String) {
+        self.task = Some((id, name));
+        self.follow = true;
+        self.update_rows();
+    }
+
+    fn update_rows(&mut self) {
+        let search = self.search.to_lowercase();
+
+        self.rows = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.level <= self.level)
+            .filter(|(_, entry)| {
+                self.task
+                    .as_ref()
+                    .is_none_or(|(id, _)| entry.task == Some(*id))
+            })
+            .filter(|(_, entry)| {
+                search.is_empty() || entry.message.to_lowercase().contains(&search)
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+
+        self.selected_idx = if self.follow {
+            self.rows.len().saturating_sub(1)
+        } else {
+            self.selected_idx.min(self.rows.len().saturating_sub(1))
+        };
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') if self.selected_idx + 1 < self.rows.len() => {
+                self.selected_idx += 1;
+            }
+            KeyCode::Char('k') => {
+                self.selected_idx = self.selected_idx.saturating_sub(1);
+                self.follow = false;
+            }
+            KeyCode::Char('g') => {
+                self.selected_idx = 0;
+                self.follow = false;
+            }
+            KeyCode::Char('G') => {
+                self.follow = true;
+                self.update_rows();
+            }
+            KeyCode::Char('f') => {
+                self.follow = !self.follow;
+                self.update_rows();
+            }
+            KeyCode::Char('v') => {
+                self.level = next_level(self.level);
+                self.update_rows();
+            }
+            KeyCode::Char('t') => {
+                self.task = None;
+                self.update_rows();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The next more verbose level, wrapping around to errors only.
+fn next_level(level: LevelFilter) -> LevelFilter {
+    match level {
+        LevelFilter::Off | LevelFilter::Trace => LevelFilter::Error,
+        LevelFilter::Error => LevelFilter::Warn,
+        LevelFilter::Warn => LevelFilter::Info,
+        LevelFilter::Info => LevelFilter::Debug,
+        LevelFilter::Debug => LevelFilter::Trace,
+    }
+}
+
+fn level_color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::Red,
+        Level::Warn => Color::Yellow,
+        Level::Info => Color::Blue,
+        Level::Debug => Color::Green,
+        Level::Trace => Color::Magenta,
+    }
+}
+
+impl Widget for &LogView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut title = format!(
+            "Logs ({}/{}), up to {}",
+            self.rows.len(),
+            self.entries.len(),
+            self.level
+        );
+        if let Some((_, name)) = &self.task {
+            title.push_str(&format!(", task: {}", name));
+        }
+        if !self.search.is_empty() {
+            title.push_str(&format!(", search: {}", self.search));
+        }
+        if self.follow {
+            title.push_str(", following");
+        }
+
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|&idx| {
+                let entry = &self.entries[idx];
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{} ", entry.time.format("%H:%M:%S"))),
+                    Span::styled(
+                        format!("{:<5} ", entry.level),
+                        Style::default().fg(level_color(entry.level)),
+                    ),
+                    Span::raw(entry.message.clone()),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .title_bottom("f: follow, v: level, /: search, t: all tasks, g/G: top/bottom"),
+            )
+            .highlight_style(Style::default().fg(Color::Black).bg(Color::Green));
+
+        let mut state = ListState::default().with_selected(Some(self.selected_idx));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
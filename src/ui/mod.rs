@@ -1,9 +1,14 @@
+pub mod backupview;
+pub mod diskview;
+pub mod filebrowser;
+pub mod filterpreview;
 pub mod input;
+pub mod logview;
+pub mod processview;
 
 use crate::{
-    app::{sanup::Sanup, settings::Settings, tabs::SanupTabs},
+    app::{sanup::Sanup, tabs::SanupTabs},
     error::SanupResult,
-    ui::input::inputlist::InputList,
 };
 use ratatui::{
     Frame, Terminal,
@@ -11,14 +16,21 @@ use ratatui::{
     layout::{Constraint, Layout, Rect},
     prelude::Backend,
     style::{Color, Modifier, Style, Stylize},
-    widgets::{Block, Clear, Tabs},
+    text::Line,
+    widgets::{Block, Tabs},
 };
+use std::time::Duration;
+
+const TICK_RATE: Duration = Duration::from_millis(250);
 
 pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: Sanup) -> SanupResult<()> {
     loop {
+        app.on_tick();
         terminal.draw(|f| ui(f, &mut app))?;
 
-        if let Event::Key(key) = event::read()? {
+        if event::poll(TICK_RATE)?
+            && let Event::Key(key) = event::read()?
+        {
             if let KeyCode::Char('q') = key.code {
                 return Ok(());
             }
@@ -39,21 +51,46 @@ fn ui(f: &mut Frame, app: &mut Sanup) {
         settings_tab(f, app, body_area);
     }
 
+    if app.tabs.is_main() {
+        main_tab(f, app, body_area);
+    }
+
+    if app.tabs.is_processes() {
+        f.render_widget(&app.processes, body_area);
+    }
+
+    if app.tabs.is_backups() {
+        f.render_widget(&app.backups, body_area);
+    }
+
+    if app.tabs.is_disks() {
+        f.render_widget(&app.disks, body_area);
+    }
+
+    if app.tabs.is_logs() {
+        f.render_widget(&app.logs, body_area);
+    }
+
     if app.input_form.is_active() {
         f.render_widget(&mut app.input_form, body_area);
         f.set_cursor_position(app.input_form.cursor_position());
-    } else if app.input_form.is_submitted() {
-        if let Ok(values) = toml::to_string(&app.input_form.values()) {
-            f.render_widget(values, body_area);
-        }
-    } else if app.focus.is_body() {
-        f.render_widget(Clear, body_area);
     }
 }
 
 fn tabs(f: &mut Frame, app: &mut Sanup, tabs_area: Rect) {
+    let mut block = Block::bordered().title(app.title);
+    if let Some(err) = &app.settings_error {
+        block = block.title_bottom(
+            Line::from(format!(
+                "Settings did not load, changes are not saved: {}",
+                err.lines().next().unwrap_or_default()
+            ))
+            .red(),
+        );
+    }
+
     let tabs = Tabs::new(SanupTabs::into_vec_str())
-        .block(Block::bordered().title(app.title))
+        .block(block)
         .style(Style::default().white())
         .highlight_style(
             Style::default()
@@ -67,9 +104,45 @@ fn tabs(f: &mut Frame, app: &mut Sanup, tabs_area: Rect) {
 }
 
 fn settings_tab(f: &mut Frame, app: &mut Sanup, body_area: Rect) {
-    let list = InputList::new(None, true, app.settings.clone().into());
+    f.render_widget(&app.input_list, body_area);
+}
 
-    f.render_widget(list, body_area);
+fn main_tab(f: &mut Frame, app: &mut Sanup, body_area: Rect) {
+    if let Some(filter_preview) = &app.filter_preview {
+        f.render_widget(filter_preview, body_area);
+    } else {
+        f.render_widget(&app.browser, body_area);
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// `1h 02m`, `3m 05s` or `12s`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    if seconds >= 3600 {
+        format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
 }
 
 pub fn centered_rect(parent: Rect, percent_x: u16, height: u16) -> Rect {
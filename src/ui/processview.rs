@@ -0,0 +1,232 @@
+use crate::{
+    backup::status::BackupStatus,
+    daemon::protocol::JobStatus,
+    ui::{format_bytes, format_duration},
+};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{
+        Block, Gauge, List, ListItem, Paragraph, Row, StatefulWidget, Table, TableState, Widget,
+    },
+};
+use uuid::Uuid;
+
+const BAR_WIDTH: usize = 10;
+
+/// Backup jobs of this TUI followed by the ones of the attached daemon.
+#[derive(Default)]
+pub struct ProcessView {
+    jobs: Vec<JobStatus>,
+    attached: bool,
+    selected_idx: usize,
+    /// Whether the files the selected job failed on are listed instead of
+    /// its details.
+    show_errors: bool,
+    /// Outcome of the last pause, resume or cancel, shown below the table.
+    notice: Option<String>,
+}
+
+impl ProcessView {
+    pub fn refresh(&mut self, jobs: Vec<JobStatus>, attached: bool) {
+        self.jobs = jobs;
+        self.attached = attached;
+        self.selected_idx = self.selected_idx.min(self.jobs.len().saturating_sub(1));
+    }
+
+    pub fn selected(&self) -> Option<&JobStatus> {
+        self.jobs.get(self.selected_idx)
+    }
+
+    pub fn selected_id(&self) -> Option<Uuid> {
+        self.selected().map(|job| job.id)
+    }
+
+    pub fn is_at_top(&self) -> bool {
+        self.selected_idx == 0
+    }
+
+    pub fn set_notice(&mut self, notice: String) {
+        self.notice = Some(notice);
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') if self.selected_idx + 1 < self.jobs.len() => self.selected_idx += 1,
+            KeyCode::Char('k') => self.selected_idx = self.selected_idx.saturating_sub(1),
+            KeyCode::Enter => self.show_errors = !self.show_errors,
+            KeyCode::Esc => self.show_errors = false,
+            _ => {}
+        }
+    }
+}
+
+fn status_text(status: &BackupStatus) -> String {
+    match status {
+        BackupStatus::Running { .. } => "Running".to_string(),
+        BackupStatus::Failed { reason } => format!("Failed: {}", reason),
+        status => format!("{:?}", status),
+    }
+}
+
+fn fraction(job: &JobStatus) -> f64 {
+    if job.status == BackupStatus::Completed {
+        1.0
+    } else if job.total_bytes == 0 {
+        0.0
+    } else {
+        (job.done_bytes as f64 / job.total_bytes as f64).min(1.0)
+    }
+}
+
+fn progress_bar(fraction: f64) -> String {
+    let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+    format!(
+        "{}{} {:>3.0}%",
+        "█".repeat(filled),
+        "░".repeat(BAR_WIDTH - filled),
+        fraction * 100.0
+    )
+}
+
+impl Widget for &ProcessView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let title = if self.attached {
+            format!("Jobs ({}, attached to daemon)", self.jobs.len())
+        } else {
+            format!("Jobs ({})", self.jobs.len())
+        };
+        let block = Block::bordered().title(title);
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let notice_height = if self.notice.is_some() { 1 } else { 0 };
+        let [table_area, details_area, notice_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Percentage(40),
+            Constraint::Length(notice_height),
+        ])
+        .areas(inner_area);
+
+        let header = Row::new([
+            "Name", "Status", "Progress", "Speed", "ETA", "Elapsed", "Errors",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows: Vec<Row> = self
+            .jobs
+            .iter()
+            .map(|job| {
+                Row::new([
+                    job.name.clone(),
+                    status_text(&job.status),
+                    progress_bar(fraction(job)),
+                    job.throughput()
+                        .map(|throughput| format!("{}/s", format_bytes(throughput as u64)))
+                        .unwrap_or_default(),
+                    job.eta().map(format_duration).unwrap_or_default(),
+                    format_duration(job.elapsed()),
+                    job.failed_files.len().to_string(),
+                ])
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+                Constraint::Length(BAR_WIDTH as u16 + 5),
+                Constraint::Percentage(12),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
+                Constraint::Percentage(8),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::default().fg(Color::Black).bg(Color::Green));
+
+        let mut state = TableState::default().with_selected(Some(self.selected_idx));
+        StatefulWidget::render(table, table_area, buf, &mut state);
+
+        if let Some(job) = self.selected() {
+            if self.show_errors {
+                render_errors(job, details_area, buf);
+            } else {
+                render_details(job, details_area, buf);
+            }
+        }
+
+        if let Some(notice) = &self.notice {
+            Paragraph::new(notice.as_str())
+                .style(Style::default().fg(Color::Yellow))
+                .render(notice_area, buf);
+        }
+    }
+}
+
+fn render_details(job: &JobStatus, area: Rect, buf: &mut Buffer) {
+    let block = Block::bordered().title(format!(
+        "{} (p: pause, r: resume, c: cancel, enter: errors, L: logs)",
+        job.name
+    ));
+    let inner_area = block.inner(area);
+    block.render(area, buf);
+
+    let [gauge_area, lines_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner_area);
+
+    Gauge::default()
+        .gauge_style(Style::default().fg(Color::Green))
+        .ratio(fraction(job))
+        .label(format!(
+            "{} of {}",
+            format_bytes(job.done_bytes),
+            format_bytes(job.total_bytes)
+        ))
+        .render(gauge_area, buf);
+
+    let current_file = match &job.status {
+        BackupStatus::Running { current_file, .. } => current_file.as_str(),
+        _ => "",
+    };
+    let mut lines = vec![
+        Line::from(format!("Status:  {}", status_text(&job.status))),
+        Line::from(format!("File:    {}", current_file)),
+        Line::from(format!("Target:  {}", job.target_path.display())),
+        Line::from(format!("Errors:  {}", job.failed_files.len())),
+    ];
+    if job.status.is_finished() {
+        lines.push(Line::from(format!(
+            "Copied:  {} files, {}",
+            job.file_count,
+            format_bytes(job.total_size_bytes)
+        )));
+    }
+    Paragraph::new(lines).render(lines_area, buf);
+}
+
+fn render_errors(job: &JobStatus, area: Rect, buf: &mut Buffer) {
+    let block = Block::bordered().title(format!(
+        "{}: {} files failed (esc: back)",
+        job.name,
+        job.failed_files.len()
+    ));
+
+    let items: Vec<ListItem> = job
+        .failed_files
+        .iter()
+        .map(|file| ListItem::new(file.as_str()))
+        .collect();
+
+    Widget::render(
+        List::new(items)
+            .block(block)
+            .style(Style::default().fg(Color::Red)),
+        area,
+        buf,
+    );
+}